@@ -0,0 +1,105 @@
+#ifndef IONWAVE_H
+#define IONWAVE_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdbool.h>
+#include <stddef.h>
+
+// Status codes, one per `IonwaveError` variant plus ABI-level failures.
+typedef enum IonwaveStatus {
+  IONWAVE_STATUS_OK = 0,
+  IONWAVE_STATUS_INVALID_INPUT = 1,
+  IONWAVE_STATUS_SOLVER = 2,
+  IONWAVE_STATUS_CONFIG = 3,
+  IONWAVE_STATUS_IO = 4,
+  IONWAVE_STATUS_DATA_FRAME = 5,
+  IONWAVE_STATUS_NULL_POINTER = 6,
+  IONWAVE_STATUS_BUFFER_TOO_SMALL = 7,
+  IONWAVE_STATUS_PANIC = 8,
+} IonwaveStatus;
+
+// Opaque handle: the model, the config's waypoints and solver options.
+typedef struct IonwaveModel IonwaveModel;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Load a .toml or .json config into a new handle stored in `*out`.
+//
+// # Safety
+// `path` must be a nul-terminated string and `out` a valid pointer.
+IonwaveStatus ionwave_model_load(const char *path, IonwaveModel **out);
+
+// Release a handle; null is ignored.
+//
+// # Safety
+// `model` must come from `ionwave_model_load` and not be used afterwards.
+void ionwave_model_free(IonwaveModel *model);
+
+// # Safety
+// `model` must be null or a live handle.
+size_t ionwave_model_n_electrodes(const IonwaveModel *model);
+
+// number of waypoints listed in the config
+//
+// # Safety
+// `model` must be null or a live handle.
+size_t ionwave_model_n_waypoints(const IonwaveModel *model);
+
+// Electrode name, owned by the handle, or null if `index` is out of range.
+//
+// # Safety
+// `model` must be null or a live handle.
+const char *ionwave_model_electrode_name(const IonwaveModel *model, size_t index);
+
+// Override the config's solver options. A non-finite `voltage_limit`
+// removes the clamp.
+//
+// # Safety
+// `model` must be null or a live handle.
+IonwaveStatus ionwave_model_set_solver(IonwaveModel *model,
+                                       double lambda,
+                                       double voltage_limit,
+                                       size_t iters);
+
+// Solve the config's waypoints into `out` (`n_waypoints * n_electrodes`).
+//
+// # Safety
+// `out` must point to at least `out_len` writable doubles.
+IonwaveStatus ionwave_solve(const IonwaveModel *model, bool left, double *out, size_t out_len);
+
+// Solve caller supplied waypoints: `positions` holds `n` (x, y, z) triples
+// in metres, `omega_axial` `n` target frequencies in rad/s along z.
+// `species` such as "40Ca+" may be null for the config's species (or 171Yb+).
+// Non-finite positions or frequencies that are not positive give
+// IONWAVE_STATUS_INVALID_INPUT.
+//
+// # Safety
+// `positions` must hold `3 * n` doubles, `omega_axial` `n`, `out` `out_len`.
+IonwaveStatus ionwave_solve_waypoints(const IonwaveModel *model,
+                                      const double *positions,
+                                      const double *omega_axial,
+                                      size_t n,
+                                      const char *species,
+                                      bool left,
+                                      double *out,
+                                      size_t out_len);
+
+// Message of the last failed call on this thread, null after a success.
+// Valid until the next ionwave call on the same thread.
+const char *ionwave_last_error(void);
+
+// Static name of a status code. Takes the plain integer so a value outside
+// the enum, e.g. from a newer library, gives "unknown" rather than UB.
+const char *ionwave_status_name(int status);
+
+// crate version, e.g. "0.1.0"
+const char *ionwave_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* IONWAVE_H */
@@ -0,0 +1,95 @@
+// src/analysis.rs
+
+use serde::{Deserialize, Serialize};
+use crate::basis::TrapModel;
+use crate::constraints::build_constraints;
+use crate::dynamics::eigen;
+use crate::types::{IonwaveError, Result, Vec3, Waypoint};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+/// What a waveform actually achieves at one waypoint, frequencies in Hz.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WaypointReport {
+    pub index: usize,
+    pub r: Vec3,
+    pub field: f64,                // |grad phi| at the waypoint, V/m
+    pub axial_hz: f64,             // along the waypoint's axial_dir
+    pub target_axial_hz: f64,
+    pub secular_hz: [f64; 3],      // all three modes, ascending curvature
+    pub radial_hz: [f64; 2],       // the two modes other than the one nearest axial_dir, ascending
+    pub residual: f64,             // |A v - b| of the constraint system
+}
+
+/// Worst case over a whole waveform.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Summary {
+    pub n_waypoints: usize,
+    pub n_electrodes: usize,
+    pub max_field: f64,
+    pub max_axial_dev_hz: f64,
+    pub min_radial_hz: f64,
+    pub max_abs_voltage: f64,
+    pub max_residual: f64,
+}
+
+pub fn analyze_waypoint(model: &TrapModel, index: usize, wp: &Waypoint, v: &[f64]) -> WaypointReport {
+    let g = model.grad_total(wp.r, v);
+    let h = model.hess_total(wp.r, v);
+    let qm = wp.species.charge_to_mass();
+    let axial = (qm * h.quad(wp.axial_dir.unit())).max(0.0).sqrt();
+    let (lambda, modes) = eigen(h);
+    let freq = |ev: f64| (qm * ev).max(0.0).sqrt() / TWO_PI;
+    let mut secular = lambda.map(freq);
+    secular.sort_by(|a, b| a.total_cmp(b));
+    // the axial mode is the one best aligned with axial_dir, whatever its stiffness
+    let u = wp.axial_dir.unit();
+    let ax = (0..3).max_by(|&i, &j| modes[i].dot(u).abs().total_cmp(&modes[j].dot(u).abs())).unwrap();
+    let mut radial: Vec<f64> = (0..3).filter(|&k| k != ax).map(|k| freq(lambda[k])).collect();
+    radial.sort_by(|a, b| a.total_cmp(b));
+
+    // residual in the model's internal units, as the solver saw it
+    let (a, b) = build_constraints(model, wp);
+    let x: Vec<f64> = v.iter().map(|&vi| model.units.voltage_to_internal(vi)).collect();
+    let residual = a.dot(&ndarray::ArrayView1::from(&x[..])) - b;
+
+    WaypointReport {
+        index,
+        r: wp.r,
+        field: g.norm(),
+        axial_hz: axial / TWO_PI,
+        target_axial_hz: wp.omega_axial / TWO_PI,
+        secular_hz: secular,
+        radial_hz: [radial[0], radial[1]],
+        residual: residual.dot(&residual).sqrt(),
+    }
+}
+
+/// re-evaluate a waveform, one row of electrode voltages per waypoint
+pub fn analyze(model: &TrapModel, waypoints: &[Waypoint], volts: &[Vec<f64>]) -> Result<Vec<WaypointReport>> {
+    if volts.len() != waypoints.len() {
+        return Err(IonwaveError::InvalidInput(format!(
+            "{} waveform rows for {} waypoints", volts.len(), waypoints.len())));
+    }
+    for row in volts {
+        if row.len() != model.n_electrodes() {
+            return Err(IonwaveError::InvalidInput(format!(
+                "{} voltages for {} electrodes", row.len(), model.n_electrodes())));
+        }
+    }
+    Ok(waypoints.iter().zip(volts).enumerate()
+        .map(|(i, (wp, v))| analyze_waypoint(model, i, wp, v))
+        .collect())
+}
+
+pub fn summarize(reports: &[WaypointReport], volts: &[Vec<f64>]) -> Summary {
+    Summary {
+        n_waypoints: reports.len(),
+        n_electrodes: volts.first().map_or(0, |r| r.len()),
+        max_field: reports.iter().map(|r| r.field).fold(0.0, f64::max),
+        max_axial_dev_hz: reports.iter().map(|r| (r.axial_hz - r.target_axial_hz).abs()).fold(0.0, f64::max),
+        min_radial_hz: reports.iter().map(|r| r.radial_hz[0]).fold(f64::INFINITY, f64::min),
+        max_abs_voltage: volts.iter().flatten().map(|v| v.abs()).fold(0.0, f64::max),
+        max_residual: reports.iter().map(|r| r.residual).fold(0.0, f64::max),
+    }
+}
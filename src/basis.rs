@@ -1,11 +1,34 @@
 // src/basis.rs
 
-use crate::types::{Vec3, Hess};
+use crate::spec::{BackgroundSpec, BasisSpec, RfDriveSpec};
+use crate::electrode::{default_electrodes, Electrode};
+use crate::species::IonSpecies;
+use crate::types::{Vec3, Hess, IonwaveError, Result};
+use crate::units::Units;
+
+/// Bounding sphere outside of which a basis is small enough to leave out of
+/// the constraint assembly. The potential itself is never truncated.
+#[derive(Clone, Copy, Debug)]
+pub struct Support {
+    pub center: Vec3,
+    pub radius: f64,
+}
+
+impl Support {
+    pub fn contains(&self, r: Vec3) -> bool { (r - self.center).norm() <= self.radius }
+}
 
 pub trait PotentialBasis: Send + Sync {
     fn phi(&self, r: Vec3) -> f64;
     fn grad(&self, r: Vec3) -> Vec3;
     fn hess(&self, r: Vec3) -> Hess;
+    /// region that decides which waypoints assemble this basis, None means all
+    fn support(&self) -> Option<Support> { None }
+    fn influences(&self, r: Vec3) -> bool {
+        self.support().is_none_or(|s| s.contains(r))
+    }
+    /// serializable description, None for bases that cannot be written to a config
+    fn spec(&self) -> Option<BasisSpec> { None }
 }
 
 pub struct RfPseudo { pub kr: f64, pub kz: f64 }
@@ -15,9 +38,11 @@ impl PotentialBasis for RfPseudo {
     fn hess(&self, _r: Vec3) -> Hess {
         Hess { xx: self.kr, yy: self.kr, zz: self.kz, xy: 0.0, xz: 0.0, yz: 0.0 }
     }
+    fn spec(&self) -> Option<BasisSpec> { Some(BasisSpec::RfPseudo { kr: self.kr, kz: self.kz }) }
 }
 
-pub struct GaussianBasis { pub center: Vec3, pub sigma: f64, pub scale: f64 }
+/// `cutoff` is the assembly radius in units of sigma, None assembles the lobe everywhere
+pub struct GaussianBasis { pub center: Vec3, pub sigma: f64, pub scale: f64, pub cutoff: Option<f64> }
 
 impl PotentialBasis for GaussianBasis {
     fn phi(&self, r: Vec3) -> f64 {
@@ -50,33 +75,218 @@ impl PotentialBasis for GaussianBasis {
         let yz = p * ((dy*dz) / s4);
         Hess { xx, yy, zz, xy, xz, yz }
     }
+    fn support(&self) -> Option<Support> {
+        self.cutoff.map(|k| Support { center: self.center, radius: k * self.sigma })
+    }
+    fn spec(&self) -> Option<BasisSpec> {
+        Some(BasisSpec::Gaussian { center: self.center, sigma: self.sigma, scale: self.scale, cutoff: self.cutoff })
+    }
+}
+
+/// φ = ½ (r - c)ᵀ K (r - c), an ideal multipole expansion to second order
+pub struct QuadraticBasis { pub center: Vec3, pub curvature: Hess }
+
+impl PotentialBasis for QuadraticBasis {
+    fn phi(&self, r: Vec3) -> f64 { 0.5 * self.curvature.quad(r - self.center) }
+    fn grad(&self, r: Vec3) -> Vec3 { self.curvature.apply(r - self.center) }
+    fn hess(&self, _r: Vec3) -> Hess { self.curvature }
+    fn spec(&self) -> Option<BasisSpec> { Some(BasisSpec::Quadratic { center: self.center, curvature: self.curvature }) }
+}
+
+/// Stray field about `origin`, φ = -E·(r - o) - ½ (r - o)ᵀ G (r - o), with
+/// E the field in V/m and G its gradient ∂E_i/∂x_j in V/m².
+pub struct StrayFieldBasis { pub field: Vec3, pub gradient: Hess, pub origin: Vec3 }
+
+impl PotentialBasis for StrayFieldBasis {
+    fn phi(&self, r: Vec3) -> f64 {
+        let d = r - self.origin;
+        -self.field.dot(d) - 0.5 * self.gradient.quad(d)
+    }
+    fn grad(&self, r: Vec3) -> Vec3 { (self.field + self.gradient.apply(r - self.origin)) * -1.0 }
+    fn hess(&self, _r: Vec3) -> Hess { self.gradient.scale(-1.0) }
+    fn spec(&self) -> Option<BasisSpec> {
+        Some(BasisSpec::StrayField { field: self.field, gradient: self.gradient, origin: self.origin })
+    }
+}
+
+/// A potential the waveform does not control: a stray field, or a patch or
+/// electrode held at a fixed voltage.
+pub struct Background {
+    pub basis: Box<dyn PotentialBasis>,
+    pub volts: f64,                       // V, 1 for stray fields
+}
+
+impl Background {
+    pub fn spec(&self) -> Option<BackgroundSpec> { Some(BackgroundSpec { volts: self.volts, basis: self.basis.spec()? }) }
+}
+
+/// The RF electrode's actual potential, `amplitude * basis(r) * cos(Ω t)`,
+/// for dynamics beyond the pseudopotential in `TrapModel::rf`.
+pub struct RfDrive {
+    pub basis: Box<dyn PotentialBasis>,   // potential per volt of RF
+    pub amplitude: f64,                   // V
+    pub frequency: f64,                   // Hz
+}
+
+impl RfDrive {
+    pub fn omega(&self) -> f64 { 2.0 * std::f64::consts::PI * self.frequency }
+    /// gradient of the RF potential at its peak, V/m
+    pub fn grad(&self, r: Vec3) -> Vec3 { self.basis.grad(r) * self.amplitude }
+    /// curvature of the RF potential at its peak, V/m²
+    pub fn hess(&self, r: Vec3) -> Hess { self.basis.hess(r).scale(self.amplitude) }
+    /// Curvature of the pseudopotential q |∇φ|² / 4 m Ω² at an RF null,
+    /// where it reduces to q K² / 2 m Ω², for comparison with `TrapModel::rf`.
+    pub fn pseudo_hess(&self, r: Vec3, species: &IonSpecies) -> Hess {
+        let k = self.hess(r);
+        let s = species.charge_to_mass() / (2.0 * self.omega() * self.omega());
+        let (a, b, c) = (
+            k.apply(Vec3 { x: 1.0, y: 0.0, z: 0.0 }),
+            k.apply(Vec3 { x: 0.0, y: 1.0, z: 0.0 }),
+            k.apply(Vec3 { x: 0.0, y: 0.0, z: 1.0 }),
+        );
+        Hess { xx: a.dot(a), yy: b.dot(b), zz: c.dot(c), xy: a.dot(b), xz: a.dot(c), yz: b.dot(c) }.scale(s)
+    }
+    pub fn spec(&self) -> Option<RfDriveSpec> {
+        Some(RfDriveSpec { amplitude: self.amplitude, frequency: self.frequency, basis: self.basis.spec()? })
+    }
 }
 
 pub struct TrapModel {
     pub rf: Box<dyn PotentialBasis>,
     pub dc: Vec<Box<dyn PotentialBasis>>,
     pub c2lr_pair: Option<(usize, usize)>,
+    pub units: Units,
+    pub electrodes: Vec<Electrode>,
+    pub rf_drive: Option<RfDrive>,
+    pub background: Vec<Background>,
 }
 
 impl TrapModel {
     pub fn new(rf: Box<dyn PotentialBasis>, dc: Vec<Box<dyn PotentialBasis>>, c2lr_pair: Option<(usize, usize)>) -> Self {
-        Self { rf, dc, c2lr_pair }
+        let electrodes = default_electrodes(dc.len());
+        Self { rf, dc, c2lr_pair, units: Units::default(), electrodes, rf_drive: None, background: Vec::new() }
+    }
+    pub fn with_units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+    pub fn with_rf_drive(mut self, drive: RfDrive) -> Self {
+        self.rf_drive = Some(drive);
+        self
+    }
+    /// add a fixed background term, seen by every waveform
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background.push(background);
+        self
+    }
+    /// replace the default electrode descriptors, one per dc basis
+    pub fn with_electrodes(mut self, electrodes: Vec<Electrode>) -> Result<Self> {
+        self.set_electrodes(electrodes)?;
+        Ok(self)
+    }
+    /// `with_electrodes` in place; the model is unchanged on error
+    pub fn set_electrodes(&mut self, electrodes: Vec<Electrode>) -> Result<()> {
+        if electrodes.len() != self.dc.len() {
+            return Err(IonwaveError::InvalidInput(format!(
+                "{} electrode descriptors for {} dc bases", electrodes.len(), self.dc.len())));
+        }
+        for e in &electrodes { e.validate()?; }
+        self.electrodes = electrodes;
+        Ok(())
     }
     pub fn n_electrodes(&self) -> usize { self.dc.len() }
-    pub fn grad_total(&self, r: Vec3, v: &[f64]) -> Vec3 {
-        let mut g = self.rf.grad(r);
+    /// error if any voltage breaks its electrode's limits or drives a disabled electrode
+    pub fn check_voltages(&self, v: &[f64]) -> Result<()> {
+        if v.len() != self.n_electrodes() {
+            return Err(IonwaveError::InvalidInput(format!(
+                "{} voltages for {} electrodes", v.len(), self.n_electrodes())));
+        }
+        for (e, &vi) in self.electrodes.iter().zip(v) {
+            if !e.accepts(vi) {
+                return Err(IonwaveError::InvalidInput(format!(
+                    "{} V on electrode {} outside [{}, {}]{}", vi, e.name, e.v_min, e.v_max,
+                    if !e.enabled { " (disabled)" } else if e.dac_channel.is_none() { " (unwired)" } else { "" })));
+            }
+        }
+        Ok(())
+    }
+    /// indices of dc electrodes whose support contains r
+    pub fn active_electrodes(&self, r: Vec3) -> Vec<usize> {
+        (0..self.dc.len()).filter(|&i| self.dc[i].influences(r)).collect()
+    }
+    pub fn phi_total(&self, r: Vec3, v: &[f64]) -> f64 {
+        let mut p = self.rf.phi(r) + self.phi_background(r);
+        for (i, b) in self.dc.iter().enumerate() { p += v[i] * b.phi(r); }
+        p
+    }
+    pub fn grad_total(&self, r: Vec3, v: &[f64]) -> Vec3 { self.add_grad_dc(self.rf.grad(r) + self.grad_background(r), r, v) }
+    pub fn hess_total(&self, r: Vec3, v: &[f64]) -> Hess { self.add_hess_dc(self.rf.hess(r) + self.hess_background(r), r, v) }
+    /// potential of the background terms alone, zero without any
+    pub fn phi_background(&self, r: Vec3) -> f64 {
+        self.background.iter().map(|b| b.volts * b.basis.phi(r)).sum()
+    }
+    pub fn grad_background(&self, r: Vec3) -> Vec3 {
+        self.background.iter().fold(Vec3::ZERO, |g, b| g + b.basis.grad(r) * b.volts)
+    }
+    pub fn hess_background(&self, r: Vec3) -> Hess {
+        self.background.iter().fold(Hess::ZERO, |h, b| h + b.basis.hess(r).scale(b.volts))
+    }
+    /// gradient of the dc electrodes alone, without the RF pseudopotential
+    pub fn grad_dc(&self, r: Vec3, v: &[f64]) -> Vec3 { self.add_grad_dc(Vec3::ZERO, r, v) }
+    pub fn hess_dc(&self, r: Vec3, v: &[f64]) -> Hess { self.add_hess_dc(Hess::ZERO, r, v) }
+    fn add_grad_dc(&self, mut g: Vec3, r: Vec3, v: &[f64]) -> Vec3 {
         for (i, b) in self.dc.iter().enumerate() {
             let gi = b.grad(r);
             g.x += v[i]*gi.x; g.y += v[i]*gi.y; g.z += v[i]*gi.z;
         }
         g
     }
-    pub fn hess_total(&self, r: Vec3, v: &[f64]) -> Hess {
-        let mut h = self.rf.hess(r);
+    fn add_hess_dc(&self, mut h: Hess, r: Vec3, v: &[f64]) -> Hess {
         for (i, b) in self.dc.iter().enumerate() {
             let hi = b.hess(r).scale(v[i]);
-            h = h.add(hi);
+            h = h + hi;
         }
         h
     }
+    /// Stable fingerprint of the geometry and electrode wiring. Bases without
+    /// a spec only contribute their position in the list.
+    pub fn geometry_hash(&self) -> String {
+        let describe = |b: &dyn PotentialBasis| serde_json::to_value(b.spec()).unwrap_or_default();
+        // json has no infinities and serde writes both as null, so name them
+        let limit = |v: f64| if v.is_finite() { serde_json::json!(v) } else { serde_json::json!(if v > 0.0 { "inf" } else { "-inf" }) };
+        let electrodes: Vec<_> = self.electrodes.iter().map(|e| serde_json::json!({
+            "name": e.name, "dac_channel": e.dac_channel, "v_min": limit(e.v_min), "v_max": limit(e.v_max), "enabled": e.enabled,
+        })).collect();
+        let desc = serde_json::json!({
+            "rf": describe(self.rf.as_ref()),
+            "dc": self.dc.iter().map(|b| describe(b.as_ref())).collect::<Vec<_>>(),
+            "electrodes": electrodes,
+            "c2lr_pair": self.c2lr_pair,
+            "units": self.units,
+        });
+        // only present when set, so hashes of pseudopotential-only models are unchanged
+        let desc = match &self.rf_drive {
+            Some(d) => {
+                let mut desc = desc;
+                desc["rf_drive"] = serde_json::json!({
+                    "basis": describe(d.basis.as_ref()), "amplitude": d.amplitude, "frequency": d.frequency,
+                });
+                desc
+            }
+            None => desc,
+        };
+        let desc = if self.background.is_empty() { desc } else {
+            let mut desc = desc;
+            desc["background"] = self.background.iter()
+                .map(|b| serde_json::json!({ "basis": describe(b.basis.as_ref()), "volts": b.volts }))
+                .collect();
+            desc
+        };
+        format!("{:016x}", fnv1a64(desc.to_string().as_bytes()))
+    }
+}
+
+// 64 bit FNV-1a, stable across platforms and releases unlike DefaultHasher
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325u64, |h, &b| (h ^ b as u64).wrapping_mul(0x100000001b3))
 }
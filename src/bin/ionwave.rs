@@ -0,0 +1,848 @@
+use std::path::{Path, PathBuf};
+use clap::{Parser, Subcommand, ValueEnum};
+use polars::prelude::{NamedFrom, Series};
+use serde::Serialize;
+use ionwave::analysis::{analyze, summarize, Summary, WaypointReport};
+use ionwave::basis::TrapModel;
+use ionwave::c2lr::solve_channels;
+use ionwave::channels::{ChannelMap, ChannelWaveform};
+use ionwave::compensation::{apply_compensation, compensate};
+use ionwave::config::Config;
+use ionwave::control::{optimize_transport, ControlOptions};
+use ionwave::dac::{quantization_report, quantize, DacImageFormat};
+use ionwave::excitation::{candidate_profiles, transport_excitation, ExcitationOptions};
+use ionwave::filter::{predistort, FilterError, PredistortOptions};
+use ionwave::io::{diagnostics_frame, read_model_csv, read_waveform, write_channel_csv, write_dac_image, write_frame, write_model_csv, write_parquet, write_piecewise_csv, write_streams_npz, write_streams_raw, write_waveform, write_waveform_npz, Playback, WaveformMetadata};
+use ionwave::lsq::LsqOptions;
+use ionwave::mathieu::rf_stability;
+use ionwave::noise::heating_rates;
+use ionwave::piecewise::{fit_piecewise, PiecewiseOptions};
+use ionwave::simulate::{simulate, Integrator, SimulationOptions};
+use ionwave::spline::SplineKind;
+use ionwave::timing::{uniform_times, TimedWaveform};
+use ionwave::trajectory::{TransportPath, Trajectory, VelocityProfile};
+use ionwave::types::{IonwaveError, Vec3, Waypoint};
+
+#[derive(Parser)]
+#[command(name = "ionwave", version, about = "Transport waveforms for segmented Paul traps")]
+struct Cli {
+    /// print summaries as JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// overrides for the `[solver]` table of the config
+#[derive(clap::Args)]
+struct SolverArgs {
+    #[arg(long)]
+    lambda: Option<f64>,
+    #[arg(long)]
+    voltage_limit: Option<f64>,
+    #[arg(long)]
+    iters: Option<usize>,
+    /// solve the mirrored (left) C2LR segment
+    #[arg(long)]
+    left: bool,
+}
+
+impl SolverArgs {
+    fn apply(&self, base: &LsqOptions) -> LsqOptions {
+        let mut opts = base.clone();
+        if let Some(l) = self.lambda { opts.lambda = l; }
+        if let Some(v) = self.voltage_limit { opts.voltage_limit = Some(v); }
+        if let Some(n) = self.iters { opts.iters = n; }
+        opts
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// solve the config's waypoints and write electrode and channel waveforms
+    Solve {
+        config: PathBuf,
+        #[arg(short, long, default_value = "target/out")]
+        out: PathBuf,
+        /// also write per-waypoint diagnostics to diagnostics.parquet
+        #[arg(long)]
+        parquet: bool,
+        /// give every line the velocity profile leaving the fewest phonons
+        /// for a transport of this many seconds
+        #[arg(long, value_name = "DURATION")]
+        min_excitation: Option<f64>,
+        /// move every line's trap centres along an invariant-based shortcut
+        /// lasting this many seconds, leaving the ion in its ground state
+        #[arg(long, value_name = "DURATION", conflicts_with = "min_excitation")]
+        shortcut: Option<f64>,
+        #[command(flatten)]
+        solver: SolverArgs,
+    },
+    /// re-evaluate fields and frequencies of an existing waveform
+    Analyze {
+        config: PathBuf,
+        waveform: PathBuf,
+        /// also print one report per waypoint
+        #[arg(long)]
+        per_waypoint: bool,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// convert an electrode waveform into a hardware format
+    Export {
+        config: PathBuf,
+        waveform: PathBuf,
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::ChannelCsv)]
+        format: ExportFormat,
+        #[arg(short, long)]
+        out: PathBuf,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// spread the waypoints evenly over a duration and sample every channel at a fixed rate
+    Resample {
+        config: PathBuf,
+        waveform: PathBuf,
+        /// transport duration in seconds
+        #[arg(long)]
+        duration: f64,
+        /// DAC update rate in samples per second
+        #[arg(long)]
+        rate: f64,
+        #[arg(long, value_enum, default_value_t = Interpolation::Monotone)]
+        interpolation: Interpolation,
+        /// an .npz file, or a directory for one raw little-endian f64 file per channel
+        #[arg(short, long)]
+        out: PathBuf,
+        /// pre-distort the samples against the config's `[filter]` so the electrodes see the waveform
+        #[arg(long)]
+        predistort: bool,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// fit each channel with piecewise cubics and write fixed point segment coefficients
+    Splines {
+        config: PathBuf,
+        waveform: PathBuf,
+        /// transport duration in seconds
+        #[arg(long)]
+        duration: f64,
+        /// sequencer clock, knot times are whole ticks of it
+        #[arg(long, default_value_t = 50e6)]
+        clock: f64,
+        /// largest allowed deviation in volts
+        #[arg(long, default_value_t = 1e-4)]
+        tolerance: f64,
+        #[arg(long, default_value_t = 24)]
+        frac_bits: u32,
+        #[arg(long, value_enum, default_value_t = Interpolation::Monotone)]
+        interpolation: Interpolation,
+        /// segment table as csv
+        #[arg(short, long)]
+        out: PathBuf,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// integrate the motion of one ion through a waveform spread over a duration
+    Simulate {
+        config: PathBuf,
+        waveform: PathBuf,
+        /// transport duration in seconds
+        #[arg(long)]
+        duration: f64,
+        #[arg(long, value_enum, default_value_t = Integration::Verlet)]
+        integrator: Integration,
+        /// integration steps per period of the fastest secular mode
+        #[arg(long, default_value_t = 40.0)]
+        steps_per_period: f64,
+        #[arg(long, value_enum, default_value_t = Interpolation::Monotone)]
+        interpolation: Interpolation,
+        /// integrate in the explicit RF field of `[trap.rf_drive]` instead of the pseudopotential
+        #[arg(long)]
+        explicit_rf: bool,
+        /// write the ion and well positions of every step as csv
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// Mathieu a/q parameters, RF stability and excess micromotion at every waypoint
+    Stability {
+        config: PathBuf,
+        waveform: PathBuf,
+        /// print every waypoint rather than the worst case
+        #[arg(long)]
+        per_waypoint: bool,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// estimate the phonons a waveform spread over a duration leaves in the axial mode
+    Excitation {
+        config: PathBuf,
+        waveform: PathBuf,
+        /// transport duration in seconds
+        #[arg(long)]
+        duration: f64,
+        #[arg(long, value_enum, default_value_t = Interpolation::Monotone)]
+        interpolation: Interpolation,
+        /// write the well position, frequency and running phonon number as csv
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// dc offsets that move the ion onto the RF null at every waypoint
+    Compensate {
+        config: PathBuf,
+        waveform: PathBuf,
+        /// measured stray field in V/m as x,y,z
+        #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+        stray_field: Vec<f64>,
+        /// write the waveform with the offsets added
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// write the offsets alone, one row per waypoint, to add to another waveform
+        #[arg(long)]
+        offsets: Option<PathBuf>,
+        /// print every waypoint, with its field vectors, rather than the worst case
+        #[arg(long)]
+        per_waypoint: bool,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// axial heating rate at every waypoint from the `[noise]` of the DAC channels
+    Heating {
+        config: PathBuf,
+        waveform: PathBuf,
+        /// print every waypoint, with each channel's share, rather than the worst case
+        #[arg(long)]
+        per_waypoint: bool,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// reshape a waveform spread over a duration to leave the least simulated excitation
+    Optimize {
+        config: PathBuf,
+        waveform: PathBuf,
+        /// transport duration in seconds
+        #[arg(long)]
+        duration: f64,
+        #[arg(long, default_value_t = 200)]
+        iterations: usize,
+        /// integration steps per period of the fastest secular mode
+        #[arg(long, default_value_t = 40.0)]
+        steps_per_period: f64,
+        /// largest slope of any channel in V/s
+        #[arg(long)]
+        slew_limit: Option<f64>,
+        /// write the optimised waveform, to be played through natural splines
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// read a bare csv: skip the metadata sidecar and its geometry check
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// solve repeatedly while scanning one parameter
+    Sweep {
+        config: PathBuf,
+        #[arg(short, long, value_enum)]
+        param: SweepParam,
+        /// comma separated values
+        #[arg(long, value_delimiter = ',', required = true)]
+        values: Vec<f64>,
+        /// write the summary table as csv
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// write per-waypoint diagnostics of every sweep point as <dir>/<param>_<i>.parquet
+        #[arg(long)]
+        parquet_dir: Option<PathBuf>,
+        #[command(flatten)]
+        solver: SolverArgs,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// one column per DAC channel, ganged electrodes collapsed
+    ChannelCsv,
+    /// one column per electrode, checked against electrode limits
+    ElectrodeCsv,
+    /// per-waypoint diagnostics table
+    Parquet,
+    /// numpy archive of voltages and waypoint arrays
+    Npz,
+    /// binary DAC code image, needs a `[dac]` table in the config
+    DacBin,
+    /// hex DAC code image for `$readmemh`, needs a `[dac]` table in the config
+    DacHex,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Interpolation {
+    /// C2 natural cubic spline, may overshoot between waypoints
+    Natural,
+    /// shape preserving cubic, stays within neighbouring waypoint voltages
+    Monotone,
+}
+
+impl Interpolation {
+    fn kind(self) -> SplineKind {
+        match self {
+            Interpolation::Natural => SplineKind::Natural,
+            Interpolation::Monotone => SplineKind::Monotone,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Integration {
+    /// fixed step velocity Verlet
+    Verlet,
+    /// adaptive Dormand-Prince
+    Rk45,
+}
+
+impl Integration {
+    fn integrator(self) -> Integrator {
+        match self {
+            Integration::Verlet => Integrator::Verlet,
+            Integration::Rk45 => Integrator::Rk45,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+enum SweepParam {
+    Lambda,
+    VoltageLimit,
+    /// multiplies every waypoint's target axial frequency
+    OmegaScale,
+}
+
+impl SweepParam {
+    fn name(self) -> &'static str {
+        match self {
+            SweepParam::Lambda => "lambda",
+            SweepParam::VoltageLimit => "voltage_limit",
+            SweepParam::OmegaScale => "omega_scale",
+        }
+    }
+}
+
+fn path_str(p: &Path) -> anyhow::Result<&str> {
+    p.to_str().ok_or_else(|| anyhow::anyhow!("non utf-8 path {}", p.display()))
+}
+
+fn load(config: &Path) -> anyhow::Result<(Config, TrapModel, Vec<Waypoint>)> {
+    let cfg = Config::load(config)?;
+    let (model, wps) = cfg.build()?;
+    if wps.is_empty() { return Err(IonwaveError::InvalidInput("config has no waypoints".to_string()).into()); }
+    Ok((cfg, model, wps))
+}
+
+/// Load a waveform csv, checking its sidecar against the model unless
+/// `no_verify`, in which case the config's waypoints are used.
+fn load_waveform(model: &TrapModel, wps: Vec<Waypoint>, path: &Path, no_verify: bool)
+    -> anyhow::Result<(Vec<Vec<f64>>, Vec<Waypoint>)> {
+    let (volts, meta) = load_waveform_meta(model, &wps, &LsqOptions::default(), path, no_verify)?;
+    Ok((volts, meta.waypoints))
+}
+
+/// As `load_waveform`, keeping the sidecar to write along with a derived
+/// waveform; without one, fresh metadata from the config.
+fn load_waveform_meta(model: &TrapModel, wps: &[Waypoint], solver: &LsqOptions, path: &Path, no_verify: bool)
+    -> anyhow::Result<(Vec<Vec<f64>>, WaveformMetadata)> {
+    let path = path_str(path)?;
+    if no_verify { return Ok((read_model_csv(path, model)?, WaveformMetadata::new(model, wps, solver, false))); }
+    Ok(read_waveform(path, model)?)
+}
+
+/// channel voltages of an electrode waveform, which must already agree on
+/// ganged electrodes and hold unwired ones at 0 V
+fn channel_waveform(model: &TrapModel, volts: Vec<Vec<f64>>) -> anyhow::Result<ChannelWaveform> {
+    let map = ChannelMap::from_model(model)?;
+    let wf = ChannelWaveform {
+        channels: map.channels.clone(),
+        channel_volts: volts.iter().map(|v| map.collapse(v)).collect(),
+        electrode_volts: volts,
+    };
+    for (vc, ve) in wf.channel_volts.iter().zip(&wf.electrode_volts) {
+        if map.expand(vc) != *ve {
+            anyhow::bail!("waveform drives ganged or unwired electrodes inconsistently");
+        }
+    }
+    Ok(wf)
+}
+
+/// channel voltages of a waveform spread evenly over `duration`
+fn timed_channels(model: &TrapModel, volts: &[Vec<f64>], duration: f64) -> anyhow::Result<TimedWaveform> {
+    let wf = channel_waveform(model, volts.to_vec())?;
+    Ok(TimedWaveform::new(uniform_times(volts.len(), duration), wf.channels, wf.channel_volts)?)
+}
+
+fn print_summary<T: Serialize>(json: bool, value: &T) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        // flat key: value listing of the same structure
+        if let serde_json::Value::Object(map) = serde_json::to_value(value)? {
+            for (k, v) in map { println!("{:<18} {}", k, v); }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct SolveOutput<'a> {
+    summary: &'a Summary,
+    electrode_csv: String,
+    channel_csv: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    profiles: Vec<ProfileChoice>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    shortcuts: Vec<ShortcutLine>,
+}
+
+#[derive(Serialize)]
+struct ProfileChoice {
+    line: usize,
+    profile: VelocityProfile,
+    mean_phonons: f64,
+}
+
+/// Replace each line's profile with the one exciting least over `duration`,
+/// scored by `transport_excitation` on the waveform solved for it, so that
+/// the choice sees what the electrodes can actually do. Profiles whose
+/// waveform loses confinement are passed over.
+fn choose_profiles(cfg: &mut Config, model: &TrapModel, left: bool, opts: &LsqOptions, duration: f64)
+    -> anyhow::Result<Vec<ProfileChoice>> {
+    let mut out = Vec::with_capacity(cfg.lines.len());
+    for (i, line) in cfg.lines.iter_mut().enumerate() {
+        let mut best: Option<(VelocityProfile, f64)> = None;
+        for profile in candidate_profiles() {
+            line.profile = profile;
+            let wps = line.waypoints();
+            let scored = solve_channels(model, &wps, left, opts).map_err(anyhow::Error::from)
+                .and_then(|wf| timed_channels(model, &wf.electrode_volts, duration))
+                .and_then(|timed| Ok(transport_excitation(model, &wps, &timed, &ExcitationOptions::default())?));
+            if let Ok(report) = scored {
+                if best.is_none_or(|(_, b)| report.mean_phonons < b) { best = Some((profile, report.mean_phonons)); }
+            }
+        }
+        let (profile, mean_phonons) = best.ok_or_else(|| anyhow::anyhow!("line {}: no velocity profile keeps the ion confined", i))?;
+        line.profile = profile;
+        out.push(ProfileChoice { line: i, profile, mean_phonons });
+    }
+    Ok(out)
+}
+
+#[derive(Serialize)]
+struct ShortcutLine {
+    line: usize,
+    max_lead: f64,    // m, of the trap centre ahead of the ion
+}
+
+/// the config's waypoints with each line replaced by the trap centres of a
+/// shortcut lasting `duration`
+fn shortcut_waypoints(cfg: &Config, duration: f64) -> anyhow::Result<(Vec<Waypoint>, Vec<ShortcutLine>)> {
+    let mut wps = cfg.waypoints.clone();
+    let mut out = Vec::with_capacity(cfg.lines.len());
+    for (i, line) in cfg.lines.iter().enumerate() {
+        let path = TransportPath::line(line.start, line.end)?;
+        let traj = Trajectory::shortcut(&path, duration, line.n, line.omega_axial, line.species)?;
+        wps.extend(traj.waypoints.iter().map(|wp| Waypoint { axial_dir: line.axial_dir, ..*wp }));
+        out.push(ShortcutLine { line: i, max_lead: traj.max_lead() });
+    }
+    Ok((wps, out))
+}
+
+#[derive(Serialize)]
+struct SweepRow {
+    param: SweepParam,
+    value: f64,
+    #[serde(flatten)]
+    summary: Summary,
+}
+
+fn solve_and_summarize(model: &TrapModel, wps: &[Waypoint], left: bool, opts: &LsqOptions)
+    -> anyhow::Result<(ChannelWaveform, Vec<WaypointReport>, Summary)> {
+    let wf = solve_channels(model, wps, left, opts)?;
+    let reports = analyze(model, wps, &wf.electrode_volts)?;
+    let summary = summarize(&reports, &wf.electrode_volts);
+    Ok((wf, reports, summary))
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Solve { config, out, parquet, min_excitation, shortcut, solver } => {
+            let (mut cfg, model, mut wps) = load(&config)?;
+            let opts = solver.apply(&cfg.solver);
+            let profiles = match min_excitation {
+                Some(duration) => {
+                    let choices = choose_profiles(&mut cfg, &model, solver.left, &opts, duration)?;
+                    wps = cfg.all_waypoints();
+                    choices
+                }
+                None => Vec::new(),
+            };
+            let shortcuts = match shortcut {
+                Some(duration) => {
+                    let (centres, lines) = shortcut_waypoints(&cfg, duration)?;
+                    wps = centres;
+                    lines
+                }
+                None => Vec::new(),
+            };
+            let (wf, _, summary) = solve_and_summarize(&model, &wps, solver.left, &opts)?;
+            let electrode_csv = out.join("waveforms.csv");
+            let channel_csv = out.join("channels.csv");
+            let meta = WaveformMetadata::new(&model, &wps, &opts, solver.left);
+            write_waveform(path_str(&electrode_csv)?, &model, &wf.electrode_volts, &meta)?;
+            write_channel_csv(path_str(&channel_csv)?, &wf)?;
+            if parquet {
+                let path = out.join("diagnostics.parquet");
+                write_parquet(path_str(&path)?, &model, &wps, &wf.electrode_volts, None)?;
+                if !cli.json { println!("wrote {}", path.display()); }
+            }
+            if cli.json {
+                let output = SolveOutput {
+                    summary: &summary,
+                    electrode_csv: electrode_csv.display().to_string(),
+                    channel_csv: channel_csv.display().to_string(),
+                    profiles,
+                    shortcuts,
+                };
+                print_summary(true, &output)?;
+            } else {
+                for p in &profiles {
+                    println!("line {}: {:?}, {:.3e} phonons", p.line, p.profile, p.mean_phonons);
+                }
+                for l in &shortcuts {
+                    println!("line {}: shortcut, trap centre up to {:.3} um ahead", l.line, l.max_lead * 1e6);
+                }
+                print_summary(false, &summary)?;
+                println!("wrote {} and {}", electrode_csv.display(), channel_csv.display());
+            }
+        }
+        Command::Analyze { config, waveform, per_waypoint, no_verify } => {
+            let (_, model, wps) = load(&config)?;
+            let (volts, wps) = load_waveform(&model, wps, &waveform, no_verify)?;
+            let reports = analyze(&model, &wps, &volts)?;
+            let summary = summarize(&reports, &volts);
+            if per_waypoint {
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&reports)?);
+                } else {
+                    println!("{:>4} {:>12} {:>12} {:>12} {:>12}", "wp", "z [um]", "axial [kHz]", "field [V/m]", "residual");
+                    for r in &reports {
+                        println!("{:>4} {:>12.3} {:>12.3} {:>12.3e} {:>12.3e}",
+                            r.index, r.r.z * 1e6, r.axial_hz / 1e3, r.field, r.residual);
+                    }
+                }
+            }
+            print_summary(cli.json, &summary)?;
+        }
+        Command::Export { config, waveform, format, out, no_verify } => {
+            let (cfg, model, wps) = load(&config)?;
+            let (volts, wps) = load_waveform(&model, wps, &waveform, no_verify)?;
+            let rows = volts.len();
+            match format {
+                ExportFormat::ChannelCsv => write_channel_csv(path_str(&out)?, &channel_waveform(&model, volts)?)?,
+                ExportFormat::ElectrodeCsv => write_model_csv(path_str(&out)?, &model, &volts)?,
+                ExportFormat::Parquet => write_parquet(path_str(&out)?, &model, &wps, &volts, None)?,
+                ExportFormat::Npz => write_waveform_npz(path_str(&out)?, &model, &wps, &volts)?,
+                ExportFormat::DacBin | ExportFormat::DacHex => {
+                    let dac = cfg.dac.as_ref().ok_or_else(|| anyhow::anyhow!("config has no [dac] table"))?;
+                    let wf = channel_waveform(&model, volts)?;
+                    let codes = quantize(&wf, dac)?;
+                    let image = if matches!(format, ExportFormat::DacBin) { DacImageFormat::Binary } else { DacImageFormat::Hex };
+                    write_dac_image(path_str(&out)?, &codes, image)?;
+                    print_summary(cli.json, &quantization_report(&model, &wps, &wf, &codes, dac)?)?;
+                }
+            }
+            if !cli.json {
+                println!("wrote {}", out.display());
+            } else if !matches!(format, ExportFormat::DacBin | ExportFormat::DacHex) {
+                // the DAC formats have printed their quantization report instead
+                print_summary(true, &serde_json::json!({ "out": path_str(&out)?, "rows": rows }))?;
+            }
+        }
+        Command::Resample { config, waveform, duration, rate, interpolation, out, predistort: pre, no_verify } => {
+            let (cfg, model, wps) = load(&config)?;
+            let (volts, wps) = load_waveform(&model, wps, &waveform, no_verify)?;
+            let timed = timed_channels(&model, &volts, duration)?;
+            let streams = if pre {
+                let filters = cfg.filter.as_ref().ok_or_else(|| anyhow::anyhow!("config has no [filter] table"))?;
+                let opts = PredistortOptions { kind: interpolation.kind(), voltage_limit: cfg.solver.voltage_limit, ..Default::default() };
+                let p = predistort(&model, &wps, &timed, rate, filters, &opts)?;
+                #[derive(Serialize)]
+                struct PredistortSummary { uncorrected: FilterError, corrected: FilterError, limited_samples: usize }
+                print_summary(cli.json, &PredistortSummary { uncorrected: p.uncorrected, corrected: p.corrected, limited_samples: p.limited })?;
+                p.dac
+            } else {
+                timed.resample(rate, interpolation.kind())?
+            };
+            if out.extension().is_some_and(|e| e == "npz") {
+                write_streams_npz(path_str(&out)?, &streams)?;
+            } else {
+                write_streams_raw(path_str(&out)?, &streams)?;
+            }
+            if !cli.json {
+                println!("wrote {} samples x {} channels to {}", streams.n_samples(), streams.channels.len(), out.display());
+            }
+        }
+        Command::Splines { config, waveform, duration, clock, tolerance, frac_bits, interpolation, out, no_verify } => {
+            let (_, model, wps) = load(&config)?;
+            let (volts, wps) = load_waveform(&model, wps, &waveform, no_verify)?;
+            let timed = timed_channels(&model, &volts, duration)?;
+            let opts = PiecewiseOptions { kind: interpolation.kind(), tolerance, clock_hz: clock, frac_bits, ..Default::default() };
+            let export = fit_piecewise(&model, &wps, &timed, &opts)?;
+            write_piecewise_csv(path_str(&out)?, &export)?;
+            #[derive(Serialize)]
+            struct SplineSummary { segments: usize, max_error_volts: f64, max_axial_error_hz: f64 }
+            print_summary(cli.json, &SplineSummary {
+                segments: export.n_segments(),
+                max_error_volts: export.max_error_volts,
+                max_axial_error_hz: export.max_axial_error_hz,
+            })?;
+            if !cli.json { println!("wrote {}", out.display()); }
+        }
+        Command::Simulate { config, waveform, duration, integrator, steps_per_period, interpolation, explicit_rf, out, no_verify } => {
+            let (_, model, wps) = load(&config)?;
+            let (volts, wps) = load_waveform(&model, wps, &waveform, no_verify)?;
+            let timed = timed_channels(&model, &volts, duration)?;
+            let opts = SimulationOptions {
+                integrator: integrator.integrator(),
+                kind: interpolation.kind(),
+                steps_per_period,
+                explicit_rf,
+                ..Default::default()
+            };
+            let sim = simulate(&model, &wps, &timed, &opts)?;
+            #[derive(Serialize)]
+            struct SimulationSummary { steps: usize, max_lag: f64, final_lag: f64, final_kinetic: f64, final_energy: f64, energy_gain: f64, final_quanta: f64 }
+            print_summary(cli.json, &SimulationSummary {
+                steps: sim.steps,
+                max_lag: sim.max_lag,
+                final_lag: sim.final_lag,
+                final_kinetic: sim.final_kinetic,
+                final_energy: sim.final_energy,
+                energy_gain: sim.energy_gain,
+                final_quanta: sim.final_quanta,
+            })?;
+            if let Some(out) = out {
+                let rows: Vec<Vec<f64>> = sim.samples.iter().map(|s| vec![
+                    s.t, s.r.x, s.r.y, s.r.z, s.v.x, s.v.y, s.v.z, s.well.x, s.well.y, s.well.z, s.energy,
+                ]).collect();
+                ionwave::io::write_table_csv(path_str(&out)?,
+                    &["t", "x", "y", "z", "vx", "vy", "vz", "well_x", "well_y", "well_z", "energy"], &rows)?;
+                if !cli.json { println!("wrote {}", out.display()); }
+            }
+        }
+        Command::Stability { config, waveform, per_waypoint, no_verify } => {
+            let (_, model, wps) = load(&config)?;
+            let (volts, wps) = load_waveform(&model, wps, &waveform, no_verify)?;
+            let reports = rf_stability(&model, &wps, &volts)?;
+            if per_waypoint {
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&reports)?);
+                } else {
+                    println!("{:>4} {:>10} {:>30} {:>30} {:>10} {:>14}", "wp", "z [um]", "a", "q", "margin", "micromotion [nm]");
+                    for r in &reports {
+                        let a = r.modes.map(|m| format!("{:.3e}", m.a)).join(" ");
+                        let q = r.modes.map(|m| format!("{:.3e}", m.q)).join(" ");
+                        let margin = r.modes.iter().map(|m| m.margin).fold(f64::INFINITY, f64::min);
+                        println!("{:>4} {:>10.3} {:>30} {:>30} {:>10.4} {:>14.3}",
+                            r.index, r.r.z * 1e6, a, q, margin, r.micromotion_amplitude * 1e9);
+                    }
+                }
+            } else {
+                #[derive(Serialize)]
+                struct StabilitySummary { stable: bool, min_margin: f64, max_abs_q: f64, max_rf_field: f64, max_micromotion_amplitude: f64 }
+                let modes = || reports.iter().flat_map(|r| r.modes.iter());
+                print_summary(cli.json, &StabilitySummary {
+                    stable: reports.iter().all(|r| r.stable),
+                    min_margin: modes().map(|m| m.margin).fold(f64::INFINITY, f64::min),
+                    max_abs_q: modes().map(|m| m.q.abs()).fold(0.0, f64::max),
+                    max_rf_field: reports.iter().map(|r| r.rf_field).fold(0.0, f64::max),
+                    max_micromotion_amplitude: reports.iter().map(|r| r.micromotion_amplitude).fold(0.0, f64::max),
+                })?;
+            }
+        }
+        Command::Excitation { config, waveform, duration, interpolation, out, no_verify } => {
+            let (_, model, wps) = load(&config)?;
+            let (volts, wps) = load_waveform(&model, wps, &waveform, no_verify)?;
+            let timed = timed_channels(&model, &volts, duration)?;
+            let opts = ExcitationOptions { kind: interpolation.kind(), ..Default::default() };
+            let report = transport_excitation(&model, &wps, &timed, &opts)?;
+            #[derive(Serialize)]
+            struct ExcitationSummary { mean_phonons: f64, alpha: [f64; 2], min_axial_hz: f64, max_axial_hz: f64 }
+            print_summary(cli.json, &ExcitationSummary {
+                mean_phonons: report.mean_phonons,
+                alpha: report.alpha,
+                min_axial_hz: report.min_axial_hz,
+                max_axial_hz: report.max_axial_hz,
+            })?;
+            if let Some(out) = out {
+                let rows: Vec<Vec<f64>> = report.samples.iter().map(|s| vec![s.t, s.position, s.axial_hz, s.phonons]).collect();
+                ionwave::io::write_table_csv(path_str(&out)?, &["t", "position", "axial_hz", "phonons"], &rows)?;
+                if !cli.json { println!("wrote {}", out.display()); }
+            }
+        }
+        Command::Compensate { config, waveform, stray_field, out, offsets, per_waypoint, no_verify } => {
+            let (cfg, model, wps) = load(&config)?;
+            let stray = match stray_field.as_slice() {
+                [] => Vec::new(),
+                &[x, y, z] => vec![Vec3 { x, y, z }],
+                _ => anyhow::bail!("--stray-field takes three components x,y,z"),
+            };
+            let (volts, meta) = load_waveform_meta(&model, &wps, &cfg.solver, &waveform, no_verify)?;
+            let comps = compensate(&model, &meta.waypoints, &volts, &stray, &cfg.solver)?;
+            if let Some(out) = out {
+                write_waveform(path_str(&out)?, &model, &apply_compensation(&volts, &comps), &meta)?;
+                if !cli.json { println!("wrote {}", out.display()); }
+            }
+            if let Some(path) = offsets {
+                let rows: Vec<Vec<f64>> = comps.iter().map(|c| c.offset.clone()).collect();
+                write_model_csv(path_str(&path)?, &model, &rows)?;
+                if !cli.json { println!("wrote {}", path.display()); }
+            }
+            if per_waypoint {
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&comps)?);
+                } else {
+                    println!("{:>4} {:>10} {:>16} {:>16} {:>14} {:>14}", "wp", "z [um]", "before [nm]", "after [nm]", "residual [V/m]", "max |dV| [mV]");
+                    for c in &comps {
+                        let dv = c.offset.iter().fold(0.0_f64, |m, v| m.max(v.abs()));
+                        println!("{:>4} {:>10.3} {:>16.4} {:>16.4} {:>14.3e} {:>14.4}",
+                            c.index, c.rf_null.z * 1e6, c.micromotion_before * 1e9, c.micromotion_after * 1e9, c.residual_field, dv * 1e3);
+                    }
+                }
+            } else {
+                #[derive(Serialize)]
+                struct CompensationSummary {
+                    max_micromotion_before: f64,
+                    max_micromotion_after: f64,
+                    max_residual_field: f64,
+                    max_abs_offset: f64,
+                }
+                print_summary(cli.json, &CompensationSummary {
+                    max_micromotion_before: comps.iter().map(|c| c.micromotion_before).fold(0.0, f64::max),
+                    max_micromotion_after: comps.iter().map(|c| c.micromotion_after).fold(0.0, f64::max),
+                    max_residual_field: comps.iter().map(|c| c.residual_field).fold(0.0, f64::max),
+                    max_abs_offset: comps.iter().flat_map(|c| c.offset.iter()).fold(0.0, |m, v| m.max(v.abs())),
+                })?;
+            }
+        }
+        Command::Heating { config, waveform, per_waypoint, no_verify } => {
+            let (cfg, model, wps) = load(&config)?;
+            let noise = cfg.noise.as_ref().ok_or_else(|| anyhow::anyhow!("config has no [noise] table"))?;
+            let (volts, wps) = load_waveform(&model, wps, &waveform, no_verify)?;
+            let reports = heating_rates(&model, &wps, &volts, noise, cfg.filter.as_ref())?;
+            if per_waypoint {
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&reports)?);
+                } else {
+                    println!("{:>4} {:>10} {:>12} {:>16} {:>14}", "wp", "z [um]", "axial [kHz]", "S_E [V²/m²/Hz]", "rate [q/s]");
+                    for r in &reports {
+                        println!("{:>4} {:>10.3} {:>12.3} {:>16.3e} {:>14.3e}",
+                            r.index, r.r.z * 1e6, r.axial_hz / 1e3, r.field_psd, r.heating_rate);
+                    }
+                }
+            } else {
+                let channels = ChannelMap::from_model(&model)?.channels;
+                let totals: Vec<f64> = (0..channels.len()).map(|c| reports.iter().map(|r| r.channel_rates[c]).sum()).collect();
+                let noisiest = totals.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).map(|(c, _)| channels[c]);
+                #[derive(Serialize)]
+                struct HeatingSummary { max_heating_rate: f64, mean_heating_rate: f64, max_field_psd: f64, noisiest_channel: Option<usize> }
+                print_summary(cli.json, &HeatingSummary {
+                    max_heating_rate: reports.iter().map(|r| r.heating_rate).fold(0.0, f64::max),
+                    mean_heating_rate: reports.iter().map(|r| r.heating_rate).sum::<f64>() / reports.len() as f64,
+                    max_field_psd: reports.iter().map(|r| r.field_psd).fold(0.0, f64::max),
+                    noisiest_channel: noisiest,
+                })?;
+            }
+        }
+        Command::Optimize { config, waveform, duration, iterations, steps_per_period, slew_limit, out, no_verify } => {
+            let (cfg, model, wps) = load(&config)?;
+            let (volts, meta) = load_waveform_meta(&model, &wps, &cfg.solver, &waveform, no_verify)?;
+            let timed = timed_channels(&model, &volts, duration)?;
+            let opts = ControlOptions {
+                steps_per_period,
+                iterations,
+                voltage_limit: cfg.solver.voltage_limit,
+                slew_limit,
+                ..Default::default()
+            };
+            let result = optimize_transport(&model, &meta.waypoints, &timed, &opts)?;
+            if let Some(out) = out {
+                let map = ChannelMap::from_model(&model)?;
+                let electrode_volts: Vec<Vec<f64>> = result.timed.volts.iter().map(|v| map.expand(v)).collect();
+                // the rows only hold their gain played back as they were optimised
+                let meta = WaveformMetadata { playback: Some(Playback { kind: SplineKind::Natural, duration }), ..meta };
+                write_waveform(path_str(&out)?, &model, &electrode_volts, &meta)?;
+                if !cli.json { println!("wrote {}", out.display()); }
+            }
+            #[derive(Serialize)]
+            struct ControlSummary { initial_quanta: f64, final_quanta: f64, iterations: usize, evaluations: usize, max_slew: f64 }
+            print_summary(cli.json, &ControlSummary {
+                initial_quanta: result.initial_quanta,
+                final_quanta: result.final_quanta,
+                iterations: result.iterations,
+                evaluations: result.evaluations,
+                max_slew: result.max_slew,
+            })?;
+        }
+        Command::Sweep { config, param, values, out, parquet_dir, solver } => {
+            let (cfg, model, wps) = load(&config)?;
+            let base = solver.apply(&cfg.solver);
+            let mut rows = Vec::with_capacity(values.len());
+            for (i, &value) in values.iter().enumerate() {
+                let mut opts = base.clone();
+                let mut points = wps.clone();
+                match param {
+                    SweepParam::Lambda => opts.lambda = value,
+                    SweepParam::VoltageLimit => opts.voltage_limit = Some(value),
+                    SweepParam::OmegaScale => points.iter_mut().for_each(|wp| wp.omega_axial *= value),
+                }
+                let (wf, _, summary) = solve_and_summarize(&model, &points, solver.left, &opts)?;
+                if let Some(dir) = &parquet_dir {
+                    let mut df = diagnostics_frame(&model, &points, &wf.electrode_volts, None)?;
+                    df.with_column(Series::new("sweep_value", vec![value; points.len()]))?;
+                    write_frame(path_str(&dir.join(format!("{}_{}.parquet", param.name(), i)))?, &mut df)?;
+                }
+                rows.push(SweepRow { param, value, summary });
+            }
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+            } else {
+                println!("{:>12} {:>16} {:>14} {:>12}", "value", "max dev [kHz]", "max field", "max |V|");
+                for r in &rows {
+                    println!("{:>12.4e} {:>16.3} {:>14.3e} {:>12.3}",
+                        r.value, r.summary.max_axial_dev_hz / 1e3, r.summary.max_field, r.summary.max_abs_voltage);
+                }
+            }
+            if let Some(out) = out {
+                let table: Vec<Vec<f64>> = rows.iter().map(|r| vec![
+                    r.value, r.summary.max_axial_dev_hz, r.summary.max_field,
+                    r.summary.min_radial_hz, r.summary.max_abs_voltage, r.summary.max_residual,
+                ]).collect();
+                ionwave::io::write_table_csv(path_str(&out)?,
+                    &["value", "max_axial_dev_hz", "max_field", "min_radial_hz", "max_abs_voltage", "max_residual"], &table)?;
+                if !cli.json { println!("wrote {}", out.display()); }
+            }
+        }
+    }
+    Ok(())
+}
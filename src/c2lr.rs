@@ -1,33 +1,54 @@
 use crate::basis::TrapModel;
-use crate::constraints::build_constraints;
-use crate::lsq::{tikhonov, LsqOptions};
+use crate::channels::{ChannelMap, ChannelWaveform};
+use crate::constraints::build_constraints_grouped;
+use crate::lsq::{tikhonov_bounded, LsqOptions};
 use crate::types::{Waypoint, Result, IonwaveError};
 use rayon::prelude::*;
 
 pub fn solve_waveform(
     model: &TrapModel,
     waypoints: &[Waypoint],
-    q_charge: f64,
-    mass: f64,
     left: bool,
     opts: &LsqOptions,
 ) -> Result<Vec<Vec<f64>>> {
+    Ok(solve_channels(model, waypoints, left, opts)?.electrode_volts)
+}
+
+/// Solve for one voltage per DAC channel, ganged electrodes sharing their
+/// channel's voltage, and expand the result back to electrodes.
+pub fn solve_channels(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    left: bool,
+    opts: &LsqOptions,
+) -> Result<ChannelWaveform> {
     let n_el = model.n_electrodes();
     if n_el == 0 { return Err(IonwaveError::InvalidInput("no electrodes".to_string())); }
+    let map = ChannelMap::from_model(model)?;
+    if map.n_channels() == 0 { return Err(IonwaveError::InvalidInput("no driven channels".to_string())); }
     let pair = model.c2lr_pair.unwrap_or((0, 0));
+    let swap = if left { Some(pair) } else { None };
+    let units = model.units;
+    let opts_int = opts.to_internal(&units);
+    let bounds = map.clamped_bounds(opts.voltage_limit);
+    if let Some(c) = bounds.iter().position(|(lo, hi)| lo > hi) {
+        return Err(IonwaveError::InvalidInput(format!(
+            "channel {} limits [{}, {}] lie outside the voltage limit", map.channels[c], map.bounds[c].0, map.bounds[c].1)));
+    }
+    let bounds_int: Vec<(f64, f64)> = bounds.iter()
+        .map(|&(l, h)| (units.voltage_to_internal(l), units.voltage_to_internal(h)))
+        .collect();
 
-    let sols: Vec<Vec<f64>> = waypoints.par_iter().map(|wp| {
-        let (mut a, b) = build_constraints(model, wp, q_charge, mass);
-        if left {
-            let (i, j) = pair;
-            for row in 0..a.nrows() {
-    let tmp = a[[row, i]];
-    a[[row, i]] = a[[row, j]];
-    a[[row, j]] = tmp;
-}
-        }
-        tikhonov(&a, &b, opts)
+    let channel_volts: Vec<Vec<f64>> = waypoints.par_iter().map(|wp| {
+        // only channels reaching the waypoint enter the solve
+        let local = build_constraints_grouped(model, wp, &map.members, swap);
+        let (lo, hi): (Vec<f64>, Vec<f64>) = local.cols.iter().map(|&c| bounds_int[c]).unzip();
+        let x = tikhonov_bounded(&local.a, &local.b, &lo, &hi, &opts_int);
+        let v = local.scatter(&x, &bounds_int);
+        // the round trip through internal units may step just past a bound
+        v.into_iter().zip(&bounds).map(|(vi, &(l, h))| units.voltage_to_si(vi).clamp(l, h)).collect()
     }).collect();
 
-    Ok(sols)
+    let electrode_volts = channel_volts.iter().map(|vc| map.expand(vc)).collect();
+    Ok(ChannelWaveform { channels: map.channels, channel_volts, electrode_volts })
 }
@@ -0,0 +1,78 @@
+// src/channels.rs
+
+use std::collections::BTreeMap;
+use crate::basis::TrapModel;
+use crate::types::{IonwaveError, Result};
+
+/// Groups electrodes by DAC channel. Electrodes sharing a channel are ganged
+/// and always carry the same voltage; disabled electrodes and electrodes
+/// without a channel are held at 0 V and belong to no group.
+#[derive(Clone, Debug)]
+pub struct ChannelMap {
+    pub channels: Vec<usize>,       // DAC channel ids, ascending
+    pub members: Vec<Vec<usize>>,   // electrode indices per channel
+    pub bounds: Vec<(f64, f64)>,    // intersection of member limits
+    n_electrodes: usize,
+}
+
+impl ChannelMap {
+    pub fn from_model(model: &TrapModel) -> Result<Self> {
+        let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (j, e) in model.electrodes.iter().enumerate() {
+            if let (true, Some(ch)) = (e.enabled, e.dac_channel) {
+                groups.entry(ch).or_default().push(j);
+            }
+        }
+        let mut map = ChannelMap {
+            channels: Vec::with_capacity(groups.len()),
+            members: Vec::with_capacity(groups.len()),
+            bounds: Vec::with_capacity(groups.len()),
+            n_electrodes: model.n_electrodes(),
+        };
+        for (ch, members) in groups {
+            let lo = members.iter().map(|&j| model.electrodes[j].v_min).fold(f64::NEG_INFINITY, f64::max);
+            let hi = members.iter().map(|&j| model.electrodes[j].v_max).fold(f64::INFINITY, f64::min);
+            if lo > hi {
+                return Err(IonwaveError::InvalidInput(format!(
+                    "electrodes tied to channel {} have disjoint limits", ch)));
+            }
+            map.channels.push(ch);
+            map.members.push(members);
+            map.bounds.push((lo, hi));
+        }
+        Ok(map)
+    }
+
+    pub fn n_channels(&self) -> usize { self.channels.len() }
+
+    /// channel bounds intersected with an optional symmetric clamp
+    pub fn clamped_bounds(&self, voltage_limit: Option<f64>) -> Vec<(f64, f64)> {
+        self.bounds.iter().map(|&(lo, hi)| match voltage_limit {
+            Some(l) => (lo.max(-l), hi.min(l)),
+            None => (lo, hi),
+        }).collect()
+    }
+
+    /// channel voltages from electrode voltages, taking each channel's first member
+    pub fn collapse(&self, electrode_volts: &[f64]) -> Vec<f64> {
+        self.members.iter().map(|m| electrode_volts[m[0]]).collect()
+    }
+
+    /// electrode voltages from channel voltages
+    pub fn expand(&self, channel_volts: &[f64]) -> Vec<f64> {
+        let mut v = vec![0.0; self.n_electrodes];
+        for (members, &vc) in self.members.iter().zip(channel_volts) {
+            for &j in members { v[j] = vc; }
+        }
+        v
+    }
+}
+
+/// A solved waveform at both the DAC channel and the electrode level,
+/// one row per waypoint.
+#[derive(Clone, Debug)]
+pub struct ChannelWaveform {
+    pub channels: Vec<usize>,
+    pub channel_volts: Vec<Vec<f64>>,
+    pub electrode_volts: Vec<Vec<f64>>,
+}
@@ -0,0 +1,159 @@
+// src/compensation.rs
+//
+// Excess micromotion compensation. The ion is pushed back onto the RF null,
+// where the RF field of the explicit drive vanishes, by dc offsets solved per
+// waypoint: their field cancels what holds the ion off the null (the dc well,
+// the model's background and any measured stray field), while they add no
+// axial curvature so the confinement set by the waveform is kept.
+
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use sprs::TriMat;
+use crate::basis::{RfDrive, TrapModel};
+use crate::channels::ChannelMap;
+use crate::dynamics::eigen;
+use crate::constraints::W_AX;
+use crate::lsq::{tikhonov_bounded, LsqOptions};
+use crate::simulate::equilibrium_in_field;
+use crate::types::{IonwaveError, Result, Vec3, Waypoint};
+
+// λ of the offset solve per squared column norm: the waveform's λ would swamp
+// these four rows, this one only keeps unreachable directions bounded
+const LAMBDA_REL: f64 = 1e-12;
+
+/// Point of least RF field nearest `guess`, by Gauss-Newton on the RF
+/// gradient. Directions the RF curvature does not confine (the axis of a
+/// linear trap) are left alone, so the null is the nearest point of a null
+/// line.
+pub fn rf_null(drive: &RfDrive, guess: Vec3) -> Result<Vec3> {
+    let mut r = guess;
+    for _ in 0..50 {
+        let (lambda, axes) = eigen(drive.hess(r));
+        let cutoff = 1e-6 * lambda.iter().fold(0.0_f64, |m, l| m.max(l.abs()));
+        if cutoff == 0.0 { return Err(IonwaveError::Solver(format!("no RF curvature at {:?}", r))); }
+        let g = drive.grad(r);
+        let step = lambda.iter().zip(axes).filter(|(l, _)| l.abs() > cutoff)
+            .fold(Vec3::ZERO, |s, (l, e)| s + e * (e.dot(g) / l));
+        r = r - step;
+        if step.norm() <= 1e-15 + 1e-12 * r.norm() { return Ok(r); }
+    }
+    Err(IonwaveError::Solver(format!("no RF null found near {:?}", guess)))
+}
+
+/// Micromotion compensation at one waypoint.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Compensation {
+    pub index: usize,
+    pub rf_null: Vec3,
+    pub offset: Vec<f64>,                // V per electrode, to add to the waveform row
+    pub field_vectors: [Vec<f64>; 3],    // V per electrode for 1 V/m along x, y, z at the null
+    pub residual_field: f64,             // V/m, net field left on the ion at the null
+    pub micromotion_before: f64,         // m, excess micromotion amplitude Q E / m Ω²
+    pub micromotion_after: f64,          // m
+}
+
+/// Offsets moving the ion onto the RF null at every waypoint of a waveform,
+/// the ion seeing the stray field `stray` (V/m) on top of the model's own
+/// background: none, one for all waypoints or one per waypoint. Offsets stay
+/// within each channel's limits once added to the waveform. Needs the model's
+/// explicit `rf_drive`. `opts` gives the voltage limit and the LSQR settings;
+/// λ is taken from the size of the offset system itself.
+pub fn compensate(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    volts: &[Vec<f64>],
+    stray: &[Vec3],
+    opts: &LsqOptions,
+) -> Result<Vec<Compensation>> {
+    let drive = model.rf_drive.as_ref()
+        .ok_or_else(|| IonwaveError::InvalidInput("the model has no explicit RF drive".to_string()))?;
+    if volts.len() != waypoints.len() {
+        return Err(IonwaveError::InvalidInput(format!("{} waveform rows for {} waypoints", volts.len(), waypoints.len())));
+    }
+    if stray.len() > 1 && stray.len() != waypoints.len() {
+        return Err(IonwaveError::InvalidInput(format!("{} stray fields for {} waypoints", stray.len(), waypoints.len())));
+    }
+    let map = ChannelMap::from_model(model)?;
+    let units = model.units;
+    let opts_int = opts.to_internal(&units);
+    let bounds = map.clamped_bounds(opts.voltage_limit);
+    let omega = drive.omega();
+
+    waypoints.iter().zip(volts).enumerate().map(|(index, (wp, v))| {
+        if v.len() != model.n_electrodes() {
+            return Err(IonwaveError::InvalidInput(format!("{} voltages for {} electrodes", v.len(), model.n_electrodes())));
+        }
+        let field = stray.get(if stray.len() == 1 { 0 } else { index }).copied().unwrap_or(Vec3::ZERO);
+        let qm = wp.species.charge_to_mass().abs();
+        let micromotion = |r: Vec3| qm * drive.grad(r).norm() / (omega * omega);
+        let before = equilibrium_in_field(model, v, field, wp.r)?;
+        let null = rf_null(drive, before)?;
+
+        // 3 gradient rows and the axial curvature, per internal voltage unit
+        let axis = wp.axial_dir.unit();
+        let cols: Vec<usize> = (0..map.n_channels())
+            .filter(|&c| map.members[c].iter().any(|&j| model.dc[j].influences(null)))
+            .collect();
+        let mut tri = TriMat::new((4, cols.len()));
+        let mut norm2 = 0.0_f64;
+        for (k, &c) in cols.iter().enumerate() {
+            let mut col = [0.0; 4];
+            for &j in map.members[c].iter().filter(|&&j| model.dc[j].influences(null)) {
+                let g = model.dc[j].grad(null) * (units.voltage / units.field());
+                let h = model.dc[j].hess(null).quad(axis) * (units.weight_scale() * units.voltage / units.curvature());
+                for (a, b) in col.iter_mut().zip([g.x, g.y, g.z, W_AX * h]) { *a += b; }
+            }
+            for (row, a) in col.iter().enumerate() {
+                if *a != 0.0 { tri.add_triplet(row, k, *a); }
+            }
+            norm2 = norm2.max(col.iter().map(|a| a * a).sum());
+        }
+        let a = tri.to_csr();
+        let opts_int = LsqOptions { lambda: LAMBDA_REL * norm2, ..opts_int.clone() };
+        // offsets within [lo, hi] (V) per column
+        let solve = |target: Vec3, lo: &[f64], hi: &[f64]| {
+            let t = target / units.field();
+            let to_int = |v: &[f64]| -> Vec<f64> { v.iter().map(|&x| units.voltage_to_internal(x)).collect() };
+            let x = tikhonov_bounded(&a, &Array1::from(vec![t.x, t.y, t.z, 0.0]), &to_int(lo), &to_int(hi), &opts_int);
+            // the round trip through internal units may step just past a bound
+            let vc: Vec<f64> = x.into_iter().zip(lo.iter().zip(hi))
+                .map(|(xi, (&l, &h))| units.voltage_to_si(xi).clamp(l, h))
+                .collect();
+            let mut full = vec![0.0; map.n_channels()];
+            for (k, &c) in cols.iter().enumerate() { full[c] = vc[k]; }
+            map.expand(&full)
+        };
+
+        // the offset takes the channel's present voltage off its limits
+        let present = map.collapse(v);
+        let (lo, hi): (Vec<f64>, Vec<f64>) = cols.iter().map(|&c| {
+            let (l, h) = bounds[c];
+            (l - present[c], h - present[c])
+        }).unzip();
+        let offset = solve(field - model.grad_total(null, v), &lo, &hi);
+        // field E = -∇φ, so 1 V/m along an axis needs a gradient of -1 V/m
+        let (free_lo, free_hi) = (vec![f64::NEG_INFINITY; cols.len()], vec![f64::INFINITY; cols.len()]);
+        let field_vectors = [
+            Vec3 { x: -1.0, y: 0.0, z: 0.0 },
+            Vec3 { x: 0.0, y: -1.0, z: 0.0 },
+            Vec3 { x: 0.0, y: 0.0, z: -1.0 },
+        ].map(|g| solve(g, &free_lo, &free_hi));
+
+        let compensated: Vec<f64> = v.iter().zip(&offset).map(|(a, b)| a + b).collect();
+        let after = equilibrium_in_field(model, &compensated, field, null)?;
+        Ok(Compensation {
+            index,
+            rf_null: null,
+            residual_field: (model.grad_total(null, &compensated) - field).norm(),
+            micromotion_before: micromotion(before),
+            micromotion_after: micromotion(after),
+            offset,
+            field_vectors,
+        })
+    }).collect()
+}
+
+/// A waveform with each waypoint's compensation offset added.
+pub fn apply_compensation(volts: &[Vec<f64>], comps: &[Compensation]) -> Vec<Vec<f64>> {
+    volts.iter().zip(comps).map(|(v, c)| v.iter().zip(&c.offset).map(|(a, b)| a + b).collect()).collect()
+}
@@ -0,0 +1,307 @@
+// src/config.rs
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::basis::{Background, GaussianBasis, PotentialBasis, QuadraticBasis, RfDrive, RfPseudo, StrayFieldBasis, TrapModel};
+use crate::dac::DacConfig;
+use crate::filter::FilterConfig;
+use crate::noise::NoiseConfig;
+use crate::electrode::Electrode;
+use crate::lsq::LsqOptions;
+use crate::species::IonSpecies;
+use crate::trajectory::VelocityProfile;
+use crate::types::{Hess, Result, Vec3, Waypoint};
+use crate::units::Units;
+use crate::spec::{context, finite_vec, invalid, positive};
+pub use crate::spec::{BackgroundSpec, BasisSpec, RfDriveSpec};
+
+impl BasisSpec {
+    pub fn build(&self) -> Result<Box<dyn PotentialBasis>> {
+        self.validate()?;
+        Ok(match *self {
+            BasisSpec::Gaussian { center, sigma, scale, cutoff } => Box::new(GaussianBasis { center, sigma, scale, cutoff }),
+            BasisSpec::RfPseudo { kr, kz } => Box::new(RfPseudo { kr, kz }),
+            BasisSpec::Quadratic { center, curvature } => Box::new(QuadraticBasis { center, curvature }),
+            BasisSpec::StrayField { field, gradient, origin } => Box::new(StrayFieldBasis { field, gradient, origin }),
+        })
+    }
+}
+
+impl RfDriveSpec {
+    pub fn build(&self) -> Result<RfDrive> {
+        self.validate()?;
+        Ok(RfDrive { basis: self.basis.build()?, amplitude: self.amplitude, frequency: self.frequency })
+    }
+}
+
+impl BackgroundSpec {
+    pub fn build(&self) -> Result<Background> {
+        self.validate()?;
+        Ok(Background { basis: self.basis.build()?, volts: self.volts })
+    }
+}
+
+/// One dc electrode: its basis plus optional hardware metadata.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ElectrodeSpec {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub dac_channel: Option<usize>,
+    #[serde(default)]
+    pub v_min: Option<f64>,
+    #[serde(default)]
+    pub v_max: Option<f64>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub basis: BasisSpec,
+}
+
+fn default_true() -> bool { true }
+
+impl ElectrodeSpec {
+    /// descriptor for electrode `index`, defaulting to name e<index> on channel <index>
+    pub fn descriptor(&self, index: usize) -> Electrode {
+        Electrode {
+            name: self.name.clone().unwrap_or_else(|| format!("e{}", index)),
+            dac_channel: self.dac_channel.or(Some(index)),
+            v_min: self.v_min.unwrap_or(f64::NEG_INFINITY),
+            v_max: self.v_max.unwrap_or(f64::INFINITY),
+            enabled: self.enabled,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrapConfig {
+    pub rf: BasisSpec,
+    pub electrodes: Vec<ElectrodeSpec>,
+    #[serde(default)]
+    pub c2lr_pair: Option<(usize, usize)>,
+    #[serde(default)]
+    pub units: Option<Units>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rf_drive: Option<RfDriveSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub background: Vec<BackgroundSpec>,
+}
+
+impl TrapConfig {
+    pub fn validate(&self) -> Result<()> {
+        self.rf.validate()?;
+        if self.electrodes.is_empty() { return Err(invalid("trap has no electrodes".to_string())); }
+        for (i, e) in self.electrodes.iter().enumerate() {
+            e.basis.validate().map_err(|err| context(format!("electrode {}", i), err))?;
+            e.descriptor(i).validate().map_err(|err| invalid(format!("electrode {}: {}", i, err)))?;
+        }
+        let mut names: Vec<String> = (0..self.electrodes.len()).map(|i| self.electrodes[i].descriptor(i).name).collect();
+        names.sort();
+        if let Some(w) = names.windows(2).find(|w| w[0] == w[1]) {
+            return Err(invalid(format!("duplicate electrode name {}", w[0])));
+        }
+        if let Some((i, j)) = self.c2lr_pair {
+            let n = self.electrodes.len();
+            if i >= n || j >= n || i == j {
+                return Err(invalid(format!("c2lr pair ({}, {}) invalid for {} electrodes", i, j, n)));
+            }
+        }
+        if let Some(d) = &self.rf_drive { d.validate()?; }
+        for (i, b) in self.background.iter().enumerate() {
+            b.validate().map_err(|err| context(format!("background {}", i), err))?;
+        }
+        if let Some(u) = self.units {
+            positive("units length", u.length)?;
+            positive("units voltage", u.voltage)?;
+            positive("units frequency", u.frequency)?;
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Result<TrapModel> {
+        self.validate()?;
+        let dc = self.electrodes.iter().map(|e| e.basis.build()).collect::<Result<Vec<_>>>()?;
+        let descriptors = self.electrodes.iter().enumerate().map(|(i, e)| e.descriptor(i)).collect();
+        let mut model = TrapModel::new(self.rf.build()?, dc, self.c2lr_pair)
+            .with_units(self.units.unwrap_or_default())
+            .with_electrodes(descriptors)
+            .map_err(|e| invalid(e.to_string()))?;
+        if let Some(d) = &self.rf_drive { model = model.with_rf_drive(d.build()?); }
+        for b in &self.background { model = model.with_background(b.build()?); }
+        Ok(model)
+    }
+}
+
+// kept here rather than in basis.rs so that bases do not depend on configs
+impl TrapModel {
+    /// config describing this model, None if any basis has no spec
+    pub fn to_config(&self) -> Option<TrapConfig> {
+        let electrodes = self.dc.iter().zip(&self.electrodes).map(|(b, e)| {
+            Some(ElectrodeSpec {
+                name: Some(e.name.clone()),
+                dac_channel: e.dac_channel,
+                v_min: Some(e.v_min).filter(|v| v.is_finite()),
+                v_max: Some(e.v_max).filter(|v| v.is_finite()),
+                enabled: e.enabled,
+                basis: b.spec()?,
+            })
+        }).collect::<Option<Vec<_>>>()?;
+        let rf_drive = match &self.rf_drive {
+            Some(d) => Some(d.spec()?),
+            None => None,
+        };
+        let background = self.background.iter().map(Background::spec).collect::<Option<Vec<_>>>()?;
+        Some(TrapConfig { rf: self.rf.spec()?, electrodes, c2lr_pair: self.c2lr_pair, units: Some(self.units), rf_drive, background })
+    }
+}
+
+/// `n` waypoints from `start` to `end` sharing one target, at equal time
+/// steps of a transport following `profile` (evenly spaced when linear).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LineSpec {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub n: usize,
+    pub omega_axial: f64,
+    pub axial_dir: Vec3,
+    pub species: IonSpecies,
+    #[serde(default, skip_serializing_if = "is_linear")]
+    pub profile: VelocityProfile,
+}
+
+fn is_linear(p: &VelocityProfile) -> bool { *p == VelocityProfile::Linear }
+
+impl LineSpec {
+    pub fn waypoints(&self) -> Vec<Waypoint> {
+        (0..self.n).map(|i| {
+            let t = if self.n > 1 { i as f64 / (self.n as f64 - 1.0) } else { 0.0 };
+            Waypoint {
+                r: self.start + (self.end - self.start) * self.profile.eval(t).0,
+                omega_axial: self.omega_axial,
+                axial_dir: self.axial_dir,
+                species: self.species,
+            }
+        }).collect()
+    }
+}
+
+/// finite position, positive axial frequency, non-zero axis; errors name waypoint `i`
+pub(crate) fn validate_waypoint(i: usize, wp: &Waypoint) -> Result<()> {
+    let check = || -> Result<()> {
+        finite_vec("position", wp.r)?;
+        positive("omega_axial", wp.omega_axial)?;
+        positive("axial_dir length", wp.axial_dir.norm())
+    };
+    check().map_err(|e| context(format!("waypoint {}", i), e))
+}
+
+/// A trap together with the waypoints to solve on it. Explicit `waypoints`
+/// come first, followed by the expansion of each entry in `lines`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub trap: TrapConfig,
+    #[serde(default)]
+    pub waypoints: Vec<Waypoint>,
+    #[serde(default)]
+    pub lines: Vec<LineSpec>,
+    #[serde(default)]
+    pub solver: LsqOptions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dac: Option<DacConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise: Option<NoiseConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<FilterConfig>,
+}
+
+impl Config {
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        let cfg: Config = toml::from_str(s).map_err(|e| invalid(e.to_string()))?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    pub fn from_json_str(s: &str) -> Result<Self> {
+        let cfg: Config = serde_json::from_str(s).map_err(|e| invalid(e.to_string()))?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// load a .toml or .json file, chosen by extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml_str(&text),
+            Some("json") => Self::from_json_str(&text),
+            _ => Err(invalid(format!("{}: expected a .toml or .json file", path.display()))),
+        }
+    }
+
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| invalid(e.to_string()))
+    }
+
+    pub fn to_json_string(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| invalid(e.to_string()))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.trap.validate()?;
+        if let Some(l) = self.solver.voltage_limit { positive("solver voltage_limit", l)?; }
+        for (i, e) in self.trap.electrodes.iter().enumerate() {
+            let d = e.descriptor(i);
+            let (lo, hi) = d.bounds(self.solver.voltage_limit);
+            if d.driven() && lo > hi {
+                return Err(invalid(format!("electrode {}: limits [{}, {}] lie outside the solver voltage limit", i, d.v_min, d.v_max)));
+            }
+        }
+        if let Some(dac) = &self.dac { dac.validate()?; }
+        if let Some(noise) = &self.noise { noise.validate()?; }
+        if let Some(filter) = &self.filter { filter.validate()?; }
+        for (i, line) in self.lines.iter().enumerate() {
+            line.profile.validate().map_err(|e| invalid(format!("line {}: {}", i, e)))?;
+        }
+        for (i, wp) in self.all_waypoints().iter().enumerate() { validate_waypoint(i, wp)?; }
+        Ok(())
+    }
+
+    pub fn all_waypoints(&self) -> Vec<Waypoint> {
+        let mut wps = self.waypoints.clone();
+        for line in &self.lines { wps.extend(line.waypoints()); }
+        wps
+    }
+
+    pub fn build(&self) -> Result<(TrapModel, Vec<Waypoint>)> {
+        let (model, wps) = (self.trap.build()?, self.all_waypoints());
+        check_rf_drive(&model, &wps)?;
+        Ok((model, wps))
+    }
+}
+
+// relative mismatch allowed between the explicit drive's pseudopotential and `[trap.rf]`
+const RF_DRIVE_TOLERANCE: f64 = 1e-3;
+
+/// An explicit drive must obey Laplace and, at every waypoint and for its
+/// species, have the pseudopotential curvature `[trap.rf]` describes, or the
+/// dynamics and the solves would see different traps.
+fn check_rf_drive(model: &TrapModel, wps: &[Waypoint]) -> Result<()> {
+    let Some(drive) = &model.rf_drive else { return Ok(()) };
+    let size = |h: Hess| [h.xx, h.yy, h.zz, h.xy, h.xz, h.yz].iter().fold(0.0f64, |m, v| m.max(v.abs()));
+    for (i, wp) in wps.iter().enumerate() {
+        let k = drive.hess(wp.r);
+        if (k.xx + k.yy + k.zz).abs() > 1e-9 * size(k) {
+            return Err(invalid(format!("rf_drive: curvature at waypoint {} has trace {:e}, Laplace needs 0", i, k.xx + k.yy + k.zz)));
+        }
+        let (pseudo, rf) = (drive.pseudo_hess(wp.r, &wp.species), model.rf.hess(wp.r));
+        let mismatch = size(pseudo + rf.scale(-1.0)) / size(rf).max(f64::MIN_POSITIVE);
+        if mismatch > RF_DRIVE_TOLERANCE {
+            return Err(invalid(format!(
+                "rf_drive: pseudopotential at waypoint {} differs from [trap.rf] by {:.2e} of its curvature", i, mismatch)));
+        }
+    }
+    Ok(())
+}
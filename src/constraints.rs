@@ -1,62 +1,129 @@
 use ndarray::{Array1, Array2};
-use crate::basis::TrapModel;
+use sprs::{CsMat, TriMat};
+use crate::basis::{PotentialBasis, TrapModel};
 use crate::types::{Vec3, Waypoint};
+use crate::units::Units;
 
-pub fn build_constraints(
-    model: &TrapModel,
-    wp: &Waypoint,
-    charge_q: f64,
-    mass_m: f64
-) -> (Array2<f64>, Array1<f64>) {
-    let n = model.n_electrodes();
+// 3 grad rows + 1 axial curvature + 2 radial floors = 6 rows
+pub const N_ROWS: usize = 6;
 
-    // 3 grad rows + 1 axial curvature + 2 radial floors = 6 rows
-    let mut a = Array2::<f64>::zeros((6, n));
-    let mut b = Array1::<f64>::zeros(6);
-
-    // gradient = 0 at waypoint
-    let grf = model.rf.grad(wp.r);
-    for j in 0..n {
-        let bj = &model.dc[j];
-        let gj = bj.grad(wp.r);
-        a[[0, j]] = gj.x;
-        a[[1, j]] = gj.y;
-        a[[2, j]] = gj.z;
-    }
+pub const W_AX: f64 = 1e3; // heavy but not singular
+const W_RAD: f64 = 50.0;
+
+const EX: Vec3 = Vec3 { x: 1.0, y: 0.0, z: 0.0 };
+const EY: Vec3 = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+
+/// one constraint column for a single dc basis, per internal voltage unit
+fn column(basis: &dyn PotentialBasis, wp: &Waypoint, units: &Units) -> [f64; N_ROWS] {
+    let g = basis.grad(wp.r) * (units.voltage / units.field());
+    let h = basis.hess(wp.r).scale(units.weight_scale() * units.voltage / units.curvature());
+    [
+        g.x,
+        g.y,
+        g.z,
+        W_AX * h.quad(wp.axial_dir),
+        W_RAD * h.quad(EX),
+        W_RAD * h.quad(EY),
+    ]
+}
+
+fn rhs(model: &TrapModel, wp: &Waypoint) -> Array1<f64> {
+    let units = &model.units;
+    let mut b = Array1::<f64>::zeros(N_ROWS);
+
+    // gradient = 0 at waypoint, the background a known offset like the RF
+    let grf = (model.rf.grad(wp.r) + model.grad_background(wp.r)) / units.field();
     b[0] = -grf.x;
     b[1] = -grf.y;
     b[2] = -grf.z;
 
-    // axial curvature target (heavy weight)
+    // axial curvature target
     let u = wp.axial_dir;                      // assumed unit z
-    let hrf = model.rf.hess(wp.r);
-    let target_ax = mass_m * wp.omega_axial * wp.omega_axial / charge_q;
-    let w_ax = 1e3;                            // heavy but not singular
-
-    for j in 0..n {
-        let hj = model.dc[j].hess(wp.r);
-        a[[3, j]] = w_ax * hj.quad(u);
-    }
-    b[3] = w_ax * (target_ax - hrf.quad(u));
+    // curvatures carry the weight scale, see `Units::weight_scale`
+    let to_rows = units.weight_scale() / units.curvature();
+    let hrf = model.rf.hess(wp.r).scale(to_rows);
+    let known = hrf + model.hess_background(wp.r).scale(to_rows);
+    let target_ax = wp.omega_axial * wp.omega_axial / wp.species.charge_to_mass() * to_rows;
+    b[3] = W_AX * (target_ax - known.quad(u));
 
     // radial floors: keep H_xx and H_yy >= 0.2 * RF radial curvature
-    let ex = Vec3 { x: 1.0, y: 0.0, z: 0.0 };
-    let ey = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
-    let w_rad = 50.0;
-
-    let floor_x = 0.2 * model.rf.hess(wp.r).quad(ex);
-    for j in 0..n {
-        let hj = model.dc[j].hess(wp.r);
-        a[[4, j]] = w_rad * hj.quad(ex);
+    b[4] = W_RAD * (0.2 * hrf.quad(EX) - known.quad(EX));
+    b[5] = W_RAD * (0.2 * hrf.quad(EY) - known.quad(EY));
+    b
+}
+
+/// Constraint system in the model's internal units: columns act on voltages
+/// in units of `model.units.voltage`, see `Units`.
+pub fn build_constraints(model: &TrapModel, wp: &Waypoint) -> (Array2<f64>, Array1<f64>) {
+    let n = model.n_electrodes();
+    let mut a = Array2::<f64>::zeros((N_ROWS, n));
+    // disabled electrodes keep a zero column, as in the sparse assembly
+    for j in (0..n).filter(|&j| model.electrodes[j].enabled) {
+        let col = column(model.dc[j].as_ref(), wp, &model.units);
+        for (row, v) in col.iter().enumerate() { a[[row, j]] = *v; }
     }
-    b[4] = w_rad * (floor_x - hrf.quad(ex));
+    (a, rhs(model, wp))
+}
+
+/// Constraint system restricted to the unknowns that influence a waypoint.
+/// Column k of `a` belongs to unknown `cols[k]`, an electrode for
+/// `build_constraints_local` or a group for `build_constraints_grouped`.
+pub struct LocalConstraints {
+    pub a: CsMat<f64>,
+    pub b: Array1<f64>,
+    pub cols: Vec<usize>,
+}
 
-    let floor_y = 0.2 * model.rf.hess(wp.r).quad(ey);
-    for j in 0..n {
-        let hj = model.dc[j].hess(wp.r);
-        a[[5, j]] = w_rad * hj.quad(ey);
+impl LocalConstraints {
+    /// Expand a local solution to a full unknown vector, one bound pair per
+    /// unknown. Inactive unknowns take the value in their bounds nearest zero.
+    pub fn scatter(&self, x_local: &[f64], bounds: &[(f64, f64)]) -> Vec<f64> {
+        let mut v: Vec<f64> = bounds.iter().map(|&(lo, hi)| 0.0_f64.max(lo).min(hi)).collect();
+        for (k, &j) in self.cols.iter().enumerate() { v[j] = x_local[k]; }
+        v
     }
-    b[5] = w_rad * (floor_y - hrf.quad(ey));
+}
 
-    (a, b)
+/// Sparse counterpart of `build_constraints` that skips disabled electrodes
+/// and those whose support does not reach the waypoint. `swap` exchanges the
+/// bases driving two electrodes, which is how the C2LR mirror is solved.
+pub fn build_constraints_local(
+    model: &TrapModel,
+    wp: &Waypoint,
+    swap: Option<(usize, usize)>,
+) -> LocalConstraints {
+    let singles: Vec<Vec<usize>> = (0..model.n_electrodes()).map(|j| vec![j]).collect();
+    build_constraints_grouped(model, wp, &singles, swap)
+}
+
+/// Local constraints where each unknown drives a group of electrodes tied to
+/// one voltage, its column being the sum of the members' columns.
+pub fn build_constraints_grouped(
+    model: &TrapModel,
+    wp: &Waypoint,
+    groups: &[Vec<usize>],
+    swap: Option<(usize, usize)>,
+) -> LocalConstraints {
+    let basis_of = |j: usize| match swap {
+        Some((p, q)) if j == p => q,
+        Some((p, q)) if j == q => p,
+        _ => j,
+    };
+    let live = |j: usize| model.electrodes[j].enabled && model.dc[basis_of(j)].influences(wp.r);
+    let cols: Vec<usize> = (0..groups.len())
+        .filter(|&g| groups[g].iter().any(|&j| live(j)))
+        .collect();
+
+    let mut tri = TriMat::new((N_ROWS, cols.len()));
+    for (k, &g) in cols.iter().enumerate() {
+        let mut col = [0.0; N_ROWS];
+        for &j in groups[g].iter().filter(|&&j| live(j)) {
+            let cj = column(model.dc[basis_of(j)].as_ref(), wp, &model.units);
+            for (c, v) in col.iter_mut().zip(cj) { *c += v; }
+        }
+        for (row, v) in col.iter().enumerate() {
+            if *v != 0.0 { tri.add_triplet(row, k, *v); }
+        }
+    }
+    LocalConstraints { a: tri.to_csr(), b: rhs(model, wp), cols }
 }
@@ -0,0 +1,405 @@
+// src/control.rs
+//
+// Optimal control of a transport. The controls are the channel voltages at
+// the waypoint times, played through natural cubic splines, and the cost is
+// the energy the ion is left with in the final well, in quanta of its axial
+// mode, from the same velocity Verlet integration as `simulate`. The gradient
+// is the discrete adjoint of that integrator: one backward sweep gives the
+// derivative with respect to every control. The first and last rows hold the
+// ion in its start and end wells and stay fixed.
+
+use serde::{Deserialize, Serialize};
+use crate::basis::TrapModel;
+use crate::channels::ChannelMap;
+use crate::dynamics::secular_freqs;
+use crate::simulate::{equilibrium, HBAR};
+use crate::spline::{CubicSpline, SplineKind};
+use crate::timing::TimedWaveform;
+use crate::types::{IonwaveError, Result, Vec3, Waypoint};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlOptions {
+    pub steps_per_period: f64,        // Verlet steps per fastest secular period
+    pub iterations: usize,            // projected gradient steps at most
+    pub tol: f64,                     // quanta, stop once a step gains less
+    pub voltage_limit: Option<f64>,   // V, symmetric, on top of each channel's own limits
+    pub slew_limit: Option<f64>,      // V/s, on the interpolated voltages
+}
+
+impl Default for ControlOptions {
+    fn default() -> Self {
+        Self { steps_per_period: 40.0, iterations: 200, tol: 1e-6, voltage_limit: None, slew_limit: None }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ControlResult {
+    pub timed: TimedWaveform,       // optimised channel voltages at the original times
+    pub initial_quanta: f64,
+    pub final_quanta: f64,
+    pub iterations: usize,
+    pub evaluations: usize,         // forward and adjoint sweeps
+    pub max_slew: f64,              // V/s, over the integration steps
+    pub history: Vec<f64>,          // quanta after every accepted step
+}
+
+/// The transport discretised once: Verlet step times and, at each, the
+/// weight of every knot in the natural spline and in its slope.
+struct Problem<'a> {
+    model: &'a TrapModel,
+    map: ChannelMap,
+    grid: Vec<f64>,
+    weights: Vec<Vec<f64>>,
+    slopes: Vec<Vec<f64>>,
+    mass: f64,
+    qm: f64,
+    start: Vec3,               // well at the first row
+    end_volts: Vec<f64>,       // electrodes, last row
+    end_well: Vec3,
+    quantum: f64,              // J, ħω of the final axial mode
+}
+
+impl<'a> Problem<'a> {
+    fn new(model: &'a TrapModel, waypoints: &[Waypoint], timed: &TimedWaveform, steps_per_period: f64) -> Result<Self> {
+        let map = timed.channel_map(model, waypoints)?;
+        if steps_per_period.is_nan() || steps_per_period < 1.0 {
+            return Err(IonwaveError::InvalidInput("steps_per_period must be at least 1".to_string()));
+        }
+        let species = waypoints[0].species;
+        let start_volts = map.expand(&timed.volts[0]);
+        let start = equilibrium(model, &start_volts, waypoints[0].r)?;
+        let fastest = secular_freqs(model.hess_total(start, &start_volts), &species).into_iter().fold(0.0, f64::max);
+        if fastest <= 0.0 {
+            return Err(IonwaveError::InvalidInput("the ion is not trapped at the first waypoint".to_string()));
+        }
+        let end_volts = map.expand(&timed.volts[timed.volts.len() - 1]);
+        let end_wp = &waypoints[waypoints.len() - 1];
+        let end_well = equilibrium(model, &end_volts, end_wp.r)?;
+        let omega = (species.charge_to_mass() * model.hess_total(end_well, &end_volts).quad(end_wp.axial_dir.unit())).max(0.0).sqrt();
+        if omega <= 0.0 {
+            return Err(IonwaveError::InvalidInput("no axial confinement at the last waypoint".to_string()));
+        }
+
+        // the same steps as `simulate` with the Verlet integrator
+        let (t0, t1) = (timed.times[0], timed.times[timed.times.len() - 1]);
+        let dt_max = 2.0 * std::f64::consts::PI / fastest / steps_per_period;
+        let n = ((t1 - t0) / dt_max).ceil().max(1.0) as usize;
+        let grid: Vec<f64> = (0..=n).map(|k| if k == n { t1 } else { t0 + k as f64 * (t1 - t0) / n as f64 }).collect();
+
+        // splines are linear in their data, so knot j weighs in with its cardinal spline
+        let m = timed.times.len();
+        let (mut weights, mut slopes) = (vec![vec![0.0; m]; grid.len()], vec![vec![0.0; m]; grid.len()]);
+        for j in 0..m {
+            let unit: Vec<f64> = (0..m).map(|i| if i == j { 1.0 } else { 0.0 }).collect();
+            let s = CubicSpline::new(SplineKind::Natural, &timed.times, &unit)?;
+            for (k, &t) in grid.iter().enumerate() {
+                weights[k][j] = s.eval(t);
+                slopes[k][j] = s.derivative(t);
+            }
+        }
+        Ok(Self {
+            model, map, grid, weights, slopes,
+            mass: species.mass(),
+            qm: species.charge_to_mass(),
+            start, end_volts, end_well,
+            quantum: HBAR * omega,
+        })
+    }
+
+    /// electrode voltages at step k
+    fn volts(&self, x: &[Vec<f64>], k: usize) -> Vec<f64> {
+        let mut vc = vec![0.0; self.map.n_channels()];
+        for (w, row) in self.weights[k].iter().zip(x) {
+            if *w == 0.0 { continue; }
+            for (v, xi) in vc.iter_mut().zip(row) { *v += w * xi; }
+        }
+        self.map.expand(&vc)
+    }
+
+    /// gradient of the potential per volt on each channel
+    fn channel_grads(&self, r: Vec3) -> Vec<Vec3> {
+        self.map.members.iter().map(|m| m.iter().fold(Vec3::ZERO, |g, &e| g + self.model.dc[e].grad(r))).collect()
+    }
+
+    /// Final quanta and, with `gradient`, its derivative with respect to
+    /// every knot, zero on the fixed first and last rows.
+    fn evaluate(&self, x: &[Vec<f64>], gradient: bool) -> (f64, Vec<Vec<f64>>) {
+        let model = self.model;
+        let n = self.grid.len() - 1;
+        let accel = |k: usize, r: Vec3| model.grad_total(r, &self.volts(x, k)) * -self.qm;
+
+        let (mut r, mut v) = (self.start, Vec3::ZERO);
+        let mut a = accel(0, r);
+        let mut path = Vec::with_capacity(n + 1);
+        path.push(r);
+        for k in 0..n {
+            let dt = self.grid[k + 1] - self.grid[k];
+            let vh = v + a * (0.5 * dt);
+            r = r + vh * dt;
+            a = accel(k + 1, r);
+            v = vh + a * (0.5 * dt);
+            path.push(r);
+        }
+        let energy = 0.5 * self.mass * v.dot(v)
+            + self.qm * self.mass * (model.phi_total(r, &self.end_volts) - model.phi_total(self.end_well, &self.end_volts));
+        let quanta = energy / self.quantum;
+        let mut grad = vec![vec![0.0; self.map.n_channels()]; x.len()];
+        if !gradient { return (quanta, grad); }
+
+        // adjoint of the final energy, then back through every step
+        let mut lr = model.grad_total(r, &self.end_volts) * (self.qm * self.mass / self.quantum);
+        let mut lv = v * (self.mass / self.quantum);
+        // λ·∂a/∂x at step k, weighted by `c`, into the knot gradient
+        let mut add_control = |k: usize, lambda: Vec3, c: f64| {
+            let g = self.channel_grads(path[k]);
+            for (j, w) in self.weights[k].iter().enumerate() {
+                if *w == 0.0 || j == 0 || j + 1 == x.len() { continue; }
+                for (gc, gi) in grad[j].iter_mut().zip(&g) { *gc -= c * w * self.qm * gi.dot(lambda); }
+            }
+        };
+        for k in (0..n).rev() {
+            let dt = self.grid[k + 1] - self.grid[k];
+            let h1 = model.hess_total(path[k + 1], &self.volts(x, k + 1));
+            // v' = vh + dt/2 a(r')
+            lr = lr - h1.apply(lv) * (0.5 * dt * self.qm);
+            add_control(k + 1, lv, 0.5 * dt);
+            // r' = r + dt vh
+            let lh = lv + lr * dt;
+            // vh = v + dt/2 a(r)
+            let h0 = model.hess_total(path[k], &self.volts(x, k));
+            lr = lr - h0.apply(lh) * (0.5 * dt * self.qm);
+            add_control(k, lh, 0.5 * dt);
+            lv = lh;
+        }
+        (quanta, grad)
+    }
+
+    /// channel voltages at every step, V
+    fn sampled(&self, x: &[Vec<f64>]) -> Vec<Vec<f64>> { self.combine(&self.weights, x) }
+
+    /// channel slopes at every step, V/s
+    fn slew(&self, x: &[Vec<f64>]) -> Vec<Vec<f64>> { self.combine(&self.slopes, x) }
+
+    fn combine(&self, table: &[Vec<f64>], x: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        table.iter().map(|s| {
+            let mut d = vec![0.0; self.map.n_channels()];
+            for (w, row) in s.iter().zip(x) {
+                for (di, xi) in d.iter_mut().zip(row) { *di += w * xi; }
+            }
+            d
+        }).collect()
+    }
+
+    /// Nearest inner rows to `x` with every channel within `bounds`, at the
+    /// knots and at every step in between, and with a `slew` limit every
+    /// slope within it. Both are linear in the knots, so each channel is a
+    /// projection onto a polytope, solved exactly from `from`, which must lie
+    /// inside it.
+    fn project(&self, x: &mut [Vec<f64>], from: &[Vec<f64>], bounds: &[(f64, f64)], slew: Option<f64>) {
+        let last = x.len() - 1;
+        for (c, &(lo, hi)) in bounds.iter().enumerate() {
+            // g·y <= h for both sides of each bound, held rows moved to the right
+            let n = last - 1;
+            let mut rows = Vec::with_capacity(2 * (n + self.weights.len() + self.slopes.len()));
+            let mut both = |a: &[f64], b: f64, lo: f64, hi: f64| {
+                rows.push((a.iter().map(|v| -v).collect(), b - lo));
+                rows.push((a.to_vec(), hi - b));
+            };
+            for j in 0..n {
+                let e: Vec<f64> = (0..n).map(|i| if i == j { 1.0 } else { 0.0 }).collect();
+                both(&e, 0.0, lo, hi);
+            }
+            let tables = std::iter::once((&self.weights, lo, hi))
+                .chain(slew.map(|limit| (&self.slopes, -limit, limit)));
+            for (table, lo, hi) in tables {
+                for s in table {
+                    both(&s[1..last], s[0] * x[0][c] + s[last] * x[last][c], lo, hi);
+                }
+            }
+            let z: Vec<f64> = x[1..last].iter().map(|r| r[c]).collect();
+            let start: Vec<f64> = from[1..last].iter().map(|r| r[c]).collect();
+            let y = nearest_in_polytope(&z, start, &rows);
+            for (r, v) in x[1..last].iter_mut().zip(y) { r[c] = v; }
+        }
+    }
+}
+
+/// Point nearest `z` with every `g·y <= h`, by a primal active set method
+/// from the feasible `y`. Stays feasible throughout, so a degenerate stall
+/// still returns a point inside.
+fn nearest_in_polytope(z: &[f64], mut y: Vec<f64>, rows: &[(Vec<f64>, f64)]) -> Vec<f64> {
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(u, v)| u * v).sum::<f64>();
+    let rows: Vec<(Vec<f64>, f64)> = rows.iter().filter_map(|(g, h)| {
+        let norm = dot(g, g).sqrt();
+        (norm > 0.0).then(|| (g.iter().map(|v| v / norm).collect(), h / norm))
+    }).collect();
+    let scale = z.iter().chain(&y).fold(1e-12, |m: f64, v| m.max(v.abs()));
+    let mut active: Vec<usize> = Vec::new();
+    for _ in 0..10 * (rows.len() + z.len()) {
+        // nearest point on the active faces: y* = z - Σ μ_i g_i
+        let gram: Vec<Vec<f64>> = active.iter().map(|&i| active.iter().map(|&k| dot(&rows[i].0, &rows[k].0)).collect()).collect();
+        let rhs: Vec<f64> = active.iter().map(|&i| dot(&rows[i].0, z) - rows[i].1).collect();
+        let Some(mu) = solve_dense(gram, rhs) else { break };
+        let mut target = z.to_vec();
+        for (&i, m) in active.iter().zip(&mu) {
+            for (t, g) in target.iter_mut().zip(&rows[i].0) { *t -= m * g; }
+        }
+        let p: Vec<f64> = target.iter().zip(&y).map(|(t, v)| t - v).collect();
+        if p.iter().all(|v| v.abs() <= 1e-14 * scale) {
+            // optimal once no active face pulls the wrong way
+            match mu.iter().enumerate().min_by(|a, b| a.1.total_cmp(b.1)) {
+                Some((k, m)) if *m < -1e-14 * scale => { active.remove(k); }
+                _ => return target,
+            }
+            continue;
+        }
+        // walk towards it until a face blocks
+        let (mut alpha, mut block) = (1.0, None);
+        for (i, (g, h)) in rows.iter().enumerate() {
+            if active.contains(&i) { continue; }
+            let gp = dot(g, &p);
+            if gp <= 0.0 { continue; }
+            let reach = (h - dot(g, &y)).max(0.0) / gp;
+            if reach < alpha { (alpha, block) = (reach, Some(i)); }
+        }
+        for (v, pi) in y.iter_mut().zip(&p) { *v += alpha * pi; }
+        if let Some(i) = block { active.push(i); }
+    }
+    y
+}
+
+/// Gaussian elimination with partial pivoting, None when singular.
+fn solve_dense(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &k| a[i][col].abs().total_cmp(&a[k][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 { return None; }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let (upper, lower) = a.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for (row, rb) in lower.iter_mut().zip(col + 1..n) {
+            let f = row[col] / pivot_row[col];
+            for (v, p) in row[col..].iter_mut().zip(&pivot_row[col..]) { *v -= f * p; }
+            b[rb] -= f * b[col];
+        }
+    }
+    for col in (0..n).rev() {
+        b[col] = (b[col] - (col + 1..n).map(|k| a[col][k] * b[k]).sum::<f64>()) / a[col][col];
+    }
+    Some(b)
+}
+
+/// Quanta left in the final well by `timed` played through natural splines,
+/// and their derivative with respect to each channel voltage at each
+/// waypoint; the first and last rows are held and get zero.
+pub fn excitation_gradient(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    timed: &TimedWaveform,
+    steps_per_period: f64,
+) -> Result<(f64, Vec<Vec<f64>>)> {
+    Ok(Problem::new(model, waypoints, timed, steps_per_period)?.evaluate(&timed.volts, true))
+}
+
+/// Reshape the inner rows of `timed` to leave the fewest quanta in the final
+/// well, by projected gradient descent with Barzilai-Borwein steps. The
+/// interpolated voltages stay within each channel's limits at every step, and
+/// their slopes within `slew_limit`.
+pub fn optimize_transport(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    timed: &TimedWaveform,
+    opts: &ControlOptions,
+) -> Result<ControlResult> {
+    if let Some(s) = opts.slew_limit {
+        if !(s.is_finite() && s > 0.0) {
+            return Err(IonwaveError::InvalidInput(format!("slew limit {} must be positive", s)));
+        }
+    }
+    let problem = Problem::new(model, waypoints, timed, opts.steps_per_period)?;
+    let bounds = problem.map.clamped_bounds(opts.voltage_limit);
+    let last = timed.volts.len() - 1;
+    let project = |x: &mut Vec<Vec<f64>>, from: &[Vec<f64>]| problem.project(x, from, &bounds, opts.slew_limit);
+    let mut evaluations = 0;
+    let mut cost = |x: &[Vec<f64>]| {
+        evaluations += 1;
+        problem.evaluate(x, true)
+    };
+    let dot = |a: &[Vec<f64>], b: &[Vec<f64>]| -> f64 {
+        a.iter().zip(b).map(|(ra, rb)| ra.iter().zip(rb).map(|(x, y)| x * y).sum::<f64>()).sum()
+    };
+    let norm_inf = |a: &[Vec<f64>]| a.iter().flatten().fold(0.0_f64, |m, v| m.max(v.abs()));
+
+    // a straight ramp between the held rows is the slowest way between them,
+    // and inside the limits when anything is
+    let (t0, t1) = (timed.times[0], timed.times[last]);
+    let ramp: Vec<Vec<f64>> = timed.times.iter().map(|t| {
+        let u = (t - t0) / (t1 - t0);
+        timed.volts[0].iter().zip(&timed.volts[last]).map(|(a, b)| a + u * (b - a)).collect()
+    }).collect();
+    let outside = problem.sampled(&ramp).iter().flat_map(|v| v.iter().zip(&bounds))
+        .any(|(v, &(lo, hi))| *v < lo - 1e-9 * lo.abs() || *v > hi + 1e-9 * hi.abs());
+    if outside {
+        return Err(IonwaveError::InvalidInput("voltage limits cannot be met between the held end rows".to_string()));
+    }
+    if let Some(s) = opts.slew_limit {
+        let least = norm_inf(&problem.slew(&ramp));
+        if least > s * (1.0 + 1e-9) {
+            return Err(IonwaveError::InvalidInput(format!(
+                "slew limit {} V/s cannot be met between the held end rows, {} V/s at least", s, least)));
+        }
+    }
+    let mut x = timed.volts.clone();
+    project(&mut x, &ramp);
+    let (mut quanta, mut g) = cost(&x);
+    let initial_quanta = quanta;
+    // first step moves the largest voltage by a thousandth of the waveform's scale
+    let scale = norm_inf(&x).max(1e-6);
+    let mut step = 1e-3 * scale / norm_inf(&g).max(f64::MIN_POSITIVE);
+    let mut history = Vec::new();
+    let mut iterations = 0;
+    while iterations < opts.iterations {
+        // Armijo backtracking along the projected path
+        let mut accepted = None;
+        for _ in 0..40 {
+            let mut trial: Vec<Vec<f64>> = x.iter().zip(&g)
+                .map(|(row, gr)| row.iter().zip(gr).map(|(v, gv)| v - step * gv).collect())
+                .collect();
+            project(&mut trial, &x);
+            let moved: Vec<Vec<f64>> = x.iter().zip(&trial)
+                .map(|(a, b)| a.iter().zip(b).map(|(u, w)| u - w).collect())
+                .collect();
+            let (tq, tg) = cost(&trial);
+            if tq.is_finite() && tq <= quanta - 1e-4 * dot(&g, &moved) {
+                accepted = Some((trial, moved, tq, tg));
+                break;
+            }
+            step *= 0.5;
+        }
+        let Some((trial, moved, tq, tg)) = accepted else { break };
+        iterations += 1;
+        let gain = quanta - tq;
+        // Barzilai-Borwein step from the change in gradient
+        let dg: Vec<Vec<f64>> = g.iter().zip(&tg)
+            .map(|(a, b)| a.iter().zip(b).map(|(u, w)| u - w).collect())
+            .collect();
+        let sy = dot(&moved, &dg);
+        step = if sy > 0.0 { dot(&moved, &moved) / sy } else { 2.0 * step };
+        x = trial;
+        (quanta, g) = (tq, tg);
+        history.push(quanta);
+        if gain < opts.tol { break; }
+    }
+
+    Ok(ControlResult {
+        max_slew: norm_inf(&problem.slew(&x)),
+        timed: TimedWaveform::new(timed.times.clone(), timed.channels.clone(), x)?,
+        initial_quanta,
+        final_quanta: quanta,
+        iterations,
+        evaluations,
+        history,
+    })
+}
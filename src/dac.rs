@@ -0,0 +1,216 @@
+// src/dac.rs
+
+use serde::{Deserialize, Serialize};
+use crate::analysis::analyze;
+use crate::basis::TrapModel;
+use crate::channels::{ChannelMap, ChannelWaveform};
+use crate::types::{IonwaveError, Result, Waypoint};
+
+/// One DAC channel's own converter, where it differs from the board's, and
+/// its measured output `v_out = gain * v_ideal + offset`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DacChannel {
+    pub channel: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v_min: Option<f64>,   // V
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v_max: Option<f64>,   // V
+    #[serde(default = "one")]
+    pub gain: f64,
+    #[serde(default)]
+    pub offset: f64,          // V
+}
+
+fn one() -> f64 { 1.0 }
+
+impl DacChannel {
+    /// the board's converter with a measured gain and offset
+    pub fn calibrated(channel: usize, gain: f64, offset: f64) -> Self {
+        Self { channel, bits: None, v_min: None, v_max: None, gain, offset }
+    }
+}
+
+/// An offset-binary converter spanning `[v_min, v_max]` in `2^bits` codes,
+/// code 0 at `v_min`, behind a calibration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Converter {
+    pub bits: u32,
+    pub v_min: f64,
+    pub v_max: f64,
+    pub gain: f64,
+    pub offset: f64,
+}
+
+impl Converter {
+    pub fn max_code(&self) -> u32 { ((1u64 << self.bits) - 1) as u32 }
+
+    /// volts per code step at the DAC output, before calibration
+    pub fn lsb(&self) -> f64 { (self.v_max - self.v_min) / self.max_code() as f64 }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.bits == 0 || self.bits > 32 { return Err(format!("{} bits, expected 1..=32", self.bits)); }
+        if !(self.v_min.is_finite() && self.v_max.is_finite() && self.v_min < self.v_max) {
+            return Err(format!("range [{}, {}] is empty", self.v_min, self.v_max));
+        }
+        if !(self.gain.is_finite() && self.gain != 0.0 && self.offset.is_finite()) {
+            return Err("calibration needs a finite non-zero gain and finite offset".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The board's DAC: every channel spans `[v_min, v_max]` in `2^bits` codes
+/// unless its entry in `channels` says otherwise. Channels without an entry
+/// are taken as ideal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DacConfig {
+    pub bits: u32,
+    pub v_min: f64,
+    pub v_max: f64,
+    #[serde(default, alias = "calibration")]
+    pub channels: Vec<DacChannel>,
+}
+
+impl DacConfig {
+    pub fn new(bits: u32, v_min: f64, v_max: f64) -> Self {
+        Self { bits, v_min, v_max, channels: Vec::new() }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let bad = |msg: String| Err(IonwaveError::Config(format!("dac: {}", msg)));
+        if let Err(e) = self.default_converter().validate() { return bad(e); }
+        for (i, c) in self.channels.iter().enumerate() {
+            if let Err(e) = self.converter(c.channel).validate() { return bad(format!("channel {}: {}", c.channel, e)); }
+            if self.channels[..i].iter().any(|o| o.channel == c.channel) {
+                return bad(format!("channel {} given twice", c.channel));
+            }
+        }
+        Ok(())
+    }
+
+    /// an ideal channel of the board
+    pub fn default_converter(&self) -> Converter {
+        Converter { bits: self.bits, v_min: self.v_min, v_max: self.v_max, gain: 1.0, offset: 0.0 }
+    }
+
+    /// the converter behind `channel`, its own settings over the board's
+    pub fn converter(&self, channel: usize) -> Converter {
+        let d = self.default_converter();
+        self.channels.iter().find(|c| c.channel == channel).map_or(d, |c| Converter {
+            bits: c.bits.unwrap_or(d.bits),
+            v_min: c.v_min.unwrap_or(d.v_min),
+            v_max: c.v_max.unwrap_or(d.v_max),
+            gain: c.gain,
+            offset: c.offset,
+        })
+    }
+}
+
+/// Integer codes for a channel waveform, one row per waypoint.
+#[derive(Clone, Debug)]
+pub struct DacCodes {
+    pub channels: Vec<usize>,
+    pub bits: Vec<u32>,                 // per channel
+    pub codes: Vec<Vec<u32>>,
+    pub channel_volts: Vec<Vec<f64>>,   // what the calibrated DAC actually outputs
+    pub clipped: usize,                 // samples outside the DAC range
+}
+
+/// Convert channel voltages into DAC codes, inverting each channel's
+/// calibration and rounding to the nearest code. Samples beyond the range
+/// are clipped to the end codes and counted; a NaN or infinite sample is an
+/// error.
+pub fn quantize(wf: &ChannelWaveform, dac: &DacConfig) -> Result<DacCodes> {
+    dac.validate()?;
+    let conv: Vec<Converter> = wf.channels.iter().map(|&ch| dac.converter(ch)).collect();
+    let mut clipped = 0;
+    let mut codes = Vec::with_capacity(wf.channel_volts.len());
+    let mut channel_volts = Vec::with_capacity(wf.channel_volts.len());
+    for (i, row) in wf.channel_volts.iter().enumerate() {
+        if row.len() != conv.len() {
+            return Err(IonwaveError::InvalidInput(format!("{} voltages for {} channels", row.len(), conv.len())));
+        }
+        let mut crow = Vec::with_capacity(row.len());
+        let mut vrow = Vec::with_capacity(row.len());
+        for ((&v, c), &ch) in row.iter().zip(&conv).zip(&wf.channels) {
+            if !v.is_finite() {
+                return Err(IonwaveError::InvalidInput(format!("channel {} at waypoint {} is {} V", ch, i, v)));
+            }
+            let (lsb, max_code) = (c.lsb(), c.max_code() as f64);
+            let k = (((v - c.offset) / c.gain - c.v_min) / lsb).round();
+            if !(0.0..=max_code).contains(&k) { clipped += 1; }
+            let k = k.clamp(0.0, max_code);
+            crow.push(k as u32);
+            vrow.push(c.gain * (c.v_min + k * lsb) + c.offset);
+        }
+        codes.push(crow);
+        channel_volts.push(vrow);
+    }
+    Ok(DacCodes { channels: wf.channels.clone(), bits: conv.iter().map(|c| c.bits).collect(), codes, channel_volts, clipped })
+}
+
+/// Layout of a memory image: rows of channel words in ascending channel
+/// order, one row per waypoint, each word as wide as its channel's codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DacImageFormat {
+    /// little-endian u16 words for channels up to 16 bits, u32 above
+    Binary,
+    /// one row of space separated hex words per line, as read by `$readmemh`
+    Hex,
+}
+
+/// How much quantisation moves the waveform and what the ion sees.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuantizationReport {
+    pub bits: Vec<u32>,              // per channel
+    pub lsb: Vec<f64>,               // V, per channel
+    pub clipped: usize,
+    pub max_voltage_error: f64,      // V, over channels and waypoints
+    pub rms_voltage_error: f64,
+    pub max_field_error: f64,        // V/m, |grad phi(quantised) - grad phi(ideal)|
+    pub max_axial_error_hz: f64,
+    pub max_secular_error_hz: f64,   // over all three modes
+}
+
+/// Compare the quantised waveform against the ideal one, re-evaluating
+/// field and secular frequencies through `TrapModel::grad_total` and
+/// `TrapModel::hess_total` at every waypoint.
+pub fn quantization_report(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    wf: &ChannelWaveform,
+    codes: &DacCodes,
+    dac: &DacConfig,
+) -> Result<QuantizationReport> {
+    let map = ChannelMap::from_model(model)?;
+    if map.channels != codes.channels || wf.channels != codes.channels {
+        return Err(IonwaveError::InvalidInput("dac codes do not match the model's channels".to_string()));
+    }
+    let quantised: Vec<Vec<f64>> = codes.channel_volts.iter().map(|v| map.expand(v)).collect();
+    let ideal = analyze(model, waypoints, &wf.electrode_volts)?;
+    let actual = analyze(model, waypoints, &quantised)?;
+
+    let errors: Vec<f64> = wf.channel_volts.iter().zip(&codes.channel_volts)
+        .flat_map(|(a, b)| a.iter().zip(b).map(|(x, y)| (x - y).abs()))
+        .collect();
+    let max_over = |f: &dyn Fn(usize) -> f64| (0..ideal.len()).map(f).fold(0.0, f64::max);
+    Ok(QuantizationReport {
+        bits: codes.bits.clone(),
+        lsb: codes.channels.iter().map(|&ch| dac.converter(ch).lsb()).collect(),
+        clipped: codes.clipped,
+        max_voltage_error: errors.iter().copied().fold(0.0, f64::max),
+        rms_voltage_error: (errors.iter().map(|e| e * e).sum::<f64>() / errors.len().max(1) as f64).sqrt(),
+        max_field_error: max_over(&|i| {
+            let r = waypoints[i].r;
+            (model.grad_total(r, &quantised[i]) - model.grad_total(r, &wf.electrode_volts[i])).norm()
+        }),
+        max_axial_error_hz: max_over(&|i| (actual[i].axial_hz - ideal[i].axial_hz).abs()),
+        max_secular_error_hz: max_over(&|i| (0..3)
+            .map(|k| (actual[i].secular_hz[k] - ideal[i].secular_hz[k]).abs())
+            .fold(0.0, f64::max)),
+    })
+}
@@ -1,8 +1,11 @@
-use crate::basis::TrapModel;
-use crate::types::{Vec3, Hess};
+use crate::types::{Hess, Vec3};
+use crate::species::IonSpecies;
 
 /// eigenvalues of symmetric 3 by 3 given as Hess fields
-pub fn eigenvalues(h: Hess) -> [f64; 3] {
+pub fn eigenvalues(h: Hess) -> [f64; 3] { eigen(h).0 }
+
+/// eigenvalues with their unit eigenvectors, in matching order
+pub fn eigen(h: Hess) -> ([f64; 3], [Vec3; 3]) {
     // construct full matrix and use analytic symmetric eig via cubic
     // for robustness here use a simple numeric reduction
     // small 3 by 3 symmetric Jacobi iterations
@@ -21,39 +24,42 @@ pub fn eigenvalues(h: Hess) -> [f64; 3] {
             if a[i][j].abs() > maxv { maxv = a[i][j].abs(); p = i; q = j; }
         }
         if maxv < 1e-12 { break; }
-        let app = a[p][p]; let aqq = a[q][q]; let apq = a[p][q];
-        let phi = 0.5 * ((aqq - app)/(apq)).atan();
+        let phi = jacobi_angle(a[p][p], a[q][q], a[p][q]);
         let c = phi.cos(); let s = phi.sin();
 
         // rotate A
+        let (rp, rq) = (a[p], a[q]);
         for k in 0..3 {
-            let aik = a[p][k]; let aqk = a[q][k];
-            a[p][k] = c*aik - s*aqk;
-            a[q][k] = s*aik + c*aqk;
+            a[p][k] = c*rp[k] - s*rq[k];
+            a[q][k] = s*rp[k] + c*rq[k];
         }
-        for k in 0..3 {
-            let akp = a[k][p]; let akq = a[k][q];
-            a[k][p] = c*akp - s*akq;
-            a[k][q] = s*akp + c*akq;
+        for row in a.iter_mut() {
+            let akp = row[p]; let akq = row[q];
+            row[p] = c*akp - s*akq;
+            row[q] = s*akp + c*akq;
         }
         // rotate V
-        for k in 0..3 {
-            let vkp = v[k][p]; let vkq = v[k][q];
-            v[k][p] = c*vkp - s*vkq;
-            v[k][q] = s*vkp + c*vkq;
+        for row in v.iter_mut() {
+            let vkp = row[p]; let vkq = row[q];
+            row[p] = c*vkp - s*vkq;
+            row[q] = s*vkp + c*vkq;
         }
     }
-    [a[0][0], a[1][1], a[2][2]]
+    let col = |k: usize| Vec3 { x: v[0][k], y: v[1][k], z: v[2][k] };
+    ([a[0][0], a[1][1], a[2][2]], [col(0), col(1), col(2)])
 }
 
-/// given total Hessian and charge q and mass m, return secular freqs
-pub fn secular_freqs(h: Hess, q: f64, m: f64) -> [f64; 3] {
-    let ev = eigenvalues(h);
-    // m omega^2 = q * lambda
-    let mut w = [0.0; 3];
-    for i in 0..3 {
-        let val = (q*ev[i]/m).max(0.0);
-        w[i] = val.sqrt();
-    }
-    w
+/// Rotation angle that zeroes apq with the sign convention of the updates in
+/// `eigen`: tan 2φ = 2 apq / (aqq - app). The atan2 form stays right when the
+/// diagonal is degenerate, app = aqq, where 0.5 atan((aqq - app) / apq)
+/// gives φ = 0 and the pair is never rotated.
+fn jacobi_angle(app: f64, aqq: f64, apq: f64) -> f64 {
+    0.5 * (2.0 * apq).atan2(aqq - app)
+}
+
+/// given total Hessian and the trapped species, return secular freqs
+pub fn secular_freqs(h: Hess, species: &IonSpecies) -> [f64; 3] {
+    // m omega^2 = q * lambda, q signed so anions need negative curvature
+    let qm = species.charge_to_mass();
+    eigenvalues(h).map(|ev| (qm * ev).max(0.0).sqrt())
 }
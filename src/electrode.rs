@@ -0,0 +1,62 @@
+// src/electrode.rs
+
+use serde::{Deserialize, Serialize};
+use crate::types::{IonwaveError, Result};
+
+/// Hardware description of one dc electrode, index-aligned with `TrapModel::dc`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Electrode {
+    pub name: String,
+    pub dac_channel: Option<usize>,  // None for electrodes not wired to a DAC, held at 0 V
+    pub v_min: f64,
+    pub v_max: f64,
+    pub enabled: bool,               // disabled electrodes are held at 0 V
+}
+
+impl Electrode {
+    /// enabled, unlimited electrode on the given channel
+    pub fn new(name: impl Into<String>, dac_channel: Option<usize>) -> Self {
+        Self { name: name.into(), dac_channel, v_min: f64::NEG_INFINITY, v_max: f64::INFINITY, enabled: true }
+    }
+
+    pub fn with_limits(mut self, v_min: f64, v_max: f64) -> Self {
+        self.v_min = v_min;
+        self.v_max = v_max;
+        self
+    }
+
+    pub fn disabled(mut self) -> Self {
+        self.enabled = false;
+        self
+    }
+
+    /// limits intersected with an optional symmetric clamp
+    pub fn bounds(&self, voltage_limit: Option<f64>) -> (f64, f64) {
+        match voltage_limit {
+            Some(l) => (self.v_min.max(-l), self.v_max.min(l)),
+            None => (self.v_min, self.v_max),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.v_min.is_nan() || self.v_max.is_nan() || self.v_min > self.v_max {
+            return Err(IonwaveError::InvalidInput(format!(
+                "electrode {} has invalid limits [{}, {}]", self.name, self.v_min, self.v_max)));
+        }
+        Ok(())
+    }
+
+    /// enabled and wired to a DAC channel; any other electrode is held at 0 V
+    pub fn driven(&self) -> bool { self.enabled && self.dac_channel.is_some() }
+
+    /// whether v is a legal output for this electrode; one held at 0 V takes
+    /// nothing else, whatever its limits
+    pub fn accepts(&self, v: f64) -> bool {
+        if self.driven() { v >= self.v_min && v <= self.v_max } else { v == 0.0 }
+    }
+}
+
+/// default descriptors e0, e1, ... on channels 0, 1, ...
+pub fn default_electrodes(n: usize) -> Vec<Electrode> {
+    (0..n).map(|i| Electrode::new(format!("e{}", i), Some(i))).collect()
+}
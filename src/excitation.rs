@@ -0,0 +1,193 @@
+// src/excitation.rs
+//
+// Motional excitation of a transport in the harmonic approximation. In the
+// frame of the well the axial mode is a driven oscillator,
+// ü + ω(t)² u = -ẍ_well, and an ion starting in the ground state ends in a
+// coherent state |α⟩ with
+//
+//   α = sqrt(m / 2ħ) ∫ ẍ_well e^{iθ(t)} / sqrt(ω) dt,   θ = ∫ ω dt,
+//
+// valid while ω changes slowly compared to itself. The well is held before
+// and after the transport, so integrating by parts leaves only its velocity.
+
+use serde::{Deserialize, Serialize};
+use crate::basis::TrapModel;
+use crate::simulate::{equilibrium, Drive, HBAR};
+use crate::species::IonSpecies;
+use crate::spline::SplineKind;
+use crate::timing::{uniform_times, TimedWaveform};
+use crate::trajectory::{Trajectory, TransportPath, VelocityProfile};
+use crate::types::{IonwaveError, Result, Vec3, Waypoint};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+// well samples one transport may take, a guard against absurd durations or rates
+const MAX_SAMPLES: f64 = 1e8;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExcitationOptions {
+    pub kind: SplineKind,            // voltage interpolation between waypoints
+    pub samples_per_period: f64,     // of the fastest target axial frequency
+}
+
+impl Default for ExcitationOptions {
+    fn default() -> Self { Self { kind: SplineKind::Monotone, samples_per_period: 20.0 } }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ExcitationSample {
+    pub t: f64,          // s
+    pub position: f64,   // m, of the well along the axis from its start
+    pub axial_hz: f64,
+    pub phonons: f64,    // left behind if the well stopped dead here
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExcitationReport {
+    pub alpha: [f64; 2],          // coherent displacement, real and imaginary part
+    pub mean_phonons: f64,        // |α|², for an ion starting in the ground state
+    pub min_axial_hz: f64,
+    pub max_axial_hz: f64,
+    pub samples: Vec<ExcitationSample>,
+}
+
+/// Coherent displacement from the well's axial position and frequency
+/// (rad/s) at ascending times. The well velocity is taken constant between
+/// samples, the phase is integrated exactly across each interval.
+pub fn coherent_excitation(times: &[f64], positions: &[f64], omegas: &[f64], mass: f64) -> Result<ExcitationReport> {
+    let n = times.len();
+    if n < 2 || positions.len() != n || omegas.len() != n {
+        return Err(IonwaveError::InvalidInput(format!(
+            "excitation needs at least two samples, got {} times, {} positions and {} frequencies", n, positions.len(), omegas.len())));
+    }
+    if times.windows(2).any(|w| w[1] <= w[0]) || positions.iter().any(|x| !x.is_finite()) {
+        return Err(IonwaveError::InvalidInput("excitation samples must be finite with increasing times".to_string()));
+    }
+    if let Some(k) = omegas.iter().position(|w| !(w.is_finite() && *w > 0.0)) {
+        return Err(IonwaveError::InvalidInput(format!("no axial confinement at t = {} s", times[k])));
+    }
+    let scale = (mass / (2.0 * HBAR)).sqrt();
+    let (mut re, mut im, mut theta) = (0.0, 0.0, 0.0);
+    let mut samples = Vec::with_capacity(n);
+    samples.push(ExcitationSample { t: times[0], position: 0.0, axial_hz: omegas[0] / TWO_PI, phonons: 0.0 });
+    for k in 0..n - 1 {
+        let dt = times[k + 1] - times[k];
+        let w = 0.5 * (omegas[k] + omegas[k + 1]);
+        let phase = theta + 0.5 * w * dt;
+        theta += w * dt;
+        // ∫ e^{iθ} over the interval is dt sinc(ω dt / 2) e^{iθ_mid}
+        let x = 0.5 * w * dt;
+        let dx = (positions[k + 1] - positions[k]) * if x == 0.0 { 1.0 } else { x.sin() / x };
+        // -dx e^{iθ} (i sqrt(ω) - ω̇ / 2ω^{3/2})
+        let (a, b) = (w.sqrt(), (omegas[k + 1] - omegas[k]) / dt / (2.0 * w.powf(1.5)));
+        let (c, s) = (phase.cos(), phase.sin());
+        re += dx * (b * c + a * s);
+        im += dx * (b * s - a * c);
+        samples.push(ExcitationSample {
+            t: times[k + 1],
+            position: positions[k + 1] - positions[0],
+            axial_hz: omegas[k + 1] / TWO_PI,
+            phonons: scale * scale * (re * re + im * im),
+        });
+    }
+    let alpha = [scale * re, scale * im];
+    Ok(ExcitationReport {
+        alpha,
+        mean_phonons: alpha[0] * alpha[0] + alpha[1] * alpha[1],
+        min_axial_hz: omegas.iter().copied().fold(f64::INFINITY, f64::min) / TWO_PI,
+        max_axial_hz: omegas.iter().copied().fold(0.0, f64::max) / TWO_PI,
+        samples,
+    })
+}
+
+/// Excitation of an ideal trajectory, the well sitting exactly on each
+/// waypoint at its target frequency. Sample it finely compared to the
+/// secular period.
+pub fn trajectory_excitation(traj: &Trajectory) -> Result<ExcitationReport> {
+    let wps = &traj.waypoints;
+    let mut positions = vec![0.0];
+    for w in wps.windows(2) {
+        let axis = (w[0].axial_dir.unit() + w[1].axial_dir.unit()).unit();
+        positions.push(positions[positions.len() - 1] + (w[1].r - w[0].r).dot(axis));
+    }
+    let omegas: Vec<f64> = wps.iter().map(|wp| wp.omega_axial).collect();
+    coherent_excitation(&traj.times, &positions, &omegas, wps[0].species.mass())
+}
+
+/// Excitation of a timed waveform as the model sees it: the well follows the
+/// potential minimum under the interpolated voltages and its frequency comes
+/// from `TrapModel::hess_total` along the waypoints' axis.
+pub fn transport_excitation(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    timed: &TimedWaveform,
+    opts: &ExcitationOptions,
+) -> Result<ExcitationReport> {
+    if !(opts.samples_per_period.is_finite() && opts.samples_per_period >= 1.0) {
+        return Err(IonwaveError::InvalidInput(format!("samples_per_period {} must be finite and at least 1", opts.samples_per_period)));
+    }
+    let drive = Drive::new(model, waypoints, timed, opts.kind)?;
+    let species = waypoints[0].species;
+    let (t0, t1) = drive.span;
+    let fastest = waypoints.iter().map(|wp| wp.omega_axial).fold(0.0, f64::max);
+    let n = ((t1 - t0) * fastest / TWO_PI * opts.samples_per_period).ceil();
+    if n.is_nan() || n >= MAX_SAMPLES {
+        return Err(IonwaveError::InvalidInput(format!("{:e} samples needed, at most {:e} supported", n, MAX_SAMPLES)));
+    }
+    let n = n as usize + 1;
+    let times: Vec<f64> = uniform_times(n.max(4 * waypoints.len()), t1 - t0).into_iter().map(|t| t0 + t).collect();
+
+    let mut well = waypoints[0].r;
+    let mut positions = Vec::with_capacity(times.len());
+    let mut omegas = Vec::with_capacity(times.len());
+    let mut prev: Option<(Vec3, Vec3)> = None;
+    for &t in &times {
+        let volts = drive.volts(t);
+        well = equilibrium(model, &volts, well)?;
+        // axis interpolated between the waypoints either side
+        let i = timed.times.partition_point(|&x| x <= t).clamp(1, waypoints.len() - 1) - 1;
+        let f = ((t - timed.times[i]) / (timed.times[i + 1] - timed.times[i])).clamp(0.0, 1.0);
+        let axis = (waypoints[i].axial_dir.unit() * (1.0 - f) + waypoints[i + 1].axial_dir.unit() * f).unit();
+        let curvature = model.hess_total(well, &volts).quad(axis);
+        omegas.push((species.charge_to_mass() * curvature).max(0.0).sqrt());
+        positions.push(match prev {
+            None => 0.0,
+            Some((r, a)) => positions[positions.len() - 1] + (well - r).dot((a + axis).unit()),
+        });
+        prev = Some((well, axis));
+    }
+    coherent_excitation(&times, &positions, &omegas, species.mass())
+}
+
+/// The built in velocity profiles, with a few erf widths, tried by
+/// `optimize_profile`.
+pub fn candidate_profiles() -> Vec<VelocityProfile> {
+    let mut out = vec![
+        VelocityProfile::Linear,
+        VelocityProfile::SinSquared,
+        VelocityProfile::MinimumJerk,
+        VelocityProfile::BangBang,
+    ];
+    out.extend([0.1, 0.125, 0.15, 0.175, 0.2, 0.25].map(|width| VelocityProfile::Erf { width }));
+    out
+}
+
+/// Pick the velocity profile that leaves the fewest phonons after moving
+/// along `path` in `duration` at a constant axial frequency `omega_axial`.
+pub fn optimize_profile(
+    path: &TransportPath,
+    duration: f64,
+    omega_axial: f64,
+    species: IonSpecies,
+) -> Result<(VelocityProfile, ExcitationReport)> {
+    let n = (duration * omega_axial / TWO_PI * 40.0).ceil().max(1.0) as usize + 1;
+    let mut best: Option<(VelocityProfile, ExcitationReport)> = None;
+    for profile in candidate_profiles() {
+        let traj = Trajectory::generate(path, duration, profile, n, omega_axial, species)?;
+        let report = trajectory_excitation(&traj)?;
+        if best.as_ref().is_none_or(|(_, b)| report.mean_phonons < b.mean_phonons) {
+            best = Some((profile, report));
+        }
+    }
+    Ok(best.expect("at least one candidate profile"))
+}
@@ -0,0 +1,269 @@
+// src/ffi.rs
+//
+// C ABI over the cdylib. A model is loaded from a config file into an opaque
+// handle; solves write row-major (n_waypoints x n_electrodes) voltages into
+// caller-owned buffers. Every fallible call returns an IonwaveStatus, the
+// message of the last failure on the calling thread is kept for
+// `ionwave_last_error`. The matching header is include/ionwave.h.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use crate::basis::TrapModel;
+use crate::c2lr::solve_waveform;
+use crate::config::{validate_waypoint, Config};
+use crate::lsq::LsqOptions;
+use crate::species::IonSpecies;
+use crate::types::{IonwaveError, Vec3, Waypoint};
+
+/// Status codes, one per `IonwaveError` variant plus ABI-level failures.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IonwaveStatus {
+    Ok = 0,
+    InvalidInput = 1,
+    Solver = 2,
+    Config = 3,
+    Io = 4,
+    DataFrame = 5,
+    NullPointer = 6,
+    BufferTooSmall = 7,
+    Panic = 8,
+}
+
+impl From<&IonwaveError> for IonwaveStatus {
+    fn from(e: &IonwaveError) -> Self {
+        match e {
+            IonwaveError::InvalidInput(_) => IonwaveStatus::InvalidInput,
+            IonwaveError::Solver(_) => IonwaveStatus::Solver,
+            IonwaveError::Config(_) => IonwaveStatus::Config,
+            IonwaveError::Io(_) => IonwaveStatus::Io,
+            IonwaveError::Polars(_) => IonwaveStatus::DataFrame,
+        }
+    }
+}
+
+/// Opaque handle: the model, the config's waypoints and solver options.
+pub struct IonwaveModel {
+    model: TrapModel,
+    waypoints: Vec<Waypoint>,
+    solver: LsqOptions,
+    names: Vec<CString>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(msg: String) {
+    let msg = CString::new(msg).unwrap_or_else(|e| {
+        let end = e.nul_position();
+        CString::new(&e.into_vec()[..end]).unwrap()
+    });
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(msg));
+}
+
+fn fail(status: IonwaveStatus, msg: impl Into<String>) -> IonwaveStatus {
+    set_error(msg.into());
+    status
+}
+
+/// run `f`, turning errors and panics into status codes
+fn guard(f: impl FnOnce() -> Result<(), IonwaveStatus>) -> IonwaveStatus {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => IonwaveStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => fail(IonwaveStatus::Panic, "panic inside ionwave"),
+    }
+}
+
+fn check(e: IonwaveError) -> IonwaveStatus {
+    let status = IonwaveStatus::from(&e);
+    fail(status, e.to_string())
+}
+
+unsafe fn str_arg<'a>(p: *const c_char, what: &str) -> Result<&'a str, IonwaveStatus> {
+    if p.is_null() { return Err(fail(IonwaveStatus::NullPointer, format!("{} is null", what))); }
+    CStr::from_ptr(p).to_str().map_err(|_| fail(IonwaveStatus::InvalidInput, format!("{} is not utf-8", what)))
+}
+
+fn model_ref<'a>(model: *const IonwaveModel) -> Result<&'a IonwaveModel, IonwaveStatus> {
+    // SAFETY: non-null handles come from ionwave_model_load
+    unsafe { model.as_ref() }.ok_or_else(|| fail(IonwaveStatus::NullPointer, "model is null"))
+}
+
+unsafe fn write_out(h: &IonwaveModel, wps: &[Waypoint], left: bool, out: *mut f64, out_len: usize) -> Result<(), IonwaveStatus> {
+    if out.is_null() { return Err(fail(IonwaveStatus::NullPointer, "output buffer is null")); }
+    let n_el = h.model.n_electrodes();
+    let needed = wps.len() * n_el;
+    if out_len < needed {
+        return Err(fail(IonwaveStatus::BufferTooSmall, format!("output needs {} values, got {}", needed, out_len)));
+    }
+    let volts = solve_waveform(&h.model, wps, left, &h.solver).map_err(check)?;
+    let out = std::slice::from_raw_parts_mut(out, needed);
+    for (dst, row) in out.chunks_exact_mut(n_el.max(1)).zip(&volts) { dst.copy_from_slice(row); }
+    Ok(())
+}
+
+/// Load a .toml or .json config into a new handle stored in `*out`.
+///
+/// # Safety
+/// `path` must be a nul-terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ionwave_model_load(path: *const c_char, out: *mut *mut IonwaveModel) -> IonwaveStatus {
+    guard(|| {
+        if out.is_null() { return Err(fail(IonwaveStatus::NullPointer, "out is null")); }
+        let path = str_arg(path, "path")?;
+        let cfg = Config::load(path).map_err(check)?;
+        let (model, waypoints) = cfg.build().map_err(check)?;
+        let names = model.electrodes.iter()
+            .map(|e| CString::new(e.name.replace('\0', "")).unwrap())
+            .collect();
+        *out = Box::into_raw(Box::new(IonwaveModel { model, waypoints, solver: cfg.solver, names }));
+        Ok(())
+    })
+}
+
+/// Release a handle; null is ignored.
+///
+/// # Safety
+/// `model` must come from `ionwave_model_load` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ionwave_model_free(model: *mut IonwaveModel) {
+    if !model.is_null() { drop(Box::from_raw(model)); }
+}
+
+/// # Safety
+/// `model` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn ionwave_model_n_electrodes(model: *const IonwaveModel) -> usize {
+    model.as_ref().map_or(0, |h| h.model.n_electrodes())
+}
+
+/// number of waypoints listed in the config
+///
+/// # Safety
+/// `model` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn ionwave_model_n_waypoints(model: *const IonwaveModel) -> usize {
+    model.as_ref().map_or(0, |h| h.waypoints.len())
+}
+
+/// Electrode name, owned by the handle, or null if `index` is out of range.
+///
+/// # Safety
+/// `model` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn ionwave_model_electrode_name(model: *const IonwaveModel, index: usize) -> *const c_char {
+    model.as_ref().and_then(|h| h.names.get(index)).map_or(std::ptr::null(), |s| s.as_ptr())
+}
+
+/// Override the config's solver options. A non-finite `voltage_limit`
+/// removes the clamp.
+///
+/// # Safety
+/// `model` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn ionwave_model_set_solver(model: *mut IonwaveModel, lambda: f64, voltage_limit: f64, iters: usize) -> IonwaveStatus {
+    guard(|| {
+        let h = model.as_mut().ok_or_else(|| fail(IonwaveStatus::NullPointer, "model is null"))?;
+        if lambda.is_nan() || lambda < 0.0 || iters == 0 {
+            return Err(fail(IonwaveStatus::InvalidInput, "lambda must be >= 0 and iters > 0"));
+        }
+        h.solver.lambda = lambda;
+        h.solver.voltage_limit = Some(voltage_limit).filter(|v| v.is_finite());
+        h.solver.iters = iters;
+        Ok(())
+    })
+}
+
+/// Solve the config's waypoints into `out` (`n_waypoints * n_electrodes`).
+///
+/// # Safety
+/// `out` must point to at least `out_len` writable doubles.
+#[no_mangle]
+pub unsafe extern "C" fn ionwave_solve(model: *const IonwaveModel, left: bool, out: *mut f64, out_len: usize) -> IonwaveStatus {
+    guard(|| {
+        let h = model_ref(model)?;
+        write_out(h, &h.waypoints, left, out, out_len)
+    })
+}
+
+/// Solve caller supplied waypoints: `positions` holds `n` (x, y, z) triples
+/// in metres, `omega_axial` `n` target frequencies in rad/s along z.
+/// `species` such as "40Ca+" may be null for the config's species (or 171Yb+).
+/// Non-finite positions or frequencies that are not positive give InvalidInput.
+///
+/// # Safety
+/// `positions` must hold `3 * n` doubles, `omega_axial` `n`, `out` `out_len`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn ionwave_solve_waypoints(
+    model: *const IonwaveModel,
+    positions: *const f64,
+    omega_axial: *const f64,
+    n: usize,
+    species: *const c_char,
+    left: bool,
+    out: *mut f64,
+    out_len: usize,
+) -> IonwaveStatus {
+    guard(|| {
+        let h = model_ref(model)?;
+        if n > 0 && (positions.is_null() || omega_axial.is_null()) {
+            return Err(fail(IonwaveStatus::NullPointer, "positions or omega_axial is null"));
+        }
+        let species = if species.is_null() {
+            h.waypoints.first().map_or_else(IonSpecies::yb171, |w| w.species)
+        } else {
+            str_arg(species, "species")?.parse().map_err(check)?
+        };
+        let wps: Vec<Waypoint> = (0..n).map(|i| {
+            let p = std::slice::from_raw_parts(positions.add(3 * i), 3);
+            Waypoint {
+                r: Vec3 { x: p[0], y: p[1], z: p[2] },
+                omega_axial: *omega_axial.add(i),
+                axial_dir: Vec3 { x: 0.0, y: 0.0, z: 1.0 },
+                species,
+            }
+        }).collect();
+        for (i, wp) in wps.iter().enumerate() {
+            // bad caller data, not a bad config file
+            validate_waypoint(i, wp).map_err(|e| fail(IonwaveStatus::InvalidInput, e.to_string()))?;
+        }
+        write_out(h, &wps, left, out, out_len)
+    })
+}
+
+/// Message of the last failed call on this thread, null after a success.
+/// Valid until the next ionwave call on the same thread.
+#[no_mangle]
+pub extern "C" fn ionwave_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(std::ptr::null(), |s| s.as_ptr()))
+}
+
+/// Static name of a status code. Takes the plain integer so a value outside
+/// the enum, e.g. from a newer library, gives "unknown" rather than UB.
+#[no_mangle]
+pub extern "C" fn ionwave_status_name(status: c_int) -> *const c_char {
+    const NAMES: [(IonwaveStatus, &CStr); 9] = [
+        (IonwaveStatus::Ok, c"ok"),
+        (IonwaveStatus::InvalidInput, c"invalid input"),
+        (IonwaveStatus::Solver, c"solver failure"),
+        (IonwaveStatus::Config, c"config error"),
+        (IonwaveStatus::Io, c"io error"),
+        (IonwaveStatus::DataFrame, c"data frame error"),
+        (IonwaveStatus::NullPointer, c"null pointer"),
+        (IonwaveStatus::BufferTooSmall, c"buffer too small"),
+        (IonwaveStatus::Panic, c"panic"),
+    ];
+    NAMES.iter().find(|(s, _)| *s as c_int == status).map_or(c"unknown", |(_, name)| name).as_ptr()
+}
+
+/// crate version, e.g. "0.1.0"
+#[no_mangle]
+pub extern "C" fn ionwave_version() -> *const c_char {
+    const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+    VERSION.as_ptr() as *const c_char
+}
@@ -0,0 +1,389 @@
+// src/filter.rs
+//
+// Low-pass filters between the DAC and the electrodes. Each channel's filter
+// is linear and time invariant on the sample grid of the DAC streams,
+//
+//   Σ_k a_k y[n-k] = Σ_k b_k x[n-k],   a_0 = 1,
+//
+// and starts settled on the first sample, the DAC having held it before the
+// waveform begins. Pre-distortion inverts the filter: it finds the DAC output
+// whose filtered response is the desired waveform, within the channel limits.
+
+use serde::{Deserialize, Serialize};
+use crate::analysis::analyze;
+use crate::basis::TrapModel;
+use crate::spline::SplineKind;
+use crate::timing::{SampleStreams, TimedWaveform};
+use crate::types::{IonwaveError, Result, Waypoint};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+/// Serializable description of one channel's filter, tagged by `type`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FilterSpec {
+    /// first order RC low-pass behind the held DAC output, -3 dB at `cutoff` (Hz)
+    Rc { cutoff: f64 },
+    /// difference equation coefficients for samples at `sample_rate` (Hz)
+    Iir { b: Vec<f64>, a: Vec<f64>, sample_rate: f64 },
+    /// impulse response at `sample_rate` (Hz)
+    Fir { taps: Vec<f64>, sample_rate: f64 },
+}
+
+impl FilterSpec {
+    pub fn validate(&self) -> Result<()> {
+        let bad = |msg: String| Err(IonwaveError::Config(msg));
+        let finite = |c: &[f64]| c.iter().all(|v| v.is_finite());
+        match self {
+            FilterSpec::Rc { cutoff } => {
+                if !(cutoff.is_finite() && *cutoff > 0.0) { return bad(format!("cutoff {} must be positive", cutoff)); }
+            }
+            FilterSpec::Iir { b, a, sample_rate } => {
+                if !(sample_rate.is_finite() && *sample_rate > 0.0) { return bad(format!("sample rate {} must be positive", sample_rate)); }
+                if b.is_empty() || !finite(b) || !finite(a) { return bad("b needs finite coefficients, a finite ones".to_string()); }
+                if a.first().is_none_or(|a0| *a0 == 0.0) { return bad("a needs a non-zero leading coefficient".to_string()); }
+                if !roots_inside_unit_circle(a) { return bad(format!("a = {:?} has a pole on or outside the unit circle", a)); }
+            }
+            FilterSpec::Fir { taps, sample_rate } => {
+                if !(sample_rate.is_finite() && *sample_rate > 0.0) { return bad(format!("sample rate {} must be positive", sample_rate)); }
+                if taps.is_empty() || !finite(taps) { return bad("taps must be finite, at least one".to_string()); }
+            }
+        }
+        Ok(())
+    }
+
+    /// Power transmitted at frequency `f` (Hz), |H(f)|²: the analog response
+    /// of an RC filter, the response on their own sample grid for the others.
+    pub fn power_gain(&self, f: f64) -> f64 {
+        // |Σ c_k e^{-ikθ}|²
+        let power = |c: &[f64], theta: f64| {
+            let (re, im) = c.iter().enumerate().fold((0.0, 0.0), |(re, im), (k, v)| {
+                let (s, co) = (k as f64 * theta).sin_cos();
+                (re + v * co, im - v * s)
+            });
+            re * re + im * im
+        };
+        match self {
+            FilterSpec::Rc { cutoff } => 1.0 / (1.0 + (f / cutoff).powi(2)),
+            FilterSpec::Iir { b, a, sample_rate } => {
+                let theta = TWO_PI * f / sample_rate;
+                power(b, theta) / power(a, theta)
+            }
+            FilterSpec::Fir { taps, sample_rate } => power(taps, TWO_PI * f / sample_rate),
+        }
+    }
+
+    /// The filter on a grid sampled at `rate`. RC filters are discretised
+    /// exactly for a held input; IIR and FIR filters must have been designed
+    /// for that rate.
+    pub fn discretize(&self, rate: f64) -> Result<LinearFilter> {
+        self.validate()?;
+        let check = |sample_rate: f64| {
+            if (sample_rate / rate - 1.0).abs() > 1e-9 {
+                return Err(IonwaveError::InvalidInput(format!("filter designed for {} Hz, streams sampled at {} Hz", sample_rate, rate)));
+            }
+            Ok(())
+        };
+        match self {
+            FilterSpec::Rc { cutoff } => {
+                let decay = (-TWO_PI * cutoff / rate).exp();
+                LinearFilter::new(vec![0.0, 1.0 - decay], vec![1.0, -decay])
+            }
+            FilterSpec::Iir { b, a, sample_rate } => {
+                check(*sample_rate)?;
+                LinearFilter::new(b.clone(), a.clone())
+            }
+            FilterSpec::Fir { taps, sample_rate } => {
+                check(*sample_rate)?;
+                LinearFilter::new(taps.clone(), vec![1.0])
+            }
+        }
+    }
+}
+
+/// Schur-Cohn test: every root of c_0 z^n + … + c_n strictly inside the unit
+/// circle, by stepping the polynomial down through its reflection coefficients.
+/// On `a` these are the filter's poles, on `b` past its delay its zeros.
+fn roots_inside_unit_circle(c: &[f64]) -> bool {
+    let mut p: Vec<f64> = c.to_vec();
+    // trailing zeros are roots at the origin
+    while p.len() > 1 && p[p.len() - 1] == 0.0 { p.pop(); }
+    while p.len() > 1 {
+        let n = p.len() - 1;
+        let k = p[n] / p[0];
+        if k.abs() >= 1.0 { return false; }
+        p = (0..n).map(|i| (p[i] - k * p[n - i]) / (1.0 - k * k)).collect();
+    }
+    true
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelFilter {
+    pub channel: usize,
+    pub filter: FilterSpec,
+}
+
+/// Filter of every DAC channel: its own entry if it has one, else `default`;
+/// without either the electrodes see the DAC output unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<FilterSpec>,
+    #[serde(default)]
+    pub channels: Vec<ChannelFilter>,
+}
+
+impl FilterConfig {
+    pub fn validate(&self) -> Result<()> {
+        let prefixed = |what: String, e: IonwaveError| IonwaveError::Config(format!("filter: {}: {}", what, e));
+        if let Some(f) = &self.default { f.validate().map_err(|e| prefixed("default".to_string(), e))?; }
+        for (i, c) in self.channels.iter().enumerate() {
+            c.filter.validate().map_err(|e| prefixed(format!("channel {}", c.channel), e))?;
+            if self.channels[..i].iter().any(|o| o.channel == c.channel) {
+                return Err(IonwaveError::Config(format!("filter: channel {} given twice", c.channel)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn channel(&self, channel: usize) -> Option<&FilterSpec> {
+        self.channels.iter().find(|c| c.channel == channel).map(|c| &c.filter).or(self.default.as_ref())
+    }
+}
+
+/// A discrete filter normalised to a_0 = 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearFilter {
+    pub b: Vec<f64>,
+    pub a: Vec<f64>,
+}
+
+impl LinearFilter {
+    pub fn new(b: Vec<f64>, a: Vec<f64>) -> Result<Self> {
+        let a0 = a.first().copied().unwrap_or(0.0);
+        if a0 == 0.0 || b.iter().all(|v| *v == 0.0) {
+            return Err(IonwaveError::InvalidInput("a filter needs a non-zero a_0 and some non-zero b".to_string()));
+        }
+        let f = Self { b: b.iter().map(|v| v / a0).collect(), a: a.iter().map(|v| v / a0).collect() };
+        let gain = f.dc_gain();
+        if !(gain.is_finite() && gain != 0.0) {
+            return Err(IonwaveError::InvalidInput(format!("filter dc gain {} must be finite and non-zero", gain)));
+        }
+        Ok(f)
+    }
+
+    /// steady output per volt of held input
+    pub fn dc_gain(&self) -> f64 { self.b.iter().sum::<f64>() / self.a.iter().sum::<f64>() }
+
+    /// Response to `x` from rest: zero input and output before it.
+    pub fn zero_state(&self, x: &[f64]) -> Vec<f64> {
+        let mut y = vec![0.0; x.len()];
+        for n in 0..x.len() {
+            let fwd: f64 = self.b.iter().zip(x[..=n].iter().rev()).map(|(b, v)| b * v).sum();
+            let back: f64 = self.a[1..].iter().zip(y[..n].iter().rev()).map(|(a, v)| a * v).sum();
+            y[n] = fwd - back;
+        }
+        y
+    }
+
+    /// Transpose of `zero_state`: the same filter run backwards in time.
+    fn adjoint(&self, r: &[f64]) -> Vec<f64> {
+        let mut rev: Vec<f64> = r.iter().rev().copied().collect();
+        rev = self.zero_state(&rev);
+        rev.reverse();
+        rev
+    }
+
+    /// Response to `x`, the filter having settled on `x[0]` before.
+    pub fn apply(&self, x: &[f64]) -> Vec<f64> {
+        let Some(&x0) = x.first() else { return Vec::new() };
+        let dx: Vec<f64> = x.iter().map(|v| v - x0).collect();
+        let settled = self.dc_gain() * x0;
+        self.zero_state(&dx).into_iter().map(|v| v + settled).collect()
+    }
+
+    /// Input within `[lo, hi]` whose response is nearest `y` in least
+    /// squares: the recursion inverting the filter when it stays inside the
+    /// bounds, else projected gradient from its clamped values. The first
+    /// input is the level the filter has settled on, `y[0]` over the dc gain,
+    /// and must lie within the bounds. The filter must be minimum phase, its
+    /// zeros inside the unit circle, or its inverse grows without bound.
+    pub fn invert(&self, y: &[f64], lo: f64, hi: f64, opts: &PredistortOptions) -> Result<Vec<f64>> {
+        let n = y.len();
+        if n == 0 { return Ok(Vec::new()); }
+        // input k first reaches the output at k + delay
+        let scale = self.b.iter().fold(0.0_f64, |m, v| m.max(v.abs()));
+        let delay = self.b.iter().position(|v| v.abs() > 1e-12 * scale).unwrap_or(0);
+        if !roots_inside_unit_circle(&self.b[delay..]) {
+            return Err(IonwaveError::InvalidInput(format!(
+                "b = {:?} has a zero on or outside the unit circle, the filter cannot be inverted", self.b)));
+        }
+        let gain = self.dc_gain();
+        let held = y[0] / gain;
+        if !(lo..=hi).contains(&held) {
+            return Err(IonwaveError::InvalidInput(format!(
+                "the first sample needs {} V held before the stream, outside [{}, {}]", held, lo, hi)));
+        }
+        let target: Vec<f64> = y.iter().map(|v| v - y[0]).collect();
+        let (dlo, dhi) = (lo - held, hi - held);
+
+        let bd = self.b[delay];
+        // outputs up to the delay stay at the settled level whatever follows
+        let reached: Vec<f64> = target.iter().enumerate().map(|(m, v)| if m <= delay { 0.0 } else { *v }).collect();
+        let mut x = vec![0.0; n];
+        for k in 1..n.saturating_sub(delay) {
+            let m = k + delay;
+            let fwd: f64 = self.a.iter().zip(reached[..=m].iter().rev()).map(|(a, v)| a * v).sum();
+            let past: f64 = self.b.iter().enumerate().skip(delay + 1)
+                .take_while(|(j, _)| *j <= m)
+                .map(|(j, b)| b * x[m - j])
+                .sum();
+            x[k] = (fwd - past) / bd;
+        }
+        // the last inputs only act after the stream ends: hold the final level
+        let tail = target[n - 1] / gain;
+        for v in x[n.saturating_sub(delay).max(1)..].iter_mut() { *v = tail; }
+        if x.iter().all(|v| v.is_finite() && (dlo..=dhi).contains(v)) {
+            return Ok(x.into_iter().map(|v| v + held).collect());
+        }
+
+        // bounded least squares by projected gradient with Barzilai-Borwein steps
+        let project = |x: &mut [f64]| {
+            for v in x[1..].iter_mut() { *v = if v.is_finite() { v.clamp(dlo, dhi) } else { 0.0_f64.clamp(dlo, dhi) }; }
+        };
+        project(&mut x);
+        let residual = |x: &[f64]| -> Vec<f64> { self.zero_state(x).iter().zip(&target).map(|(a, b)| a - b).collect() };
+        let cost = |r: &[f64]| 0.5 * r.iter().map(|v| v * v).sum::<f64>();
+        let r = residual(&x);
+        let (mut f, mut g) = (cost(&r), self.adjoint(&r));
+        let mut step = 1.0 / (gain * gain).max(1e-12);
+        for _ in 0..opts.max_iter {
+            let mut accepted = None;
+            for _ in 0..40 {
+                let mut trial: Vec<f64> = x.iter().zip(&g).map(|(v, gv)| v - step * gv).collect();
+                trial[0] = 0.0;
+                project(&mut trial);
+                let tr = residual(&trial);
+                let tf = cost(&tr);
+                let descent: f64 = g.iter().zip(x.iter().zip(&trial)).map(|(gv, (a, b))| gv * (a - b)).sum();
+                if tf <= f - 1e-4 * descent {
+                    accepted = Some((trial, tr, tf));
+                    break;
+                }
+                step *= 0.5;
+            }
+            let Some((trial, tr, tf)) = accepted else { break };
+            let tg = self.adjoint(&tr);
+            let s: Vec<f64> = trial.iter().zip(&x).map(|(a, b)| a - b).collect();
+            let sy: f64 = s.iter().zip(tg.iter().zip(&g)).map(|(si, (a, b))| si * (a - b)).sum();
+            let ss: f64 = s.iter().map(|v| v * v).sum();
+            let moved = s.iter().fold(0.0_f64, |m, v| m.max(v.abs()));
+            step = if sy > 0.0 { ss / sy } else { 2.0 * step };
+            (x, f, g) = (trial, tf, tg);
+            if moved <= opts.tol { break; }
+        }
+        Ok(x.into_iter().map(|v| v + held).collect())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PredistortOptions {
+    pub kind: SplineKind,             // interpolation of the desired waveform between waypoints
+    pub voltage_limit: Option<f64>,   // V, symmetric, on top of each channel's own limits
+    pub max_iter: usize,              // projected gradient steps when the limits bind
+    pub tol: f64,                     // V, stop once no sample moves further
+}
+
+impl Default for PredistortOptions {
+    fn default() -> Self {
+        Self { kind: SplineKind::Monotone, voltage_limit: None, max_iter: 500, tol: 1e-9 }
+    }
+}
+
+/// How far what the electrodes see strays from the desired waveform.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct FilterError {
+    pub max_error_volts: f64,         // over every sample
+    pub max_field_error: f64,         // V/m, at the samples nearest the waypoints
+    pub max_axial_error_hz: f64,      // there too
+}
+
+#[derive(Clone, Debug)]
+pub struct Predistortion {
+    pub desired: SampleStreams,
+    pub dac: SampleStreams,           // to play
+    pub seen: SampleStreams,          // at the electrodes
+    pub uncorrected: FilterError,     // playing `desired` as it is
+    pub corrected: FilterError,       // playing `dac`
+    pub limited: usize,               // DAC samples held at a channel limit
+}
+
+/// Voltages the electrodes see when the DAC plays `streams`.
+pub fn filtered(streams: &SampleStreams, filters: &FilterConfig) -> Result<SampleStreams> {
+    filters.validate()?;
+    let samples = streams.channels.iter().zip(&streams.samples).map(|(&ch, s)| match filters.channel(ch) {
+        Some(spec) => Ok(spec.discretize(streams.rate)?.apply(s)),
+        None => Ok(s.clone()),
+    }).collect::<Result<Vec<_>>>()?;
+    Ok(SampleStreams { samples, ..streams.clone() })
+}
+
+/// DAC streams at `rate` whose filtered output follows `timed`, with the
+/// error of playing it uncorrected and corrected, checked through the model
+/// at the waypoints.
+pub fn predistort(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    timed: &TimedWaveform,
+    rate: f64,
+    filters: &FilterConfig,
+    opts: &PredistortOptions,
+) -> Result<Predistortion> {
+    filters.validate()?;
+    let map = timed.channel_map(model, waypoints)?;
+    let bounds = map.clamped_bounds(opts.voltage_limit);
+    let desired = timed.resample(rate, opts.kind)?;
+    let mut limited = 0;
+    let samples = desired.channels.iter().zip(&desired.samples).zip(&bounds).map(|((&ch, s), &(lo, hi))| {
+        let x = match filters.channel(ch) {
+            Some(spec) => spec.discretize(rate)?.invert(s, lo, hi, opts)?,
+            None => s.iter().map(|v| v.clamp(lo, hi)).collect(),
+        };
+        limited += x.iter().filter(|v| **v <= lo || **v >= hi).count();
+        Ok(x)
+    }).collect::<Result<Vec<_>>>()?;
+    let dac = SampleStreams { samples, ..desired.clone() };
+    let seen = filtered(&dac, filters)?;
+
+    // what the ion sees at the sample nearest each waypoint, against the desired voltages
+    let last = desired.n_samples() - 1;
+    let nearest: Vec<usize> = timed.times.iter().map(|t| (((t - desired.t0) * rate).round().max(0.0) as usize).min(last)).collect();
+    let at = |streams: &SampleStreams| -> Vec<Vec<f64>> {
+        nearest.iter().map(|&k| map.expand(&streams.samples.iter().map(|s| s[k]).collect::<Vec<_>>())).collect()
+    };
+    let wanted = at(&desired);
+    let ideal = analyze(model, waypoints, &wanted)?;
+    let check = |played: &SampleStreams| -> Result<FilterError> {
+        let max_error_volts = played.samples.iter().zip(&desired.samples)
+            .flat_map(|(a, b)| a.iter().zip(b).map(|(u, v)| (u - v).abs()))
+            .fold(0.0, f64::max);
+        let volts = at(played);
+        let actual = analyze(model, waypoints, &volts)?;
+        let max_field_error = waypoints.iter().zip(wanted.iter().zip(&volts))
+            .map(|(wp, (a, b))| (model.grad_total(wp.r, a) - model.grad_total(wp.r, b)).norm())
+            .fold(0.0, f64::max);
+        let max_axial_error_hz = ideal.iter().zip(&actual).map(|(a, b)| (a.axial_hz - b.axial_hz).abs()).fold(0.0, f64::max);
+        Ok(FilterError { max_error_volts, max_field_error, max_axial_error_hz })
+    };
+    Ok(Predistortion {
+        uncorrected: check(&filtered(&desired, filters)?)?,
+        corrected: check(&seen)?,
+        desired,
+        dac,
+        seen,
+        limited,
+    })
+}
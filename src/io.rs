@@ -1,25 +1,379 @@
 use std::fs::{create_dir_all, File};
-use std::io::Write;
+use std::io::{BufRead, BufReader, Write};
+use crate::analysis::analyze;
+use crate::basis::TrapModel;
+use crate::channels::ChannelWaveform;
+use crate::dac::{DacCodes, DacImageFormat};
+use crate::constraints::{build_constraints, N_ROWS};
+use crate::lsq::LsqOptions;
+use crate::piecewise::PiecewiseExport;
+use crate::spline::SplineKind;
+use crate::timing::SampleStreams;
+use crate::npy::{self, NpyArray};
+use crate::types::{IonwaveError, Result, Waypoint};
+use polars::prelude::{DataFrame, LazyFrame, NamedFrom, ParquetWriter, ScanArgsParquet, Series};
+use serde::{Deserialize, Serialize};
 
-pub fn write_csv(path: &str, data: &[Vec<f64>]) -> std::io::Result<()> {
+fn create(path: &str) -> std::io::Result<File> {
     if let Some(dir) = std::path::Path::new(path).parent() {
         if !dir.as_os_str().is_empty() { let _ = create_dir_all(dir); }
     }
-    let mut f = File::create(path)?;
-    if data.is_empty() { return Ok(()); }
-    let n_el = data[0].len();
-    // header
-    for j in 0..n_el {
+    File::create(path)
+}
+
+fn write_rows<S: AsRef<str>>(f: &mut File, header: &[S], data: &[Vec<f64>]) -> std::io::Result<()> {
+    for (j, h) in header.iter().enumerate() {
         if j > 0 { write!(f, ",")?; }
-        write!(f, "e{}", j)?;
+        write!(f, "{}", h.as_ref())?;
     }
     writeln!(f)?;
     for row in data {
-        for j in 0..n_el {
+        for (j, v) in row.iter().take(header.len()).enumerate() {
             if j > 0 { write!(f, ",")?; }
-            write!(f, "{}", row[j])?;
+            write!(f, "{}", v)?;
         }
         writeln!(f)?;
     }
     Ok(())
 }
+
+pub fn write_csv(path: &str, data: &[Vec<f64>]) -> std::io::Result<()> {
+    let mut f = create(path)?;
+    if data.is_empty() { return Ok(()); }
+    let header: Vec<String> = (0..data[0].len()).map(|j| format!("e{}", j)).collect();
+    write_rows(&mut f, &header, data)
+}
+
+/// numeric table with an explicit header
+pub fn write_table_csv<S: AsRef<str>>(path: &str, header: &[S], rows: &[Vec<f64>]) -> std::io::Result<()> {
+    let mut f = create(path)?;
+    write_rows(&mut f, header, rows)
+}
+
+/// Like `write_csv` but headed by the model's electrode names. Refuses to
+/// write rows that break an electrode's limits or drive a disabled electrode.
+pub fn write_model_csv(path: &str, model: &TrapModel, data: &[Vec<f64>]) -> Result<()> {
+    for row in data { model.check_voltages(row)?; }
+    let mut f = create(path)?;
+    let header: Vec<&str> = model.electrodes.iter().map(|e| e.name.as_str()).collect();
+    write_rows(&mut f, &header, data)?;
+    Ok(())
+}
+
+/// channel-level voltages of a ganged waveform, headed ch<id>
+pub fn write_channel_csv(path: &str, wf: &ChannelWaveform) -> std::io::Result<()> {
+    let mut f = create(path)?;
+    let header: Vec<String> = wf.channels.iter().map(|c| format!("ch{}", c)).collect();
+    write_rows(&mut f, &header, &wf.channel_volts)
+}
+
+/// read a waveform csv back as (header, rows)
+pub fn read_csv(path: &str) -> Result<(Vec<String>, Vec<Vec<f64>>)> {
+    let bad = |line: usize, msg: String| IonwaveError::InvalidInput(format!("{}:{}: {}", path, line, msg));
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header: Vec<String> = match lines.next() {
+        Some(h) => h?.split(',').map(|s| s.trim().to_string()).collect(),
+        None => return Ok((Vec::new(), Vec::new())),
+    };
+    let mut rows = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+        let row = line.split(',')
+            .map(|s| s.trim().parse::<f64>().map_err(|e| bad(i + 2, format!("{}: '{}'", e, s))))
+            .collect::<Result<Vec<f64>>>()?;
+        if row.len() != header.len() {
+            return Err(bad(i + 2, format!("{} values for {} columns", row.len(), header.len())));
+        }
+        rows.push(row);
+    }
+    Ok((header, rows))
+}
+
+/// read a waveform written by `write_model_csv`, columns matched to the
+/// model's electrodes by name so column order does not matter
+pub fn read_model_csv(path: &str, model: &TrapModel) -> Result<Vec<Vec<f64>>> {
+    let (header, rows) = read_csv(path)?;
+    let cols = model.electrodes.iter().map(|e| {
+        header.iter().position(|h| *h == e.name).ok_or_else(|| {
+            IonwaveError::InvalidInput(format!("{}: no column for electrode {}", path, e.name))
+        })
+    }).collect::<Result<Vec<usize>>>()?;
+    Ok(rows.iter().map(|r| cols.iter().map(|&c| r[c]).collect()).collect())
+}
+
+/// Provenance of a waveform, stored in a json sidecar next to its csv.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WaveformMetadata {
+    pub crate_version: String,
+    pub geometry_hash: String,
+    pub electrodes: Vec<String>,
+    pub waypoints: Vec<Waypoint>,
+    pub solver: LsqOptions,
+    pub left: bool,
+    pub created_unix: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playback: Option<Playback>,   // set when the rows were tuned for one playback
+}
+
+/// Interpolation and duration a waveform's rows were shaped for.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Playback {
+    pub kind: SplineKind,
+    pub duration: f64,    // s
+}
+
+impl WaveformMetadata {
+    pub fn new(model: &TrapModel, waypoints: &[Waypoint], solver: &LsqOptions, left: bool) -> Self {
+        let created_unix = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            geometry_hash: model.geometry_hash(),
+            electrodes: model.electrodes.iter().map(|e| e.name.clone()).collect(),
+            waypoints: waypoints.to_vec(),
+            solver: solver.clone(),
+            left,
+            created_unix,
+            playback: None,
+        }
+    }
+
+    /// error unless the waveform was produced for this model's geometry
+    pub fn verify(&self, model: &TrapModel) -> Result<()> {
+        let hash = model.geometry_hash();
+        if self.geometry_hash != hash {
+            return Err(IonwaveError::InvalidInput(format!(
+                "waveform was solved for geometry {} but the model is {}", self.geometry_hash, hash)));
+        }
+        Ok(())
+    }
+}
+
+/// waveforms.csv -> waveforms.meta.json
+pub fn sidecar_path(csv_path: &str) -> std::path::PathBuf {
+    std::path::Path::new(csv_path).with_extension("meta.json")
+}
+
+/// write a named-column csv and its metadata sidecar
+pub fn write_waveform(path: &str, model: &TrapModel, data: &[Vec<f64>], meta: &WaveformMetadata) -> Result<()> {
+    if meta.waypoints.len() != data.len() {
+        return Err(IonwaveError::InvalidInput(format!(
+            "{} waveform rows for {} waypoints", data.len(), meta.waypoints.len())));
+    }
+    write_model_csv(path, model, data)?;
+    let json = serde_json::to_string_pretty(meta).map_err(|e| IonwaveError::InvalidInput(e.to_string()))?;
+    std::fs::write(sidecar_path(path), json)?;
+    Ok(())
+}
+
+/// read a waveform csv and its sidecar, verified against the model; a csv
+/// without a sidecar is an error, read it with `read_model_csv` to skip the check
+pub fn read_waveform(path: &str, model: &TrapModel) -> Result<(Vec<Vec<f64>>, WaveformMetadata)> {
+    let data = read_model_csv(path, model)?;
+    let sidecar = sidecar_path(path);
+    if !sidecar.exists() {
+        return Err(IonwaveError::InvalidInput(format!("{}: no metadata sidecar {}", path, sidecar.display())));
+    }
+    let meta: WaveformMetadata = serde_json::from_str(&std::fs::read_to_string(&sidecar)?)
+        .map_err(|e| IonwaveError::InvalidInput(format!("{}: {}", sidecar.display(), e)))?;
+    meta.verify(model)?;
+    if meta.waypoints.len() != data.len() {
+        return Err(IonwaveError::InvalidInput(format!(
+            "{}: {} rows but metadata lists {} waypoints", path, data.len(), meta.waypoints.len())));
+    }
+    Ok((data, meta))
+}
+
+/// Per-waypoint diagnostics as a data frame, one row per waypoint: index,
+/// position, time (null when `times` is None), one voltage column per
+/// electrode named `v_<name>`, achieved field, secular frequencies and the
+/// constraint residual. The prefix keeps an electrode called e.g. `x` or
+/// `residual` from clashing with the fixed columns.
+pub fn diagnostics_frame(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    volts: &[Vec<f64>],
+    times: Option<&[f64]>,
+) -> Result<DataFrame> {
+    let reports = analyze(model, waypoints, volts)?;
+    if let Some(t) = times {
+        if t.len() != waypoints.len() {
+            return Err(IonwaveError::InvalidInput(format!(
+                "{} times for {} waypoints", t.len(), waypoints.len())));
+        }
+    }
+    let col = |name: &str, f: &dyn Fn(usize) -> f64| Series::new(name, (0..reports.len()).map(f).collect::<Vec<f64>>());
+
+    let mut columns = vec![
+        Series::new("waypoint", reports.iter().map(|r| r.index as u32).collect::<Vec<u32>>()),
+        col("x", &|i| reports[i].r.x),
+        col("y", &|i| reports[i].r.y),
+        col("z", &|i| reports[i].r.z),
+        Series::new("time", (0..reports.len()).map(|i| times.map(|t| t[i])).collect::<Vec<Option<f64>>>()),
+    ];
+    for (j, e) in model.electrodes.iter().enumerate() {
+        columns.push(col(&format!("v_{}", e.name), &|i| volts[i][j]));
+    }
+    columns.extend([
+        col("field", &|i| reports[i].field),
+        col("axial_hz", &|i| reports[i].axial_hz),
+        col("target_axial_hz", &|i| reports[i].target_axial_hz),
+        col("secular_0_hz", &|i| reports[i].secular_hz[0]),
+        col("secular_1_hz", &|i| reports[i].secular_hz[1]),
+        col("secular_2_hz", &|i| reports[i].secular_hz[2]),
+        col("residual", &|i| reports[i].residual),
+    ]);
+    Ok(DataFrame::new(columns)?)
+}
+
+/// write `diagnostics_frame` to a parquet file
+pub fn write_parquet(
+    path: &str,
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    volts: &[Vec<f64>],
+    times: Option<&[f64]>,
+) -> Result<()> {
+    write_frame(path, &mut diagnostics_frame(model, waypoints, volts, times)?)
+}
+
+/// write any data frame to a parquet file, e.g. a `diagnostics_frame` with
+/// extra columns added
+pub fn write_frame(path: &str, df: &mut DataFrame) -> Result<()> {
+    ParquetWriter::new(create(path)?).finish(df)?;
+    Ok(())
+}
+
+/// Lazily scan one parquet file or a glob of them, e.g. every point of a
+/// sweep written to `sweep/*.parquet`; nothing is read until collected.
+pub fn scan_parquet(path: &str) -> Result<LazyFrame> {
+    Ok(LazyFrame::scan_parquet(path, ScanArgsParquet::default())?)
+}
+
+/// write a single array as a .npy file
+pub fn write_npy(path: &str, array: &NpyArray) -> Result<()> {
+    create(path)?.write_all(&array.to_bytes())?;
+    Ok(())
+}
+
+pub fn read_npy(path: &str) -> Result<NpyArray> {
+    NpyArray::from_bytes(&std::fs::read(path)?)
+}
+
+/// Waypoint arrays shared by the npz writers: `r` (n, 3), `omega_axial` (n,),
+/// `axial_dir` (n, 3) and `charge_to_mass` (n,), all SI.
+fn waypoint_arrays(waypoints: &[Waypoint]) -> Result<Vec<(&'static str, NpyArray)>> {
+    let n = waypoints.len();
+    Ok(vec![
+        ("r", NpyArray::f64(vec![n, 3], waypoints.iter().flat_map(|w| [w.r.x, w.r.y, w.r.z]).collect())?),
+        ("omega_axial", NpyArray::f64(vec![n], waypoints.iter().map(|w| w.omega_axial).collect())?),
+        ("axial_dir", NpyArray::f64(vec![n, 3], waypoints.iter().flat_map(|w| [w.axial_dir.x, w.axial_dir.y, w.axial_dir.z]).collect())?),
+        ("charge_to_mass", NpyArray::f64(vec![n], waypoints.iter().map(|w| w.species.charge_to_mass()).collect())?),
+    ])
+}
+
+/// Write a waveform as an .npz: `voltages` (n_waypoints, n_electrodes),
+/// `electrodes` (names, in column order) and the waypoint arrays.
+pub fn write_waveform_npz(path: &str, model: &TrapModel, waypoints: &[Waypoint], volts: &[Vec<f64>]) -> Result<()> {
+    if volts.len() != waypoints.len() || volts.iter().any(|v| v.len() != model.n_electrodes()) {
+        return Err(IonwaveError::InvalidInput(format!(
+            "waveform shape does not match {} waypoints x {} electrodes", waypoints.len(), model.n_electrodes())));
+    }
+    let names: Vec<String> = model.electrodes.iter().map(|e| e.name.clone()).collect();
+    let mut arrays = vec![
+        ("voltages", NpyArray::from_rows(volts)?),
+        ("electrodes", NpyArray::Str { shape: vec![names.len()], data: names }),
+    ];
+    arrays.extend(waypoint_arrays(waypoints)?);
+    npy::write_npz(std::io::BufWriter::new(create(path)?), &arrays)
+}
+
+/// Write the per-waypoint constraint systems from `build_constraints` as an
+/// .npz: `A` (n_waypoints, 6, n_electrodes) and `b` (n_waypoints, 6), in the
+/// model's internal units, plus `electrodes` and the waypoint arrays.
+pub fn write_constraints_npz(path: &str, model: &TrapModel, waypoints: &[Waypoint]) -> Result<()> {
+    let n_el = model.n_electrodes();
+    let mut a = Vec::with_capacity(waypoints.len() * N_ROWS * n_el);
+    let mut b = Vec::with_capacity(waypoints.len() * N_ROWS);
+    for wp in waypoints {
+        let (aw, bw) = build_constraints(model, wp);
+        a.extend(aw.iter());
+        b.extend(bw.iter());
+    }
+    let names: Vec<String> = model.electrodes.iter().map(|e| e.name.clone()).collect();
+    let mut arrays = vec![
+        ("A", NpyArray::f64(vec![waypoints.len(), N_ROWS, n_el], a)?),
+        ("b", NpyArray::f64(vec![waypoints.len(), N_ROWS], b)?),
+        ("electrodes", NpyArray::Str { shape: vec![n_el], data: names }),
+    ];
+    arrays.extend(waypoint_arrays(waypoints)?);
+    npy::write_npz(std::io::BufWriter::new(create(path)?), &arrays)
+}
+
+/// read every member of an uncompressed .npz by name
+pub fn read_npz(path: &str) -> Result<Vec<(String, NpyArray)>> {
+    npy::read_npz(&std::fs::read(path)?)
+}
+
+/// write DAC codes as a memory image ready for upload, see `DacImageFormat`
+pub fn write_dac_image(path: &str, codes: &DacCodes, format: DacImageFormat) -> Result<()> {
+    let mut f = std::io::BufWriter::new(create(path)?);
+    match format {
+        DacImageFormat::Binary => {
+            for row in &codes.codes {
+                for (&k, &bits) in row.iter().zip(&codes.bits) {
+                    if bits <= 16 { f.write_all(&(k as u16).to_le_bytes())?; } else { f.write_all(&k.to_le_bytes())?; }
+                }
+            }
+        }
+        DacImageFormat::Hex => {
+            for row in &codes.codes {
+                let words: Vec<String> = row.iter().zip(&codes.bits)
+                    .map(|(k, bits)| format!("{:0w$x}", k, w = bits.div_ceil(4) as usize))
+                    .collect();
+                writeln!(f, "{}", words.join(" "))?;
+            }
+        }
+    }
+    f.flush()?;
+    Ok(())
+}
+
+/// Write sample streams as an .npz: `samples` (n_channels, n_samples),
+/// `channels`, `rate` [Hz] and `t0` [s].
+pub fn write_streams_npz(path: &str, streams: &SampleStreams) -> Result<()> {
+    let arrays = [
+        ("samples", NpyArray::from_rows(&streams.samples)?),
+        ("channels", NpyArray::U32 { shape: vec![streams.channels.len()], data: streams.channels.iter().map(|&c| c as u32).collect() }),
+        ("rate", NpyArray::f64(vec![], vec![streams.rate])?),
+        ("t0", NpyArray::f64(vec![], vec![streams.t0])?),
+    ];
+    npy::write_npz(std::io::BufWriter::new(create(path)?), &arrays)
+}
+
+/// one raw little-endian f64 file per channel, `<dir>/ch<id>.f64`
+pub fn write_streams_raw(dir: &str, streams: &SampleStreams) -> Result<()> {
+    create_dir_all(dir)?;
+    for (ch, s) in streams.channels.iter().zip(&streams.samples) {
+        let mut f = std::io::BufWriter::new(File::create(std::path::Path::new(dir).join(format!("ch{}.f64", ch)))?);
+        for v in s { f.write_all(&v.to_le_bytes())?; }
+        f.flush()?;
+    }
+    Ok(())
+}
+
+/// one row per segment: channel, start and end tick, fixed point a, b, c, d
+pub fn write_piecewise_csv(path: &str, export: &PiecewiseExport) -> Result<()> {
+    let mut f = std::io::BufWriter::new(create(path)?);
+    writeln!(f, "channel,start_tick,end_tick,a,b,c,d")?;
+    for ch in &export.channels {
+        for (i, [a, b, c, d]) in ch.coeffs.iter().enumerate() {
+            writeln!(f, "{},{},{},{},{},{},{}", ch.channel, ch.knots[i], ch.knots[i + 1], a, b, c, d)?;
+        }
+    }
+    f.flush()?;
+    Ok(())
+}
@@ -1,7 +1,30 @@
 pub mod types;
+pub mod units;
+pub mod species;
+pub mod electrode;
+pub mod channels;
+pub mod dac;
+pub mod spec;
 pub mod basis;
 pub mod constraints;
 pub mod lsq;
 pub mod c2lr;
 pub mod dynamics;
+pub mod spline;
+pub mod timing;
+pub mod trajectory;
+pub mod piecewise;
+pub mod simulate;
+pub mod excitation;
+pub mod mathieu;
+pub mod compensation;
+pub mod control;
+pub mod noise;
+pub mod filter;
+pub mod npy;
 pub mod io;
+pub mod analysis;
+pub mod config;
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
@@ -1,6 +1,8 @@
 use ndarray::{Array1, Array2};
 use rayon::prelude::*;
 use sprs::{CsMat, TriMat};
+use crate::units::Units;
+use serde::{Deserialize, Serialize};
 
 // ---- helpers to convert and do matvecs on CSR/CSC ----
 fn dense_to_csr(a: &Array2<f64>) -> CsMat<f64> {
@@ -41,11 +43,24 @@ fn spmtv_csc(at: &CsMat<f64>, x: &[f64]) -> Vec<f64> {
     }).collect()
 }
 
+// keep only the listed columns, in order
+fn select_cols(a: &CsMat<f64>, keep: &[usize]) -> CsMat<f64> {
+    let mut new_col = vec![usize::MAX; a.cols()];
+    for (k, &j) in keep.iter().enumerate() { new_col[j] = k; }
+    let mut tri = TriMat::new((a.rows(), keep.len()));
+    for (v, (i, j)) in a.iter() {
+        if new_col[j] != usize::MAX { tri.add_triplet(i, new_col[j], *v); }
+    }
+    tri.to_csr()
+}
+
 // BLAS-like tiny helpers
 fn nrm2(x: &[f64]) -> f64 { x.iter().map(|t| t*t).sum::<f64>().sqrt() }
 fn scal(x: &mut [f64], a: f64) { x.iter_mut().for_each(|t| *t *= a); }
-fn axpy(y: &mut [f64], x: &[f64], a: f64) { for i in 0..y.len() { y[i] += a * x[i]; } }
+fn axpy(y: &mut [f64], x: &[f64], a: f64) { y.iter_mut().zip(x).for_each(|(yi, xi)| *yi += a * xi); }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct LsqOptions {
     pub lambda: f64,                 // Tikhonov (λ >= 0)
     pub voltage_limit: Option<f64>,  // symmetric clamp
@@ -53,6 +68,18 @@ pub struct LsqOptions {
     pub tol: f64,                    // early stop on |phi_bar|
 }
 
+impl LsqOptions {
+    /// The same options in internal units: the voltage clamp converted, and λ
+    /// rescaled with the rows so that it weighs the same SI residual.
+    pub fn to_internal(&self, units: &Units) -> Self {
+        Self {
+            lambda: self.lambda / (units.weight_scale() * units.weight_scale()),
+            voltage_limit: self.voltage_limit.map(|v| units.voltage_to_internal(v)),
+            ..self.clone()
+        }
+    }
+}
+
 impl Default for LsqOptions {
     fn default() -> Self {
         Self { lambda: 1e-2, voltage_limit: Some(5.0), iters: 400, tol: 1e-10 }
@@ -61,7 +88,11 @@ impl Default for LsqOptions {
 
 /// LSQR on augmented matrix [A; sqrt(λ) I] with rhs [b; 0]
 pub fn tikhonov(a_dense: &Array2<f64>, b: &Array1<f64>, opts: &LsqOptions) -> Vec<f64> {
-    let a_csr = dense_to_csr(a_dense);
+    tikhonov_csr(&dense_to_csr(a_dense), b, opts)
+}
+
+/// same as `tikhonov` for a system that is already assembled sparse
+pub fn tikhonov_csr(a_csr: &CsMat<f64>, b: &Array1<f64>, opts: &LsqOptions) -> Vec<f64> {
     let a_csc = a_csr.to_csc();            // for A^T * x
     let m0 = a_csr.rows();
     let n  = a_csr.cols();
@@ -95,34 +126,35 @@ pub fn tikhonov(a_dense: &Array2<f64>, b: &Array1<f64>, opts: &LsqOptions) -> Ve
 
     for _ in 0..opts.iters {
         // u = [A v; sqrt(λ) v] - α [u_top; u_bot]
-        let mut Av = spmv_csr(&a_csr, &v);
+        let mut av = spmv_csr(a_csr, &v);
         // append sqrt(λ) v
-        Av.extend(v.iter().map(|&t| sqrt_lam * t));
+        av.extend(v.iter().map(|&t| sqrt_lam * t));
         // subtract α u
-        axpy(&mut Av, &u, -alpha);
-        u = Av;
+        axpy(&mut av, &u, -alpha);
+        u = av;
 
         beta = nrm2(&u);
         if beta != 0.0 { scal(&mut u, 1.0 / beta); }
 
         // v = [A^T u_top + sqrt(λ) u_bot] - β v
         let (u_top2, u_bot2) = u.split_at(m0);
-        let mut Atu = spmtv_csc(&a_csc, u_top2);
-        for j in 0..n { Atu[j] += sqrt_lam * u_bot2[j]; }
-        axpy(&mut Atu, &v, -beta);
+        let mut atu = spmtv_csc(&a_csc, u_top2);
+        for j in 0..n { atu[j] += sqrt_lam * u_bot2[j]; }
+        axpy(&mut atu, &v, -beta);
 
-        alpha = nrm2(&Atu);
-        if alpha != 0.0 { scal(&mut Atu, 1.0 / alpha); }
-        let v_new = Atu;
+        alpha = nrm2(&atu);
+        if alpha != 0.0 { scal(&mut atu, 1.0 / alpha); }
+        let v_new = atu;
 
         // orthogonal update
         let rho   = (rho_bar * rho_bar + beta * beta).sqrt();
+        if rho == 0.0 { break; }           // the previous step was exact
         let c     = rho_bar / rho;
         let s     = beta / rho;
         let theta = s * alpha;
         rho_bar   = -c * alpha;
         let phi   = c * phi_bar;
-        phi_bar   = s * phi_bar;
+        phi_bar  *= s;
 
         // x and w
         for j in 0..n {
@@ -144,7 +176,65 @@ pub fn tikhonov(a_dense: &Array2<f64>, b: &Array1<f64>, opts: &LsqOptions) -> Ve
     x
 }
 
+/// Tikhonov solve with per-unknown bounds replacing the symmetric clamp, by a
+/// primal active set: the free unknowns are solved with the bound ones held,
+/// stepping only as far as the first bound met, and a bound unknown is
+/// released when its gradient points back into the box. Unknowns with
+/// `lo >= hi` are held at `lo`.
+pub fn tikhonov_bounded(a_csr: &CsMat<f64>, b: &Array1<f64>, lo: &[f64], hi: &[f64], opts: &LsqOptions) -> Vec<f64> {
+    let n = a_csr.cols();
+    let a_csc = a_csr.to_csc();
+    let free_opts = LsqOptions { voltage_limit: None, ..opts.clone() };
+    let lambda = opts.lambda.max(0.0);
+    // gradient of ½|Ax - b|² + ½λ|x|²
+    let grad = |x: &[f64]| -> Vec<f64> {
+        let r: Vec<f64> = spmv_csr(a_csr, x).iter().zip(b).map(|(ax, bi)| ax - bi).collect();
+        let mut g = spmtv_csc(&a_csc, &r);
+        axpy(&mut g, x, lambda);
+        g
+    };
+    let fixed: Vec<bool> = (0..n).map(|j| lo[j] >= hi[j]).collect();
+    let mut x: Vec<f64> = (0..n).map(|j| if fixed[j] { lo[j] } else { 0.0_f64.max(lo[j]).min(hi[j]) }).collect();
+    let mut bound = fixed.clone();
+    let tol = 1e-9 * grad(&vec![0.0; n]).iter().fold(f64::MIN_POSITIVE, |m, g| m.max(g.abs()));
+
+    for _ in 0..4 * n + 10 {
+        let free: Vec<usize> = (0..n).filter(|&j| !bound[j]).collect();
+        if !free.is_empty() {
+            // move the bound contributions to the rhs
+            let x_bound: Vec<f64> = (0..n).map(|j| if bound[j] { x[j] } else { 0.0 }).collect();
+            let r = b - &Array1::from(spmv_csr(a_csr, &x_bound));
+            let z = tikhonov_csr(&select_cols(a_csr, &free), &r, &free_opts);
+
+            // step towards the free optimum, stopping at the first bound
+            let mut step = 1.0;
+            let mut blocking = None;
+            for (k, &j) in free.iter().enumerate() {
+                let d = z[k] - x[j];
+                let room = if d < 0.0 { lo[j] - x[j] } else { hi[j] - x[j] };
+                if d != 0.0 && room / d < step {
+                    step = (room / d).max(0.0);
+                    blocking = Some((j, if d < 0.0 { lo[j] } else { hi[j] }));
+                }
+            }
+            for (k, &j) in free.iter().enumerate() { x[j] = (x[j] + step * (z[k] - x[j])).max(lo[j]).min(hi[j]); }
+            if let Some((j, v)) = blocking {
+                x[j] = v;
+                bound[j] = true;
+                continue;
+            }
+        }
 
-/* helpers */
-fn norm(x: &[f64]) -> f64 { x.iter().map(|t| t*t).sum::<f64>().sqrt() }
-
+        // release the bound unknown whose gradient most wants back inside
+        let g = grad(&x);
+        let release = (0..n).filter(|&j| bound[j] && !fixed[j])
+            .map(|j| (j, if x[j] <= lo[j] { -g[j] } else { g[j] }))
+            .filter(|&(_, pull)| pull > tol)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match release {
+            Some((j, _)) => bound[j] = false,
+            None => break,
+        }
+    }
+    x
+}
@@ -0,0 +1,114 @@
+// src/mathieu.rs
+//
+// Stability of the full RF motion. Along each principal axis of the RF
+// curvature K the ion obeys the Mathieu equation
+//
+//   d²u/dτ² + (a - 2q cos 2τ) u = 0,   τ = Ω t / 2,
+//   a = 4 Q H_dc / m Ω²,   q = -2 Q K / m Ω²,
+//
+// with H_dc the static curvature along the axis, dc electrodes plus any
+// background. The axes are taken uncoupled, which holds when the static and
+// RF curvatures share principal axes.
+
+use serde::{Deserialize, Serialize};
+use crate::basis::TrapModel;
+use crate::dynamics::eigen;
+use crate::simulate::equilibrium;
+use crate::types::{IonwaveError, Result, Vec3, Waypoint};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+/// Trace of the Mathieu monodromy matrix over one period τ ∈ [0, π]; the
+/// motion is bounded for |trace| < 2.
+pub fn mathieu_trace(a: f64, q: f64) -> f64 {
+    const STEPS: usize = 512;
+    let h = std::f64::consts::PI / STEPS as f64;
+    let f = |tau: f64, u: f64| -(a - 2.0 * q * (2.0 * tau).cos()) * u;
+    // classical RK4 on (u, u') for both unit initial conditions at once
+    let mut y = [[1.0, 0.0], [0.0, 1.0]];
+    for k in 0..STEPS {
+        let tau = k as f64 * h;
+        for s in y.iter_mut() {
+            let (u, p) = (s[0], s[1]);
+            let (k1u, k1p) = (p, f(tau, u));
+            let (k2u, k2p) = (p + 0.5 * h * k1p, f(tau + 0.5 * h, u + 0.5 * h * k1u));
+            let (k3u, k3p) = (p + 0.5 * h * k2p, f(tau + 0.5 * h, u + 0.5 * h * k2u));
+            let (k4u, k4p) = (p + h * k3p, f(tau + h, u + h * k3u));
+            s[0] = u + h / 6.0 * (k1u + 2.0 * k2u + 2.0 * k3u + k4u);
+            s[1] = p + h / 6.0 * (k1p + 2.0 * k2p + 2.0 * k3p + k4p);
+        }
+    }
+    y[0][0] + y[1][1]
+}
+
+/// Characteristic exponent β of the lowest stability region, the secular
+/// frequency being β Ω / 2. None outside the stable region.
+pub fn mathieu_beta(a: f64, q: f64) -> Option<f64> {
+    let tr = mathieu_trace(a, q);
+    if tr.abs() < 2.0 { Some((0.5 * tr).acos() / std::f64::consts::PI) } else { None }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct MathieuMode {
+    pub axis: Vec3,          // principal axis of the RF curvature
+    pub a: f64,
+    pub q: f64,
+    pub stable: bool,
+    pub margin: f64,         // 1 - |trace| / 2, positive inside the stable region
+    pub secular_hz: f64,     // β Ω / 4π, zero when unstable
+}
+
+impl MathieuMode {
+    pub fn new(axis: Vec3, a: f64, q: f64, rf_omega: f64) -> Self {
+        let tr = mathieu_trace(a, q);
+        let stable = tr.abs() < 2.0;
+        let beta = if stable { (0.5 * tr).acos() / std::f64::consts::PI } else { 0.0 };
+        Self { axis, a, q, stable, margin: 1.0 - 0.5 * tr.abs(), secular_hz: beta * rf_omega / (2.0 * TWO_PI) }
+    }
+}
+
+/// RF behaviour of the ion sitting in the well at one waypoint.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RfStability {
+    pub index: usize,
+    pub r: Vec3,                       // equilibrium nearest the waypoint
+    pub modes: [MathieuMode; 3],
+    pub stable: bool,
+    pub rf_field: f64,                 // V/m, RF field amplitude at the ion
+    pub micromotion_amplitude: f64,    // m, excess micromotion Q E / m Ω²
+}
+
+/// Mathieu parameters, stability and excess micromotion at every waypoint
+/// of a waveform. Needs the model's explicit `rf_drive`.
+pub fn rf_stability(model: &TrapModel, waypoints: &[Waypoint], volts: &[Vec<f64>]) -> Result<Vec<RfStability>> {
+    let drive = model.rf_drive.as_ref()
+        .ok_or_else(|| IonwaveError::InvalidInput("the model has no explicit RF drive".to_string()))?;
+    if volts.len() != waypoints.len() {
+        return Err(IonwaveError::InvalidInput(format!("{} waveform rows for {} waypoints", volts.len(), waypoints.len())));
+    }
+    let omega = drive.omega();
+    waypoints.iter().zip(volts).enumerate().map(|(index, (wp, v))| {
+        if v.len() != model.n_electrodes() {
+            return Err(IonwaveError::InvalidInput(format!("{} voltages for {} electrodes", v.len(), model.n_electrodes())));
+        }
+        let r = equilibrium(model, v, wp.r)?;
+        let qm = wp.species.charge_to_mass();
+        let (h_dc, k) = (model.hess_dc(r, v) + model.hess_background(r), drive.hess(r));
+        let (_, axes) = eigen(k);
+        let modes = axes.map(|e| MathieuMode::new(
+            e,
+            4.0 * qm * h_dc.quad(e) / (omega * omega),
+            -2.0 * qm * k.quad(e) / (omega * omega),
+            omega,
+        ));
+        let rf_field = drive.grad(r).norm();
+        Ok(RfStability {
+            index,
+            r,
+            stable: modes.iter().all(|m| m.stable),
+            modes,
+            rf_field,
+            micromotion_amplitude: qm.abs() * rf_field / (omega * omega),
+        })
+    }).collect()
+}
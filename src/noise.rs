@@ -0,0 +1,167 @@
+// src/noise.rs
+//
+// Motional heating from electrode voltage noise. Every DAC channel drives its
+// electrodes with voltage noise of one sided spectral density S_V(f), the
+// channels uncorrelated, so the field noise along the axial mode u is
+//
+//   S_E(f) = Σ_c (u·∇φ_c)² S_V,c(f),
+//
+// with ∇φ_c the gradient per volt of all electrodes on channel c. Field noise
+// at the mode frequency ω = 2πf heats it at ṅ = q² S_E(f) / 4 m ħ ω. The
+// noise leaves the DAC and reaches the electrodes through the channel's
+// filter from the same `[filter]` table the waveform is played through.
+
+use serde::{Deserialize, Serialize};
+use crate::basis::TrapModel;
+use crate::channels::ChannelMap;
+use crate::filter::FilterConfig;
+use crate::simulate::{equilibrium, HBAR};
+use crate::types::{IonwaveError, Result, Vec3, Waypoint};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+/// Voltage noise at the output of one DAC channel: a white floor with 1/f
+/// flicker below `flicker_corner`, or a measured `spectrum` instead.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VoltageNoise {
+    #[serde(default)]
+    pub density: f64,                  // V/√Hz, white
+    #[serde(default)]
+    pub flicker_corner: f64,           // Hz, where 1/f noise meets the white floor
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spectrum: Vec<[f64; 2]>,       // (Hz, V/√Hz), ascending, log-log interpolated
+}
+
+impl VoltageNoise {
+    pub fn white(density: f64) -> Self { Self { density, ..Default::default() } }
+
+    pub fn validate(&self) -> Result<()> {
+        let bad = |msg: String| Err(IonwaveError::Config(msg));
+        if !(self.density.is_finite() && self.density >= 0.0) { return bad(format!("density {} must be non-negative", self.density)); }
+        if !(self.flicker_corner.is_finite() && self.flicker_corner >= 0.0) {
+            return bad(format!("flicker corner {} must be non-negative", self.flicker_corner));
+        }
+        for (i, &[f, d]) in self.spectrum.iter().enumerate() {
+            if !(f.is_finite() && f > 0.0 && d.is_finite() && d > 0.0) {
+                return bad(format!("spectrum point {} needs a positive frequency and density", i));
+            }
+            if i > 0 && f <= self.spectrum[i - 1][0] { return bad("spectrum frequencies must ascend".to_string()); }
+        }
+        Ok(())
+    }
+
+    /// One sided power spectral density at the DAC output, V²/Hz. A measured
+    /// spectrum is held flat beyond its ends.
+    pub fn psd(&self, f: f64) -> f64 {
+        match self.spectrum.as_slice() {
+            [] => self.density * self.density * (1.0 + self.flicker_corner / f),
+            s => {
+                let k = s.partition_point(|p| p[0] < f);
+                let d = if k == 0 {
+                    s[0][1]
+                } else if k == s.len() {
+                    s[k - 1][1]
+                } else {
+                    let ([f0, d0], [f1, d1]) = (s[k - 1], s[k]);
+                    let u = (f / f0).ln() / (f1 / f0).ln();
+                    d0 * (d1 / d0).powf(u)
+                };
+                d * d
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelNoise {
+    pub channel: usize,
+    pub noise: VoltageNoise,
+}
+
+/// Noise of every DAC channel: its own entry if it has one, else `default`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseConfig {
+    #[serde(default)]
+    pub default: VoltageNoise,
+    #[serde(default)]
+    pub channels: Vec<ChannelNoise>,
+}
+
+impl NoiseConfig {
+    pub fn validate(&self) -> Result<()> {
+        let prefixed = |what: String, e: IonwaveError| IonwaveError::Config(format!("noise: {}: {}", what, e));
+        self.default.validate().map_err(|e| prefixed("default".to_string(), e))?;
+        for (i, c) in self.channels.iter().enumerate() {
+            c.noise.validate().map_err(|e| prefixed(format!("channel {}", c.channel), e))?;
+            if self.channels[..i].iter().any(|o| o.channel == c.channel) {
+                return Err(IonwaveError::Config(format!("noise: channel {} given twice", c.channel)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn channel(&self, channel: usize) -> &VoltageNoise {
+        self.channels.iter().find(|c| c.channel == channel).map_or(&self.default, |c| &c.noise)
+    }
+}
+
+/// Heating of the axial mode by voltage noise at one waypoint.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HeatingReport {
+    pub index: usize,
+    pub r: Vec3,                     // equilibrium nearest the waypoint
+    pub axial_hz: f64,
+    pub field_psd: f64,              // (V/m)²/Hz along the axis, at the axial frequency
+    pub heating_rate: f64,           // quanta/s
+    pub channel_rates: Vec<f64>,     // quanta/s from each channel, ascending channel ids
+}
+
+/// Axial heating rate at every waypoint of a waveform from the voltage noise
+/// of its DAC channels, each passed through its channel's filter if `filters`
+/// gives one, with each channel's share.
+pub fn heating_rates(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    volts: &[Vec<f64>],
+    noise: &NoiseConfig,
+    filters: Option<&FilterConfig>,
+) -> Result<Vec<HeatingReport>> {
+    noise.validate()?;
+    if let Some(f) = filters { f.validate()?; }
+    if volts.len() != waypoints.len() {
+        return Err(IonwaveError::InvalidInput(format!("{} waveform rows for {} waypoints", volts.len(), waypoints.len())));
+    }
+    let map = ChannelMap::from_model(model)?;
+    waypoints.iter().zip(volts).enumerate().map(|(index, (wp, v))| {
+        if v.len() != model.n_electrodes() {
+            return Err(IonwaveError::InvalidInput(format!("{} voltages for {} electrodes", v.len(), model.n_electrodes())));
+        }
+        let r = equilibrium(model, v, wp.r)?;
+        let axis = wp.axial_dir.unit();
+        let (q, m) = (wp.species.charge(), wp.species.mass());
+        let omega2 = q / m * model.hess_total(r, v).quad(axis);
+        if omega2 <= 0.0 {
+            return Err(IonwaveError::InvalidInput(format!("no axial confinement at waypoint {}", index)));
+        }
+        let omega = omega2.sqrt();
+        let f = omega / TWO_PI;
+        let per_channel: Vec<f64> = map.channels.iter().zip(&map.members).map(|(&ch, members)| {
+            let e = members.iter().fold(Vec3::ZERO, |g, &j| g + model.dc[j].grad(r)).dot(axis);
+            let gain = filters.and_then(|fl| fl.channel(ch)).map_or(1.0, |spec| spec.power_gain(f));
+            e * e * noise.channel(ch).psd(f) * gain
+        }).collect();
+        let field_psd: f64 = per_channel.iter().sum();
+        let rate = q * q / (4.0 * m * HBAR * omega);
+        Ok(HeatingReport {
+            index,
+            r,
+            axial_hz: f,
+            field_psd,
+            heating_rate: rate * field_psd,
+            channel_rates: per_channel.iter().map(|s| rate * s).collect(),
+        })
+    }).collect()
+}
@@ -0,0 +1,273 @@
+// src/npy.rs
+//
+// Minimal NumPy .npy / .npz support: little-endian f64, u32 and unicode
+// string arrays in C order, and uncompressed npz archives as np.savez writes.
+
+use std::io::Write;
+use crate::types::{IonwaveError, Result};
+
+/// An n-dimensional array ready to be written as .npy
+#[derive(Clone, Debug, PartialEq)]
+pub enum NpyArray {
+    F64 { shape: Vec<usize>, data: Vec<f64> },
+    U32 { shape: Vec<usize>, data: Vec<u32> },
+    Str { shape: Vec<usize>, data: Vec<String> },
+}
+
+impl NpyArray {
+    pub fn f64(shape: Vec<usize>, data: Vec<f64>) -> Result<Self> {
+        check_len(&shape, data.len())?;
+        Ok(NpyArray::F64 { shape, data })
+    }
+
+    /// rows of equal length as a 2-d array
+    pub fn from_rows(rows: &[Vec<f64>]) -> Result<Self> {
+        let n = rows.first().map_or(0, |r| r.len());
+        if rows.iter().any(|r| r.len() != n) {
+            return Err(IonwaveError::InvalidInput("ragged rows".to_string()));
+        }
+        Self::f64(vec![rows.len(), n], rows.concat())
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        match self {
+            NpyArray::F64 { shape, .. } | NpyArray::U32 { shape, .. } | NpyArray::Str { shape, .. } => shape,
+        }
+    }
+
+    fn descr(&self) -> String {
+        match self {
+            NpyArray::F64 { .. } => "<f8".to_string(),
+            NpyArray::U32 { .. } => "<u4".to_string(),
+            NpyArray::Str { data, .. } => format!("<U{}", str_width(data)),
+        }
+    }
+
+    /// the complete .npy file contents
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let shape = match self.shape() {
+            [n] => format!("({},)", n),
+            s => format!("({})", s.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+        };
+        let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", self.descr(), shape);
+        // magic + version + u16 length + header + newline, padded to 64 bytes
+        let unpadded = 10 + header.len() + 1;
+        header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+        header.push('\n');
+
+        let mut out = Vec::with_capacity(10 + header.len());
+        out.extend_from_slice(b"\x93NUMPY\x01\x00");
+        out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        out.extend_from_slice(header.as_bytes());
+        match self {
+            NpyArray::F64 { data, .. } => data.iter().for_each(|v| out.extend_from_slice(&v.to_le_bytes())),
+            NpyArray::U32 { data, .. } => data.iter().for_each(|v| out.extend_from_slice(&v.to_le_bytes())),
+            NpyArray::Str { data, .. } => {
+                // fixed width UTF-32, zero padded
+                let width = str_width(data);
+                for s in data {
+                    let chars: Vec<char> = s.chars().collect();
+                    for k in 0..width {
+                        let c = chars.get(k).map_or(0, |&c| c as u32);
+                        out.extend_from_slice(&c.to_le_bytes());
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// parse a .npy file written by `to_bytes` (or by numpy for these dtypes);
+    /// malformed input is an error, never a panic
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bad = |msg: &str| IonwaveError::InvalidInput(format!("npy: {}", msg));
+        if bytes.get(..6) != Some(b"\x93NUMPY".as_slice()) { return Err(bad("bad magic")); }
+        let (hlen, start) = match (bytes.get(6), bytes.get(8..12)) {
+            (Some(1), _) if bytes.len() >= 10 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+            (Some(2 | 3), Some(b)) => (u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize, 12),
+            (Some(1..=3), _) => return Err(bad("truncated header")),
+            _ => return Err(bad("unsupported version")),
+        };
+        let header = std::str::from_utf8(bytes.get(start..start + hlen).ok_or_else(|| bad("truncated header"))?)
+            .map_err(|_| bad("header is not text"))?;
+        let field = |key: &str| -> Result<&str> {
+            let at = header.find(key).ok_or_else(|| bad("missing header field"))? + key.len();
+            Ok(header[at..].trim_start_matches([':', ' ']))
+        };
+        if field("'fortran_order'")?.starts_with("True") { return Err(bad("fortran order not supported")); }
+        let descr = field("'descr'")?.trim_start_matches('\'');
+        let descr = &descr[..descr.find('\'').ok_or_else(|| bad("bad descr"))?];
+        let shape_str = field("'shape'")?.strip_prefix('(').ok_or_else(|| bad("bad shape"))?;
+        let shape_str = &shape_str[..shape_str.find(')').ok_or_else(|| bad("bad shape"))?];
+        let shape = shape_str.split(',').map(str::trim).filter(|s| !s.is_empty())
+            .map(|s| s.parse::<usize>().map_err(|_| bad("bad shape")))
+            .collect::<Result<Vec<usize>>>()?;
+        let n = shape.iter().try_fold(1usize, |n, &d| n.checked_mul(d)).ok_or_else(|| bad("shape too large"))?;
+        let body = &bytes[start + hlen..];
+        // bytes the data needs at `size` bytes per element, error on overflow or a short body
+        let need = |size: usize| match size.checked_mul(n) {
+            Some(len) if len <= body.len() => Ok(()),
+            Some(_) => Err(bad("truncated data")),
+            None => Err(bad("shape too large")),
+        };
+
+        match descr {
+            "<f8" => {
+                need(8)?;
+                let data = body.chunks_exact(8).take(n).map(|c| f64::from_le_bytes(c.try_into().unwrap())).collect();
+                Ok(NpyArray::F64 { shape, data })
+            }
+            "<u4" => {
+                need(4)?;
+                let data = body.chunks_exact(4).take(n).map(|c| u32::from_le_bytes(c.try_into().unwrap())).collect();
+                Ok(NpyArray::U32 { shape, data })
+            }
+            d if d.starts_with("<U") => {
+                let width: usize = d[2..].parse().map_err(|_| bad("bad descr"))?;
+                need(width.checked_mul(4).ok_or_else(|| bad("bad descr"))?)?;
+                let data = (0..n).map(|i| {
+                    body[4 * width * i..4 * width * (i + 1)].chunks_exact(4)
+                        .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                        .take_while(|&c| c != 0)
+                        .filter_map(char::from_u32)
+                        .collect()
+                }).collect();
+                Ok(NpyArray::Str { shape, data })
+            }
+            d => Err(bad(&format!("unsupported dtype {}", d))),
+        }
+    }
+}
+
+fn str_width(data: &[String]) -> usize {
+    data.iter().map(|s| s.chars().count()).max().unwrap_or(0).max(1)
+}
+
+fn check_len(shape: &[usize], len: usize) -> Result<()> {
+    let n: usize = shape.iter().product();
+    if n != len {
+        return Err(IonwaveError::InvalidInput(format!("shape {:?} needs {} values, got {}", shape, n, len)));
+    }
+    Ok(())
+}
+
+// ---- npz: a zip archive of .npy members, stored without compression ----
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Write named arrays as an .npz archive; `np.load` exposes each under its name.
+pub fn write_npz<W: Write>(mut w: W, arrays: &[(&str, NpyArray)]) -> Result<()> {
+    let too_big = || IonwaveError::InvalidInput("npz members above 4 GiB are not supported".to_string());
+    let mut central = Vec::new();
+    let mut offset: u64 = 0;
+    for (name, arr) in arrays {
+        let file_name = format!("{}.npy", name);
+        let data = arr.to_bytes();
+        let size = u32::try_from(data.len()).map_err(|_| too_big())?;
+        let crc = crc32(&data);
+        let local_offset = u32::try_from(offset).map_err(|_| too_big())?;
+
+        // local file header, version 2.0, stored, no timestamp
+        let mut local = Vec::with_capacity(30 + file_name.len());
+        local.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        local.extend_from_slice(&20u16.to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes());   // flags
+        local.extend_from_slice(&0u16.to_le_bytes());   // stored
+        local.extend_from_slice(&0u16.to_le_bytes());   // time
+        local.extend_from_slice(&0x21u16.to_le_bytes()); // date 1980-01-01
+        local.extend_from_slice(&crc.to_le_bytes());
+        local.extend_from_slice(&size.to_le_bytes());
+        local.extend_from_slice(&size.to_le_bytes());
+        local.extend_from_slice(&(file_name.len() as u16).to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes());
+        local.extend_from_slice(file_name.as_bytes());
+        w.write_all(&local)?;
+        w.write_all(&data)?;
+        offset += (local.len() + data.len()) as u64;
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // made by
+        central.extend_from_slice(&20u16.to_le_bytes()); // needed
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&0x21u16.to_le_bytes());
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&(file_name.len() as u16).to_le_bytes());
+        central.extend_from_slice(&[0u8; 12]); // extra, comment, disk, internal and external attrs
+        central.extend_from_slice(&local_offset.to_le_bytes());
+        central.extend_from_slice(file_name.as_bytes());
+    }
+    let central_offset = u32::try_from(offset).map_err(|_| too_big())?;
+    w.write_all(&central)?;
+
+    // end of central directory
+    let n = arrays.len() as u16;
+    let mut end = Vec::with_capacity(22);
+    end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    end.extend_from_slice(&[0u8; 4]);
+    end.extend_from_slice(&n.to_le_bytes());
+    end.extend_from_slice(&n.to_le_bytes());
+    end.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    end.extend_from_slice(&central_offset.to_le_bytes());
+    end.extend_from_slice(&0u16.to_le_bytes());
+    w.write_all(&end)?;
+    Ok(())
+}
+
+/// Read the members of an uncompressed .npz, such as those from `write_npz`
+/// or np.savez, in archive order. np.savez writes every member zip64 style:
+/// the local header sizes are 0xFFFFFFFF and the real ones sit in a zip64
+/// extra field.
+pub fn read_npz(bytes: &[u8]) -> Result<Vec<(String, NpyArray)>> {
+    let bad = |msg: &str| IonwaveError::InvalidInput(format!("npz: {}", msg));
+    let u16_at = |i: usize| bytes.get(i..i + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize).ok_or_else(|| bad("truncated"));
+    let u32_at = |i: usize| bytes.get(i..i + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize).ok_or_else(|| bad("truncated"));
+    let u64_at = |i: usize| bytes.get(i..i + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap())).ok_or_else(|| bad("truncated"));
+
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while u32_at(pos)? == 0x0403_4b50 {
+        if u16_at(pos + 8)? != 0 { return Err(bad("compressed members are not supported")); }
+        let mut size = u32_at(pos + 18)?;
+        let name_len = u16_at(pos + 26)?;
+        let extra_len = u16_at(pos + 28)?;
+        let name = std::str::from_utf8(bytes.get(pos + 30..pos + 30 + name_len).ok_or_else(|| bad("truncated"))?)
+            .map_err(|_| bad("bad member name"))?;
+        let start = pos + 30 + name_len + extra_len;
+        if size == 0xFFFF_FFFF {
+            // zip64 extra field, id 0x0001: uncompressed then compressed size
+            let (mut at, end) = (pos + 30 + name_len, start);
+            let mut found = None;
+            while at + 4 <= end {
+                let (id, len) = (u16_at(at)?, u16_at(at + 2)?);
+                if id == 0x0001 {
+                    if len < 16 { return Err(bad("short zip64 extra field")); }
+                    found = Some(u64_at(at + 12)?);
+                    break;
+                }
+                at += 4 + len;
+            }
+            let compressed = found.ok_or_else(|| bad("zip64 sizes without a zip64 extra field"))?;
+            size = usize::try_from(compressed).map_err(|_| bad("member too large"))?;
+        } else if size == 0 && u16_at(pos + 6)? & 0x08 != 0 {
+            return Err(bad("members with trailing data descriptors are not supported"));
+        }
+        let end = start.checked_add(size).ok_or_else(|| bad("truncated"))?;
+        let data = bytes.get(start..end).ok_or_else(|| bad("truncated"))?;
+        out.push((name.trim_end_matches(".npy").to_string(), NpyArray::from_bytes(data)?));
+        pos = end;
+    }
+    Ok(out)
+}
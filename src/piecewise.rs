@@ -0,0 +1,143 @@
+// src/piecewise.rs
+//
+// Piecewise cubic export for sequencers that play polynomial segments
+// instead of raw samples. Each channel keeps only as many waypoints as knots
+// as it needs to stay within a voltage tolerance of the full spline.
+
+use serde::{Deserialize, Serialize};
+use crate::analysis::analyze;
+use crate::basis::TrapModel;
+use crate::spline::{CubicSpline, SplineKind};
+use crate::timing::TimedWaveform;
+use crate::types::{IonwaveError, Result, Waypoint};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PiecewiseOptions {
+    pub kind: SplineKind,
+    pub tolerance: f64,      // V, max deviation from the spline through every waypoint
+    pub clock_hz: f64,       // knot times are whole ticks of this clock
+    pub frac_bits: u32,      // fractional bits of the fixed point coefficients
+    pub oversample: usize,   // error checks per waypoint interval
+}
+
+impl Default for PiecewiseOptions {
+    fn default() -> Self {
+        Self { kind: SplineKind::Monotone, tolerance: 1e-4, clock_hz: 50e6, frac_bits: 24, oversample: 8 }
+    }
+}
+
+/// Segment polynomials of one channel. Segment i starts at `knots[i]` ticks
+/// and gives v = (a + b u + c u² + d u³) / 2^frac_bits volts for
+/// u = (t - t_i) / (t_{i+1} - t_i) in [0, 1).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChannelSegments {
+    pub channel: usize,
+    pub knots: Vec<i64>,
+    pub coeffs: Vec<[i64; 4]>,
+}
+
+impl ChannelSegments {
+    fn from_spline(channel: usize, s: &CubicSpline, knots: Vec<i64>, frac_bits: u32) -> Self {
+        let scale = (frac_bits as f64).exp2();
+        let coeffs = s.coeffs.iter().enumerate().map(|(i, &[a, b, c, d])| {
+            // rescale to the unit interval
+            let h = s.knots[i + 1] - s.knots[i];
+            [a, b * h, c * h * h, d * h * h * h].map(|x| (x * scale).round() as i64)
+        }).collect();
+        Self { channel, knots, coeffs }
+    }
+
+    /// voltage at `t` seconds as the sequencer plays it
+    pub fn eval(&self, t: f64, clock_hz: f64, frac_bits: u32) -> f64 {
+        let ticks = t * clock_hz;
+        let i = self.knots.partition_point(|&k| k as f64 <= ticks).clamp(1, self.coeffs.len()) - 1;
+        let (k0, k1) = (self.knots[i] as f64, self.knots[i + 1] as f64);
+        let u = (ticks - k0) / (k1 - k0);
+        let [a, b, c, d] = self.coeffs[i].map(|q| q as f64);
+        (a + u * (b + u * (c + u * d))) / (frac_bits as f64).exp2()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PiecewiseExport {
+    pub clock_hz: f64,
+    pub frac_bits: u32,
+    pub channels: Vec<ChannelSegments>,
+    pub max_error_volts: f64,      // against the spline through every waypoint
+    pub max_axial_error_hz: f64,   // at the waypoints, against the solved voltages
+}
+
+impl PiecewiseExport {
+    pub fn n_segments(&self) -> usize { self.channels.iter().map(|c| c.coeffs.len()).sum() }
+}
+
+/// Fit one channel greedily: start from the end points and insert the
+/// waypoint nearest the worst error until it is within tolerance, or every
+/// waypoint is a knot.
+fn fit_channel(channel: usize, times: &[f64], ticks: &[i64], y: &[f64], opts: &PiecewiseOptions)
+    -> Result<(ChannelSegments, f64)> {
+    let reference = CubicSpline::new(opts.kind, times, y)?;
+    let grid: Vec<f64> = times.windows(2)
+        .flat_map(|w| (0..opts.oversample).map(move |k| w[0] + (w[1] - w[0]) * k as f64 / opts.oversample as f64))
+        .chain(std::iter::once(times[times.len() - 1]))
+        .collect();
+    let truth = reference.eval_sorted(grid.iter().copied());
+    let tick_times: Vec<f64> = ticks.iter().map(|&k| k as f64 / opts.clock_hz).collect();
+
+    let mut active = vec![0, times.len() - 1];
+    loop {
+        let kt: Vec<f64> = active.iter().map(|&i| tick_times[i]).collect();
+        let ky: Vec<f64> = active.iter().map(|&i| y[i]).collect();
+        let fit = CubicSpline::new(opts.kind, &kt, &ky)?;
+        let seg = ChannelSegments::from_spline(channel, &fit, active.iter().map(|&i| ticks[i]).collect(), opts.frac_bits);
+        let (worst, err) = grid.iter().zip(&truth).enumerate()
+            .map(|(g, (&t, &v))| (g, (seg.eval(t, opts.clock_hz, opts.frac_bits) - v).abs()))
+            .fold((0, 0.0), |best, e| if e.1 > best.1 { e } else { best });
+        if err <= opts.tolerance || active.len() == times.len() { return Ok((seg, err)); }
+        // nearest inactive waypoint to the worst grid point
+        let t = grid[worst];
+        let next = (0..times.len()).filter(|i| !active.contains(i))
+            .min_by(|&a, &b| (times[a] - t).abs().total_cmp(&(times[b] - t).abs()))
+            .unwrap();
+        let at = active.partition_point(|&i| i < next);
+        active.insert(at, next);
+    }
+}
+
+/// Fit every channel of a timed waveform and report the reconstruction error
+/// in volts and in the axial frequency the model predicts at the waypoints.
+pub fn fit_piecewise(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    timed: &TimedWaveform,
+    opts: &PiecewiseOptions,
+) -> Result<PiecewiseExport> {
+    if !(opts.tolerance >= 0.0 && opts.clock_hz > 0.0 && opts.oversample > 0 && opts.frac_bits < 52) {
+        return Err(IonwaveError::InvalidInput("piecewise options need tolerance >= 0, clock_hz > 0, oversample > 0 and frac_bits < 52".to_string()));
+    }
+    let map = timed.channel_map(model, waypoints)?;
+    let ticks: Vec<i64> = timed.times.iter().map(|t| (t * opts.clock_hz).round() as i64).collect();
+    if ticks.windows(2).any(|w| w[1] <= w[0]) {
+        return Err(IonwaveError::InvalidInput("waypoints closer than one clock tick".to_string()));
+    }
+
+    let mut channels = Vec::with_capacity(timed.channels.len());
+    let mut max_error_volts: f64 = 0.0;
+    for (c, &ch) in timed.channels.iter().enumerate() {
+        let y: Vec<f64> = timed.volts.iter().map(|row| row[c]).collect();
+        let (seg, err) = fit_channel(ch, &timed.times, &ticks, &y, opts)?;
+        max_error_volts = max_error_volts.max(err);
+        channels.push(seg);
+    }
+
+    let played: Vec<Vec<f64>> = timed.times.iter()
+        .map(|&t| map.expand(&channels.iter().map(|s| s.eval(t, opts.clock_hz, opts.frac_bits)).collect::<Vec<_>>()))
+        .collect();
+    let solved: Vec<Vec<f64>> = timed.volts.iter().map(|v| map.expand(v)).collect();
+    let ideal = analyze(model, waypoints, &solved)?;
+    let actual = analyze(model, waypoints, &played)?;
+    let max_axial_error_hz = ideal.iter().zip(&actual).map(|(a, b)| (a.axial_hz - b.axial_hz).abs()).fold(0.0, f64::max);
+
+    Ok(PiecewiseExport { clock_hz: opts.clock_hz, frac_bits: opts.frac_bits, channels, max_error_volts, max_axial_error_hz })
+}
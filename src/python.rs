@@ -0,0 +1,414 @@
+// src/python.rs
+//
+// PyO3 bindings, built with the `python` feature. Arrays cross the boundary
+// as NumPy float64 arrays, positions as (x, y, z) sequences, everything SI.
+
+use std::sync::{Arc, Mutex};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::exceptions::{PyIOError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use crate::basis::{PotentialBasis, TrapModel};
+use crate::config::{BasisSpec, Config};
+use crate::electrode::Electrode;
+use crate::lsq::LsqOptions;
+use crate::species::IonSpecies;
+use crate::types::{Hess, IonwaveError, Vec3, Waypoint};
+use crate::units::Units;
+
+impl From<IonwaveError> for PyErr {
+    fn from(e: IonwaveError) -> PyErr {
+        match e {
+            IonwaveError::Io(_) => PyIOError::new_err(e.to_string()),
+            IonwaveError::Solver(_) | IonwaveError::Polars(_) => PyRuntimeError::new_err(e.to_string()),
+            IonwaveError::InvalidInput(_) | IonwaveError::Config(_) => PyValueError::new_err(e.to_string()),
+        }
+    }
+}
+
+fn vec3(r: [f64; 3]) -> Vec3 { Vec3 { x: r[0], y: r[1], z: r[2] } }
+
+fn hess_rows(h: Hess) -> Vec<Vec<f64>> {
+    vec![vec![h.xx, h.xy, h.xz], vec![h.xy, h.yy, h.yz], vec![h.xz, h.yz, h.zz]]
+}
+
+fn rows(a: &PyReadonlyArray2<f64>) -> Vec<Vec<f64>> {
+    a.as_array().rows().into_iter().map(|r| r.to_vec()).collect()
+}
+
+fn to_array2<'py>(py: Python<'py>, rows: &[Vec<f64>]) -> PyResult<Array2<'py>> {
+    PyArray2::from_vec2_bound(py, rows).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+type Array1<'py> = Bound<'py, PyArray1<f64>>;
+type Array2<'py> = Bound<'py, PyArray2<f64>>;
+
+/// first error raised by a Python callback, reported once the call returns
+type ErrorSlot = Arc<Mutex<Option<PyErr>>>;
+
+/// A basis implemented in Python by an object with `phi(r)`, `grad(r)` and
+/// `hess(r)` methods. Callback errors are stored and the basis returns NaN.
+struct CallbackBasis {
+    obj: Py<PyAny>,
+    error: ErrorSlot,
+}
+
+impl CallbackBasis {
+    fn call<T: for<'py> FromPyObject<'py>>(&self, method: &str, r: Vec3, fallback: T) -> T {
+        Python::with_gil(|py| {
+            match self.obj.bind(py).call_method1(method, ((r.x, r.y, r.z),)).and_then(|v| v.extract()) {
+                Ok(v) => v,
+                Err(e) => {
+                    self.error.lock().unwrap().get_or_insert(e);
+                    fallback
+                }
+            }
+        })
+    }
+}
+
+impl PotentialBasis for CallbackBasis {
+    fn phi(&self, r: Vec3) -> f64 { self.call("phi", r, f64::NAN) }
+    fn grad(&self, r: Vec3) -> Vec3 { vec3(self.call("grad", r, [f64::NAN; 3])) }
+    fn hess(&self, r: Vec3) -> Hess {
+        let m: [[f64; 3]; 3] = self.call("hess", r, [[f64::NAN; 3]; 3]);
+        Hess { xx: m[0][0], yy: m[1][1], zz: m[2][2], xy: m[0][1], xz: m[0][2], yz: m[1][2] }
+    }
+}
+
+/// One potential basis function, built-in or backed by a Python object.
+#[pyclass(name = "Basis", module = "ionwave")]
+pub struct PyBasis {
+    spec: Option<BasisSpec>,
+    custom: Option<Py<PyAny>>,
+}
+
+#[pymethods]
+impl PyBasis {
+    #[staticmethod]
+    #[pyo3(signature = (center, sigma, scale, cutoff=None))]
+    fn gaussian(center: [f64; 3], sigma: f64, scale: f64, cutoff: Option<f64>) -> PyResult<Self> {
+        let spec = BasisSpec::Gaussian { center: vec3(center), sigma, scale, cutoff };
+        spec.validate()?;
+        Ok(Self { spec: Some(spec), custom: None })
+    }
+
+    #[staticmethod]
+    fn rf_pseudo(kr: f64, kz: f64) -> PyResult<Self> {
+        let spec = BasisSpec::RfPseudo { kr, kz };
+        spec.validate()?;
+        Ok(Self { spec: Some(spec), custom: None })
+    }
+
+    /// wrap an object providing `phi(r)`, `grad(r)` -> 3 values and `hess(r)` -> 3x3
+    #[staticmethod]
+    fn custom(obj: &Bound<'_, PyAny>) -> PyResult<Self> {
+        for method in ["phi", "grad", "hess"] {
+            if !obj.hasattr(method)? {
+                return Err(PyValueError::new_err(format!("custom basis has no {} method", method)));
+            }
+        }
+        Ok(Self { spec: None, custom: Some(obj.clone().unbind()) })
+    }
+
+    fn __repr__(&self) -> String {
+        match &self.spec {
+            Some(spec) => format!("Basis({:?})", spec),
+            None => "Basis(custom)".to_string(),
+        }
+    }
+}
+
+impl PyBasis {
+    fn build(&self, py: Python<'_>, error: &ErrorSlot) -> PyResult<Box<dyn PotentialBasis>> {
+        match (&self.spec, &self.custom) {
+            (Some(spec), _) => Ok(spec.build()?),
+            (None, Some(obj)) => Ok(Box::new(CallbackBasis { obj: obj.clone_ref(py), error: error.clone() })),
+            (None, None) => Err(PyValueError::new_err("empty basis")),
+        }
+    }
+}
+
+#[pyclass(name = "Waypoint", module = "ionwave")]
+#[derive(Clone)]
+pub struct PyWaypoint {
+    inner: Waypoint,
+}
+
+#[pymethods]
+impl PyWaypoint {
+    #[new]
+    #[pyo3(signature = (r, omega_axial, axial_dir=[0.0, 0.0, 1.0], species="171Yb+"))]
+    fn new(r: [f64; 3], omega_axial: f64, axial_dir: [f64; 3], species: &str) -> PyResult<Self> {
+        let species: IonSpecies = species.parse()?;
+        Ok(Self { inner: Waypoint { r: vec3(r), omega_axial, axial_dir: vec3(axial_dir), species } })
+    }
+    #[getter]
+    fn r(&self) -> [f64; 3] { [self.inner.r.x, self.inner.r.y, self.inner.r.z] }
+    #[getter]
+    fn omega_axial(&self) -> f64 { self.inner.omega_axial }
+    #[getter]
+    fn axial_dir(&self) -> [f64; 3] { [self.inner.axial_dir.x, self.inner.axial_dir.y, self.inner.axial_dir.z] }
+    #[getter]
+    fn species(&self) -> String { self.inner.species.to_string() }
+    fn __repr__(&self) -> String {
+        format!("Waypoint(r={:?}, omega_axial={}, species='{}')", self.r(), self.inner.omega_axial, self.inner.species)
+    }
+}
+
+fn waypoints(wps: Vec<PyWaypoint>) -> Vec<Waypoint> { wps.into_iter().map(|w| w.inner).collect() }
+
+/// Hardware description of one dc electrode: name, DAC channel (None if not
+/// wired), voltage limits and whether it is driven at all.
+#[pyclass(name = "Electrode", module = "ionwave")]
+#[derive(Clone)]
+pub struct PyElectrode {
+    inner: Electrode,
+}
+
+#[pymethods]
+impl PyElectrode {
+    #[new]
+    #[pyo3(signature = (name, dac_channel=None, v_min=f64::NEG_INFINITY, v_max=f64::INFINITY, enabled=true))]
+    fn new(name: String, dac_channel: Option<usize>, v_min: f64, v_max: f64, enabled: bool) -> PyResult<Self> {
+        let inner = Electrode { name, dac_channel, v_min, v_max, enabled };
+        inner.validate()?;
+        Ok(Self { inner })
+    }
+    #[getter]
+    fn name(&self) -> String { self.inner.name.clone() }
+    #[getter]
+    fn dac_channel(&self) -> Option<usize> { self.inner.dac_channel }
+    #[getter]
+    fn v_min(&self) -> f64 { self.inner.v_min }
+    #[getter]
+    fn v_max(&self) -> f64 { self.inner.v_max }
+    #[getter]
+    fn enabled(&self) -> bool { self.inner.enabled }
+    fn __repr__(&self) -> String {
+        let e = &self.inner;
+        format!("Electrode(name='{}', dac_channel={:?}, v_min={}, v_max={}, enabled={})", e.name, e.dac_channel, e.v_min, e.v_max, e.enabled)
+    }
+}
+
+#[pyclass(name = "TrapModel", module = "ionwave")]
+pub struct PyTrapModel {
+    inner: TrapModel,
+    error: ErrorSlot,
+}
+
+impl PyTrapModel {
+    /// run `f` with the GIL released, so rayon workers can call custom bases
+    fn run<T: Send>(&self, py: Python<'_>, f: impl FnOnce(&TrapModel) -> crate::types::Result<T> + Send) -> PyResult<T> {
+        let out = py.allow_threads(|| f(&self.inner));
+        if let Some(e) = self.error.lock().unwrap().take() { return Err(e); }
+        Ok(out?)
+    }
+}
+
+#[pymethods]
+impl PyTrapModel {
+    /// `units` is (length [m], voltage [V], frequency [rad/s]) of one internal
+    /// unit; `electrodes` replaces the default descriptors e0, e1, ... on
+    /// channels 0, 1, ..., one per dc basis
+    #[new]
+    #[pyo3(signature = (rf, dc, c2lr_pair=None, units=None, electrodes=None))]
+    fn new(
+        py: Python<'_>,
+        rf: PyRef<'_, PyBasis>,
+        dc: Vec<PyRef<'_, PyBasis>>,
+        c2lr_pair: Option<(usize, usize)>,
+        units: Option<(f64, f64, f64)>,
+        electrodes: Option<Vec<PyElectrode>>,
+    ) -> PyResult<Self> {
+        let error = ErrorSlot::default();
+        let dc = dc.iter().map(|b| b.build(py, &error)).collect::<PyResult<Vec<_>>>()?;
+        if let Some((a, b)) = c2lr_pair {
+            if a >= dc.len() || b >= dc.len() {
+                return Err(PyValueError::new_err(format!("c2lr pair ({}, {}) out of range", a, b)));
+            }
+        }
+        let mut inner = TrapModel::new(rf.build(py, &error)?, dc, c2lr_pair);
+        if let Some((length, voltage, frequency)) = units { inner = inner.with_units(Units::new(length, voltage, frequency)); }
+        if let Some(electrodes) = electrodes { inner.set_electrodes(electrodes.into_iter().map(|e| e.inner).collect())?; }
+        Ok(Self { inner, error })
+    }
+
+    /// load a TOML or JSON config, returning the model and its waypoints
+    #[staticmethod]
+    fn from_config(path: &str) -> PyResult<(Self, Vec<PyWaypoint>)> {
+        let (inner, wps) = Config::load(path)?.build()?;
+        let wps = wps.into_iter().map(|inner| PyWaypoint { inner }).collect();
+        Ok((Self { inner, error: ErrorSlot::default() }, wps))
+    }
+
+    #[getter]
+    fn n_electrodes(&self) -> usize { self.inner.n_electrodes() }
+    #[getter]
+    fn electrode_names(&self) -> Vec<String> { self.inner.electrodes.iter().map(|e| e.name.clone()).collect() }
+    #[getter]
+    fn electrodes(&self) -> Vec<PyElectrode> { self.inner.electrodes.iter().map(|e| PyElectrode { inner: e.clone() }).collect() }
+    /// one descriptor per dc basis, in order
+    #[setter]
+    fn set_electrodes(&mut self, electrodes: Vec<PyElectrode>) -> PyResult<()> {
+        Ok(self.inner.set_electrodes(electrodes.into_iter().map(|e| e.inner).collect())?)
+    }
+    fn geometry_hash(&self) -> String { self.inner.geometry_hash() }
+
+    fn grad_total<'py>(&self, py: Python<'py>, r: [f64; 3], v: PyReadonlyArray1<f64>) -> PyResult<Array1<'py>> {
+        let v = self.voltages(v)?;
+        let g = self.run(py, |m| Ok(m.grad_total(vec3(r), &v)))?;
+        Ok(PyArray1::from_vec_bound(py, vec![g.x, g.y, g.z]))
+    }
+
+    fn hess_total<'py>(&self, py: Python<'py>, r: [f64; 3], v: PyReadonlyArray1<f64>) -> PyResult<Array2<'py>> {
+        let v = self.voltages(v)?;
+        let h = self.run(py, |m| Ok(m.hess_total(vec3(r), &v)))?;
+        to_array2(py, &hess_rows(h))
+    }
+}
+
+impl PyTrapModel {
+    fn voltages(&self, v: PyReadonlyArray1<f64>) -> PyResult<Vec<f64>> {
+        let v = v.as_array().to_vec();
+        if v.len() != self.inner.n_electrodes() {
+            return Err(PyValueError::new_err(format!("{} voltages for {} electrodes", v.len(), self.inner.n_electrodes())));
+        }
+        Ok(v)
+    }
+}
+
+fn solver_options(lam: Option<f64>, voltage_limit: Option<f64>, iters: Option<usize>, tol: Option<f64>) -> LsqOptions {
+    // no limit, or an infinite one, leaves the voltages unclamped
+    let mut opts = LsqOptions { voltage_limit: voltage_limit.filter(|v| v.is_finite()), ..Default::default() };
+    if let Some(l) = lam { opts.lambda = l; }
+    if let Some(n) = iters { opts.iters = n; }
+    if let Some(t) = tol { opts.tol = t; }
+    opts
+}
+
+/// constraint matrix A (6, n_electrodes) and rhs b (6,) in internal units
+#[pyfunction]
+fn build_constraints<'py>(py: Python<'py>, model: &PyTrapModel, waypoint: PyWaypoint)
+    -> PyResult<(Array2<'py>, Array1<'py>)> {
+    let (a, b) = model.run(py, |m| Ok(crate::constraints::build_constraints(m, &waypoint.inner)))?;
+    Ok((a.into_pyarray_bound(py), b.into_pyarray_bound(py)))
+}
+
+/// voltages (n_waypoints, n_electrodes); `lam` is the Tikhonov weight and
+/// `voltage_limit` a symmetric clamp [V], none by default
+#[pyfunction]
+#[pyo3(signature = (model, waypoints, left=false, lam=None, voltage_limit=None, iters=None, tol=None))]
+#[allow(clippy::too_many_arguments)]
+fn solve_waveform<'py>(
+    py: Python<'py>,
+    model: &PyTrapModel,
+    waypoints: Vec<PyWaypoint>,
+    left: bool,
+    lam: Option<f64>,
+    voltage_limit: Option<f64>,
+    iters: Option<usize>,
+    tol: Option<f64>,
+) -> PyResult<Array2<'py>> {
+    let wps = self::waypoints(waypoints);
+    let opts = solver_options(lam, voltage_limit, iters, tol);
+    let v = model.run(py, |m| crate::c2lr::solve_waveform(m, &wps, left, &opts))?;
+    to_array2(py, &v)
+}
+
+/// secular frequencies [rad/s] of a 3x3 potential Hessian [V/m^2]
+#[pyfunction]
+#[pyo3(signature = (hess, species="171Yb+"))]
+fn secular_freqs<'py>(py: Python<'py>, hess: PyReadonlyArray2<f64>, species: &str) -> PyResult<Array1<'py>> {
+    let h = hess.as_array();
+    if h.shape() != [3, 3] { return Err(PyValueError::new_err("hessian must be 3x3")); }
+    let h = Hess { xx: h[[0, 0]], yy: h[[1, 1]], zz: h[[2, 2]], xy: h[[0, 1]], xz: h[[0, 2]], yz: h[[1, 2]] };
+    let species: IonSpecies = species.parse()?;
+    Ok(PyArray1::from_vec_bound(py, crate::dynamics::secular_freqs(h, &species).to_vec()))
+}
+
+#[pyfunction]
+fn write_model_csv(path: &str, model: &PyTrapModel, volts: PyReadonlyArray2<f64>) -> PyResult<()> {
+    Ok(crate::io::write_model_csv(path, &model.inner, &rows(&volts))?)
+}
+
+#[pyfunction]
+fn read_model_csv<'py>(py: Python<'py>, path: &str, model: &PyTrapModel) -> PyResult<Array2<'py>> {
+    to_array2(py, &crate::io::read_model_csv(path, &model.inner)?)
+}
+
+/// write the csv and its metadata sidecar, recording the solver settings used
+#[pyfunction]
+#[pyo3(signature = (path, model, waypoints, volts, left=false, lam=None, voltage_limit=None, iters=None, tol=None))]
+#[allow(clippy::too_many_arguments)]
+fn write_waveform(
+    path: &str,
+    model: &PyTrapModel,
+    waypoints: Vec<PyWaypoint>,
+    volts: PyReadonlyArray2<f64>,
+    left: bool,
+    lam: Option<f64>,
+    voltage_limit: Option<f64>,
+    iters: Option<usize>,
+    tol: Option<f64>,
+) -> PyResult<()> {
+    let wps = self::waypoints(waypoints);
+    let meta = crate::io::WaveformMetadata::new(&model.inner, &wps, &solver_options(lam, voltage_limit, iters, tol), left);
+    Ok(crate::io::write_waveform(path, &model.inner, &rows(&volts), &meta)?)
+}
+
+/// voltages and the waypoints from the sidecar, which must exist and match the model
+#[pyfunction]
+fn read_waveform<'py>(py: Python<'py>, path: &str, model: &PyTrapModel)
+    -> PyResult<(Array2<'py>, Vec<PyWaypoint>)> {
+    let (volts, meta) = crate::io::read_waveform(path, &model.inner)?;
+    let wps = meta.waypoints.into_iter().map(|inner| PyWaypoint { inner }).collect();
+    Ok((to_array2(py, &volts)?, wps))
+}
+
+#[pyfunction]
+#[pyo3(signature = (path, model, waypoints, volts, times=None))]
+fn write_parquet(
+    py: Python<'_>,
+    path: &str,
+    model: &PyTrapModel,
+    waypoints: Vec<PyWaypoint>,
+    volts: PyReadonlyArray2<f64>,
+    times: Option<PyReadonlyArray1<f64>>,
+) -> PyResult<()> {
+    let wps = self::waypoints(waypoints);
+    let volts = rows(&volts);
+    let times = times.map(|t| t.as_array().to_vec());
+    model.run(py, |m| crate::io::write_parquet(path, m, &wps, &volts, times.as_deref()))
+}
+
+#[pyfunction]
+fn write_waveform_npz(py: Python<'_>, path: &str, model: &PyTrapModel, waypoints: Vec<PyWaypoint>, volts: PyReadonlyArray2<f64>) -> PyResult<()> {
+    let wps = self::waypoints(waypoints);
+    let volts = rows(&volts);
+    model.run(py, |m| crate::io::write_waveform_npz(path, m, &wps, &volts))
+}
+
+#[pyfunction]
+fn write_constraints_npz(py: Python<'_>, path: &str, model: &PyTrapModel, waypoints: Vec<PyWaypoint>) -> PyResult<()> {
+    let wps = self::waypoints(waypoints);
+    model.run(py, |m| crate::io::write_constraints_npz(path, m, &wps))
+}
+
+#[pymodule]
+fn ionwave(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyBasis>()?;
+    m.add_class::<PyWaypoint>()?;
+    m.add_class::<PyElectrode>()?;
+    m.add_class::<PyTrapModel>()?;
+    m.add_function(wrap_pyfunction!(build_constraints, m)?)?;
+    m.add_function(wrap_pyfunction!(solve_waveform, m)?)?;
+    m.add_function(wrap_pyfunction!(secular_freqs, m)?)?;
+    m.add_function(wrap_pyfunction!(write_model_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(read_model_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(write_waveform, m)?)?;
+    m.add_function(wrap_pyfunction!(read_waveform, m)?)?;
+    m.add_function(wrap_pyfunction!(write_parquet, m)?)?;
+    m.add_function(wrap_pyfunction!(write_waveform_npz, m)?)?;
+    m.add_function(wrap_pyfunction!(write_constraints_npz, m)?)?;
+    Ok(())
+}
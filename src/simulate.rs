@@ -0,0 +1,314 @@
+// src/simulate.rs
+//
+// Classical motion of a single ion through a timed waveform. The voltages
+// are interpolated between waypoints exactly as `TimedWaveform::resample`
+// plays them and the force comes from `TrapModel::grad_total`, so the RF
+// enters through its pseudopotential.
+
+use serde::{Deserialize, Serialize};
+use crate::basis::{RfDrive, TrapModel};
+use crate::channels::ChannelMap;
+use crate::dynamics::secular_freqs;
+use crate::species::IonSpecies;
+use crate::spline::{CubicSpline, SplineKind};
+use crate::timing::TimedWaveform;
+use crate::types::{Hess, IonwaveError, Result, Vec3, Waypoint};
+
+pub const HBAR: f64 = 1.054_571_817e-34;  // J s
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    /// fixed step, symplectic, conserves energy well in static wells
+    Verlet,
+    /// adaptive Dormand-Prince 5(4)
+    Rk45,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationOptions {
+    pub integrator: Integrator,
+    pub kind: SplineKind,            // voltage interpolation between waypoints
+    pub steps_per_period: f64,       // Verlet step, and RK45 largest step, per fastest secular period
+    pub rtol: f64,                   // RK45 only
+    pub initial_offset: Vec3,        // m, from the equilibrium at the first time stamp
+    pub initial_velocity: Vec3,      // m/s
+    pub explicit_rf: bool,           // integrate in the model's `rf_drive` instead of the pseudopotential
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self {
+            integrator: Integrator::Verlet,
+            kind: SplineKind::Monotone,
+            steps_per_period: 40.0,
+            rtol: 1e-9,
+            initial_offset: Vec3::ZERO,
+            initial_velocity: Vec3::ZERO,
+            explicit_rf: false,
+        }
+    }
+}
+
+/// The ion and the bottom of the instantaneous well at one step.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SimSample {
+    pub t: f64,          // s
+    pub r: Vec3,         // m
+    pub v: Vec3,         // m/s
+    pub well: Vec3,      // m, minimum of the potential at t
+    pub energy: f64,     // J, in the frame moving with the well
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Simulation {
+    pub species: IonSpecies,
+    pub samples: Vec<SimSample>,
+    pub steps: usize,
+    pub rejected: usize,         // RK45 steps retried with a smaller step
+    pub max_lag: f64,            // m, |r - well| over the transport
+    pub final_lag: f64,
+    pub final_kinetic: f64,      // J, lab frame
+    pub final_energy: f64,       // J, oscillation energy in the final well with the voltages held
+    pub energy_gain: f64,        // J, final_energy less the same measure at the start
+    pub final_quanta: f64,       // final_energy over ħω of the last waypoint's axial mode
+}
+
+// x = h^-1 g for a symmetric 3 by 3, None if singular
+fn solve3(h: Hess, g: Vec3) -> Option<Vec3> {
+    let c = [
+        h.yy * h.zz - h.yz * h.yz,
+        h.xz * h.yz - h.xy * h.zz,
+        h.xy * h.yz - h.xz * h.yy,
+        h.xx * h.zz - h.xz * h.xz,
+        h.xy * h.xz - h.xx * h.yz,
+        h.xx * h.yy - h.xy * h.xy,
+    ];
+    let det = h.xx * c[0] + h.xy * c[1] + h.xz * c[2];
+    if det == 0.0 || !det.is_finite() { return None; }
+    Some(Vec3 {
+        x: (c[0] * g.x + c[1] * g.y + c[2] * g.z) / det,
+        y: (c[1] * g.x + c[3] * g.y + c[4] * g.z) / det,
+        z: (c[2] * g.x + c[4] * g.y + c[5] * g.z) / det,
+    })
+}
+
+/// Bottom of the well nearest `guess` by Newton iteration on the field.
+pub fn equilibrium(model: &TrapModel, volts: &[f64], guess: Vec3) -> Result<Vec3> {
+    equilibrium_in_field(model, volts, Vec3::ZERO, guess)
+}
+
+/// Bottom of the well with an extra uniform electric field `field` (V/m)
+/// pushing on the ion, such as a measured stray field.
+pub fn equilibrium_in_field(model: &TrapModel, volts: &[f64], field: Vec3, guess: Vec3) -> Result<Vec3> {
+    let mut r = guess;
+    for _ in 0..50 {
+        let step = solve3(model.hess_total(r, volts), model.grad_total(r, volts) - field)
+            .ok_or_else(|| IonwaveError::Solver(format!("singular curvature at {:?}", r)))?;
+        r = r - step;
+        if step.norm() <= 1e-15 + 1e-12 * r.norm() { return Ok(r); }
+    }
+    Err(IonwaveError::Solver(format!("no equilibrium found near {:?}", guess)))
+}
+
+/// voltages on every electrode at any time, held beyond the ends
+pub(crate) struct Drive<'a> {
+    map: ChannelMap,
+    splines: Vec<CubicSpline>,
+    pub span: (f64, f64),
+    model: &'a TrapModel,
+    qm: f64,
+    rf: Option<&'a RfDrive>,
+}
+
+impl<'a> Drive<'a> {
+    pub fn new(model: &'a TrapModel, waypoints: &[Waypoint], timed: &TimedWaveform, kind: SplineKind) -> Result<Self> {
+        let map = timed.channel_map(model, waypoints)?;
+        let span = (timed.times[0], timed.times[timed.times.len() - 1]);
+        Ok(Self { map, splines: timed.splines(kind)?, span, model, qm: waypoints[0].species.charge_to_mass(), rf: None })
+    }
+
+    pub fn volts(&self, t: f64) -> Vec<f64> {
+        let t = t.clamp(self.span.0, self.span.1);
+        self.map.expand(&self.splines.iter().map(|s| s.eval(t)).collect::<Vec<_>>())
+    }
+
+    fn accel(&self, t: f64, r: Vec3) -> Vec3 {
+        match self.rf {
+            Some(rf) => (self.model.grad_dc(r, &self.volts(t)) + self.model.grad_background(r) + rf.grad(r) * (rf.omega() * t).cos()) * -self.qm,
+            None => self.model.grad_total(r, &self.volts(t)) * -self.qm,
+        }
+    }
+
+    /// energy of (r, v) in a well whose bottom sits at `well` moving with `v_well`
+    fn energy(&self, volts: &[f64], mass: f64, r: Vec3, v: Vec3, well: Vec3, v_well: Vec3) -> f64 {
+        let dv = v - v_well;
+        0.5 * mass * dv.dot(dv) + self.qm * mass * (self.model.phi_total(r, volts) - self.model.phi_total(well, volts))
+    }
+}
+
+type State = (f64, Vec3, Vec3);
+
+fn verlet(drive: &Drive, start: State, end: f64, dt_max: f64) -> Vec<State> {
+    let (t0, mut r, mut v) = start;
+    let n = ((end - t0) / dt_max).ceil().max(1.0) as usize;
+    let dt = (end - t0) / n as f64;
+    let mut a = drive.accel(t0, r);
+    let mut out = Vec::with_capacity(n + 1);
+    out.push(start);
+    for k in 1..=n {
+        let t = t0 + k as f64 * dt;
+        let vh = v + a * (0.5 * dt);
+        r = r + vh * dt;
+        a = drive.accel(t, r);
+        v = vh + a * (0.5 * dt);
+        out.push((t, r, v));
+    }
+    out
+}
+
+// Dormand-Prince tableau
+const C: [f64; 7] = [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+const A: [[f64; 6]; 7] = [
+    [0.0; 6],
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+    [19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0, 0.0, 0.0],
+    [9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0, 0.0],
+    [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0],
+];
+// fifth order weights less the embedded fourth order ones
+const E: [f64; 7] = [71.0 / 57600.0, 0.0, -71.0 / 16695.0, 71.0 / 1920.0, -17253.0 / 339200.0, 22.0 / 525.0, -1.0 / 40.0];
+
+fn rk45(drive: &Drive, start: State, end: f64, h_max: f64, rtol: f64, scale: (f64, f64)) -> (Vec<State>, usize) {
+    let (mut t, mut r, mut v) = start;
+    let mut out = vec![start];
+    let mut rejected = 0;
+    let mut h = h_max;
+    // first stage reuses the last of the previous step
+    let mut kr0 = v;
+    let mut kv0 = drive.accel(t, r);
+    while end - t > 1e-12 * h_max {
+        h = h.min(end - t);
+        let mut kr = [kr0; 7];
+        let mut kv = [kv0; 7];
+        for s in 1..7 {
+            let (mut dr, mut dv) = (Vec3::ZERO, Vec3::ZERO);
+            for j in 0..s {
+                dr = dr + kr[j] * A[s][j];
+                dv = dv + kv[j] * A[s][j];
+            }
+            let rs = r + dr * h;
+            kr[s] = v + dv * h;
+            kv[s] = drive.accel(t + C[s] * h, rs);
+        }
+        // stage 7 is the fifth order solution
+        let (r1, v1) = {
+            let (mut dr, mut dv) = (Vec3::ZERO, Vec3::ZERO);
+            for j in 0..6 {
+                dr = dr + kr[j] * A[6][j];
+                dv = dv + kv[j] * A[6][j];
+            }
+            (r + dr * h, v + dv * h)
+        };
+        let (mut er, mut ev) = (Vec3::ZERO, Vec3::ZERO);
+        for j in 0..7 {
+            er = er + kr[j] * (E[j] * h);
+            ev = ev + kv[j] * (E[j] * h);
+        }
+        let sr = rtol * (scale.0 + r.norm().max(r1.norm()));
+        let sv = rtol * (scale.1 + v.norm().max(v1.norm()));
+        let err = ((er.dot(er) / (sr * sr) + ev.dot(ev) / (sv * sv)) / 6.0).sqrt();
+        if err <= 1.0 {
+            t += h;
+            r = r1;
+            v = v1;
+            kr0 = kr[6];
+            kv0 = kv[6];
+            out.push((t, r, v));
+        } else {
+            rejected += 1;
+        }
+        h = (h * (0.9 * err.powf(-0.2)).clamp(0.2, 5.0)).min(h_max);
+    }
+    (out, rejected)
+}
+
+/// Move one ion through `timed`, starting at rest at the bottom of the well
+/// nearest the first waypoint (plus any offset in `opts`), and compare its
+/// motion with the instantaneous well along the way.
+pub fn simulate(
+    model: &TrapModel,
+    waypoints: &[Waypoint],
+    timed: &TimedWaveform,
+    opts: &SimulationOptions,
+) -> Result<Simulation> {
+    if !(opts.steps_per_period >= 1.0 && opts.rtol > 0.0 && opts.rtol < 1.0) {
+        return Err(IonwaveError::InvalidInput("simulation needs steps_per_period >= 1 and 0 < rtol < 1".to_string()));
+    }
+    let mut drive = Drive::new(model, waypoints, timed, opts.kind)?;
+    if opts.explicit_rf {
+        drive.rf = Some(model.rf_drive.as_ref()
+            .ok_or_else(|| IonwaveError::InvalidInput("explicit RF needs the model's rf_drive".to_string()))?);
+    }
+    let species = waypoints[0].species;
+    let (mass, qm) = (species.mass(), species.charge_to_mass());
+    let (t0, t1) = drive.span;
+
+    let v0 = drive.volts(t0);
+    let well0 = equilibrium(model, &v0, waypoints[0].r)?;
+    let fastest = secular_freqs(model.hess_total(well0, &v0), &species).into_iter().fold(0.0, f64::max);
+    if fastest <= 0.0 {
+        return Err(IonwaveError::InvalidInput("the ion is not trapped at the first waypoint".to_string()));
+    }
+    let fastest = drive.rf.map_or(fastest, |rf| fastest.max(rf.omega()));
+    let dt_max = 2.0 * std::f64::consts::PI / fastest / opts.steps_per_period;
+    let start = (t0, well0 + opts.initial_offset, opts.initial_velocity);
+
+    let (states, rejected) = match opts.integrator {
+        Integrator::Verlet => (verlet(&drive, start, t1, dt_max), 0),
+        // absolute scales of a micron and the velocity of a micron amplitude oscillation
+        Integrator::Rk45 => rk45(&drive, start, t1, dt_max, opts.rtol, (1e-6, 1e-6 * fastest)),
+    };
+
+    // follow the well from sample to sample
+    let mut wells = Vec::with_capacity(states.len());
+    let mut well = well0;
+    for &(t, _, _) in &states {
+        well = equilibrium(model, &drive.volts(t), well)?;
+        wells.push(well);
+    }
+    let n = states.len();
+    let well_velocity = |k: usize| {
+        let (a, b) = (k.saturating_sub(1), (k + 1).min(n - 1));
+        if a == b { return Vec3::ZERO; }
+        (wells[b] - wells[a]) / (states[b].0 - states[a].0)
+    };
+    let samples: Vec<SimSample> = states.iter().enumerate().map(|(k, &(t, r, v))| SimSample {
+        t, r, v,
+        well: wells[k],
+        energy: drive.energy(&drive.volts(t), mass, r, v, wells[k], well_velocity(k)),
+    }).collect();
+
+    let last = samples[n - 1];
+    let v_end = drive.volts(t1);
+    let initial_energy = drive.energy(&v0, mass, start.1, start.2, well0, Vec3::ZERO);
+    let final_energy = drive.energy(&v_end, mass, last.r, last.v, last.well, Vec3::ZERO);
+    let end_wp = &waypoints[waypoints.len() - 1];
+    let omega = (qm * model.hess_total(last.well, &v_end).quad(end_wp.axial_dir.unit())).max(0.0).sqrt();
+
+    Ok(Simulation {
+        species,
+        max_lag: samples.iter().map(|s| (s.r - s.well).norm()).fold(0.0, f64::max),
+        final_lag: (last.r - last.well).norm(),
+        final_kinetic: 0.5 * mass * last.v.dot(last.v),
+        final_energy,
+        energy_gain: final_energy - initial_energy,
+        final_quanta: if omega > 0.0 { final_energy / (HBAR * omega) } else { f64::INFINITY },
+        steps: n - 1,
+        rejected,
+        samples,
+    })
+}
@@ -0,0 +1,121 @@
+// src/spec.rs
+//
+// Serializable descriptions of potential bases, shared by the bases that
+// describe themselves and the configs that build them.
+
+use serde::{Deserialize, Serialize};
+use crate::types::{Hess, IonwaveError, Result, Vec3};
+
+pub(crate) fn invalid(msg: String) -> IonwaveError { IonwaveError::Config(msg) }
+
+// prefix a config error with where it happened
+pub(crate) fn context(what: String, err: IonwaveError) -> IonwaveError {
+    match err {
+        IonwaveError::Config(msg) => invalid(format!("{}: {}", what, msg)),
+        other => other,
+    }
+}
+
+pub(crate) fn finite(what: &str, v: f64) -> Result<()> {
+    if v.is_finite() { Ok(()) } else { Err(invalid(format!("{} must be finite, got {}", what, v))) }
+}
+
+pub(crate) fn positive(what: &str, v: f64) -> Result<()> {
+    if v > 0.0 && v.is_finite() { Ok(()) } else { Err(invalid(format!("{} must be positive, got {}", what, v))) }
+}
+
+pub(crate) fn finite_vec(what: &str, v: Vec3) -> Result<()> {
+    finite(what, v.x)?;
+    finite(what, v.y)?;
+    finite(what, v.z)
+}
+
+/// Serializable description of one potential basis, tagged by `type`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BasisSpec {
+    Gaussian {
+        center: Vec3,
+        sigma: f64,
+        scale: f64,
+        #[serde(default)]
+        cutoff: Option<f64>,
+    },
+    RfPseudo { kr: f64, kz: f64 },
+    /// φ = ½ (r - center)ᵀ curvature (r - center)
+    Quadratic { center: Vec3, curvature: Hess },
+    /// uniform field (V/m) plus a field gradient (V/m²) about `origin`
+    StrayField {
+        field: Vec3,
+        #[serde(default = "zero_hess")]
+        gradient: Hess,
+        #[serde(default = "zero_vec")]
+        origin: Vec3,
+    },
+}
+
+fn zero_hess() -> Hess { Hess::ZERO }
+fn zero_vec() -> Vec3 { Vec3::ZERO }
+
+impl BasisSpec {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            BasisSpec::Gaussian { center, sigma, scale, cutoff } => {
+                finite_vec("gaussian center", *center)?;
+                finite("gaussian scale", *scale)?;
+                positive("gaussian sigma", *sigma)?;
+                if let Some(k) = cutoff { positive("gaussian cutoff", *k)?; }
+                Ok(())
+            }
+            BasisSpec::RfPseudo { kr, kz } => {
+                finite("rf_pseudo kr", *kr)?;
+                finite("rf_pseudo kz", *kz)
+            }
+            BasisSpec::Quadratic { center, curvature: k } => {
+                finite_vec("quadratic center", *center)?;
+                [k.xx, k.yy, k.zz, k.xy, k.xz, k.yz].into_iter().try_for_each(|v| finite("quadratic curvature", v))
+            }
+            BasisSpec::StrayField { field, gradient: g, origin } => {
+                finite_vec("stray_field field", *field)?;
+                finite_vec("stray_field origin", *origin)?;
+                [g.xx, g.yy, g.zz, g.xy, g.xz, g.yz].into_iter().try_for_each(|v| finite("stray_field gradient", v))
+            }
+        }
+    }
+}
+
+/// Explicit RF drive, `amplitude * basis * cos(2π frequency t)`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RfDriveSpec {
+    pub amplitude: f64,   // V
+    pub frequency: f64,   // Hz
+    pub basis: BasisSpec,
+}
+
+impl RfDriveSpec {
+    pub fn validate(&self) -> Result<()> {
+        finite("rf_drive amplitude", self.amplitude)?;
+        positive("rf_drive frequency", self.frequency)?;
+        self.basis.validate().map_err(|e| context("rf_drive".to_string(), e))
+    }
+}
+
+/// Fixed background potential: a `stray_field` basis, or any basis held at
+/// `volts` for a patch or an electrode the waveform does not drive.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackgroundSpec {
+    #[serde(default = "default_volts")]
+    pub volts: f64,
+    pub basis: BasisSpec,
+}
+
+fn default_volts() -> f64 { 1.0 }
+
+impl BackgroundSpec {
+    pub fn validate(&self) -> Result<()> {
+        finite("background volts", self.volts)?;
+        self.basis.validate()
+    }
+}
@@ -0,0 +1,155 @@
+// src/species.rs
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use crate::types::{IonwaveError, Result};
+
+pub const ELEMENTARY_CHARGE: f64 = 1.602176634e-19;  // C
+pub const ATOMIC_MASS_UNIT: f64 = 1.66053906660e-27; // kg
+pub const ELECTRON_MASS: f64 = 9.1093837015e-31;     // kg
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Element { Be, Mg, Al, Ca, Zn, Sr, Cd, Ba, Yb, Hg }
+
+impl Element {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Element::Be => "Be", Element::Mg => "Mg", Element::Al => "Al",
+            Element::Ca => "Ca", Element::Zn => "Zn", Element::Sr => "Sr",
+            Element::Cd => "Cd", Element::Ba => "Ba", Element::Yb => "Yb",
+            Element::Hg => "Hg",
+        }
+    }
+    pub fn from_symbol(s: &str) -> Option<Element> {
+        ISOTOPES.iter().map(|iso| iso.0).find(|e| e.symbol() == s)
+    }
+}
+
+/// (element, mass number, neutral atomic mass in u), from AME2020
+const ISOTOPES: &[(Element, u16, f64)] = &[
+    (Element::Be, 9, 9.012_183_1),
+    (Element::Mg, 24, 23.985_041_697),
+    (Element::Mg, 25, 24.985_836_96),
+    (Element::Mg, 26, 25.982_592_97),
+    (Element::Al, 27, 26.981_538_41),
+    (Element::Ca, 40, 39.962_590_863),
+    (Element::Ca, 42, 41.958_617_83),
+    (Element::Ca, 43, 42.958_766_44),
+    (Element::Ca, 44, 43.955_481_6),
+    (Element::Ca, 48, 47.952_522_76),
+    (Element::Zn, 67, 66.927_127_75),
+    (Element::Sr, 86, 85.909_260_6),
+    (Element::Sr, 87, 86.908_877_5),
+    (Element::Sr, 88, 87.905_612_5),
+    (Element::Cd, 111, 110.904_182_87),
+    (Element::Cd, 113, 112.904_408_13),
+    (Element::Ba, 133, 132.906_007_4),
+    (Element::Ba, 135, 134.905_688_4),
+    (Element::Ba, 137, 136.905_827_4),
+    (Element::Ba, 138, 137.905_247_2),
+    (Element::Yb, 171, 170.936_331_5),
+    (Element::Yb, 172, 171.936_381_5),
+    (Element::Yb, 173, 172.938_210_8),
+    (Element::Yb, 174, 173.938_862_1),
+    (Element::Hg, 199, 198.968_280_64),
+    (Element::Hg, 201, 200.970_303_4),
+];
+
+/// A trapped ion: one catalogued isotope in a given charge state.
+/// Negative charge states add electrons to the neutral atom.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IonSpecies {
+    element: Element,
+    mass_number: u16,
+    charge_state: i32,
+    atomic_mass_u: f64,
+}
+
+impl IonSpecies {
+    pub fn new(element: Element, mass_number: u16, charge_state: i32) -> Result<Self> {
+        if charge_state == 0 {
+            return Err(IonwaveError::InvalidInput(format!("{}{} has zero charge", mass_number, element.symbol())));
+        }
+        let (_, _, atomic_mass_u) = ISOTOPES.iter()
+            .find(|iso| iso.0 == element && iso.1 == mass_number)
+            .copied()
+            .ok_or_else(|| IonwaveError::InvalidInput(format!("unknown isotope {}{}", mass_number, element.symbol())))?;
+        Ok(Self { element, mass_number, charge_state, atomic_mass_u })
+    }
+
+    /// every catalogued isotope as a singly charged positive ion
+    pub fn catalogue() -> Vec<IonSpecies> {
+        ISOTOPES.iter().map(|&(element, mass_number, atomic_mass_u)| {
+            IonSpecies { element, mass_number, charge_state: 1, atomic_mass_u }
+        }).collect()
+    }
+
+    pub fn element(&self) -> Element { self.element }
+    pub fn mass_number(&self) -> u16 { self.mass_number }
+    pub fn charge_state(&self) -> i32 { self.charge_state }
+
+    /// signed charge in C
+    pub fn charge(&self) -> f64 { self.charge_state as f64 * ELEMENTARY_CHARGE }
+
+    /// ion mass in kg, neutral atomic mass corrected for missing or extra electrons
+    pub fn mass(&self) -> f64 {
+        self.atomic_mass_u * ATOMIC_MASS_UNIT - self.charge_state as f64 * ELECTRON_MASS
+    }
+
+    /// q/m in C/kg
+    pub fn charge_to_mass(&self) -> f64 { self.charge() / self.mass() }
+
+    // shorthands for the species used throughout the examples
+    pub fn be9() -> Self { Self::new(Element::Be, 9, 1).unwrap() }
+    pub fn ca40() -> Self { Self::new(Element::Ca, 40, 1).unwrap() }
+    pub fn sr88() -> Self { Self::new(Element::Sr, 88, 1).unwrap() }
+    pub fn ba138() -> Self { Self::new(Element::Ba, 138, 1).unwrap() }
+    pub fn yb171() -> Self { Self::new(Element::Yb, 171, 1).unwrap() }
+}
+
+impl fmt::Display for IonSpecies {
+    /// "171Yb+", "88Sr2+", "27Al-"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.charge_state > 0 { '+' } else { '-' };
+        let z = self.charge_state.unsigned_abs();
+        if z == 1 {
+            write!(f, "{}{}{}", self.mass_number, self.element.symbol(), sign)
+        } else {
+            write!(f, "{}{}{}{}", self.mass_number, self.element.symbol(), z, sign)
+        }
+    }
+}
+
+impl FromStr for IonSpecies {
+    type Err = IonwaveError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bad = || IonwaveError::InvalidInput(format!("cannot parse ion species '{}'", s));
+        let s = s.trim();
+        let a_end = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(bad)?;
+        let mass_number: u16 = s[..a_end].parse().map_err(|_| bad())?;
+        let rest = &s[a_end..];
+        let el_end = rest.find(|c: char| !c.is_ascii_alphabetic()).ok_or_else(bad)?;
+        let element = Element::from_symbol(&rest[..el_end]).ok_or_else(bad)?;
+        let charge = &rest[el_end..];
+        let (digits, sign) = match (charge.strip_suffix('+'), charge.strip_suffix('-')) {
+            (Some(d), _) => (d, 1),
+            (_, Some(d)) => (d, -1),
+            _ => return Err(bad()),
+        };
+        if !digits.chars().all(|c| c.is_ascii_digit()) { return Err(bad()); }
+        let z: i32 = if digits.is_empty() { 1 } else { digits.parse().map_err(|_| bad())? };
+        IonSpecies::new(element, mass_number, sign * z)
+    }
+}
+
+impl TryFrom<String> for IonSpecies {
+    type Error = IonwaveError;
+    fn try_from(s: String) -> Result<Self> { s.parse() }
+}
+
+impl From<IonSpecies> for String {
+    fn from(s: IonSpecies) -> String { s.to_string() }
+}
@@ -0,0 +1,138 @@
+// src/spline.rs
+
+use serde::{Deserialize, Serialize};
+use crate::types::{IonwaveError, Result};
+
+/// How knot slopes are chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplineKind {
+    /// C2 natural cubic spline, zero curvature at both ends; may overshoot
+    Natural,
+    /// shape preserving PCHIP (Fritsch-Butland), never overshoots the data
+    Monotone,
+}
+
+/// Piecewise cubic through (t_i, y_i). Segment i covers [t_i, t_{i+1}] and
+/// holds `[a, b, c, d]` with y = a + b τ + c τ² + d τ³, τ = t - t_i.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubicSpline {
+    pub knots: Vec<f64>,
+    pub coeffs: Vec<[f64; 4]>,
+}
+
+fn check_knots(t: &[f64], y: &[f64]) -> Result<()> {
+    if t.len() != y.len() || t.len() < 2 {
+        return Err(IonwaveError::InvalidInput(format!(
+            "spline needs at least two points and as many values as knots, got {} knots and {} values", t.len(), y.len())));
+    }
+    if t.windows(2).any(|w| w[1] <= w[0]) || t.iter().chain(y).any(|v| !v.is_finite()) {
+        return Err(IonwaveError::InvalidInput("spline knots must be finite and strictly increasing".to_string()));
+    }
+    Ok(())
+}
+
+impl CubicSpline {
+    pub fn new(kind: SplineKind, t: &[f64], y: &[f64]) -> Result<Self> {
+        check_knots(t, y)?;
+        let slopes = match kind {
+            SplineKind::Natural => natural_slopes(t, y),
+            SplineKind::Monotone => monotone_slopes(t, y),
+        };
+        Ok(Self::hermite(t, y, &slopes))
+    }
+
+    /// cubic Hermite interpolant with the given slopes at the knots
+    pub fn hermite(t: &[f64], y: &[f64], m: &[f64]) -> Self {
+        let coeffs = (0..t.len() - 1).map(|i| {
+            let h = t[i + 1] - t[i];
+            let d = (y[i + 1] - y[i]) / h;
+            [y[i], m[i], (3.0 * d - 2.0 * m[i] - m[i + 1]) / h, (m[i] + m[i + 1] - 2.0 * d) / (h * h)]
+        }).collect();
+        Self { knots: t.to_vec(), coeffs }
+    }
+
+    pub fn n_segments(&self) -> usize { self.coeffs.len() }
+
+    /// segment containing t, the end segments extend beyond the knots
+    pub fn segment(&self, t: f64) -> usize {
+        let i = self.knots.partition_point(|&k| k <= t);
+        i.clamp(1, self.coeffs.len()) - 1
+    }
+
+    pub fn eval_segment(&self, i: usize, t: f64) -> f64 {
+        let [a, b, c, d] = self.coeffs[i];
+        let tau = t - self.knots[i];
+        a + tau * (b + tau * (c + tau * d))
+    }
+
+    pub fn eval(&self, t: f64) -> f64 { self.eval_segment(self.segment(t), t) }
+
+    pub fn derivative(&self, t: f64) -> f64 {
+        let i = self.segment(t);
+        let [_, b, c, d] = self.coeffs[i];
+        let tau = t - self.knots[i];
+        b + tau * (2.0 * c + tau * 3.0 * d)
+    }
+
+    /// values at ascending times, walking the segments once
+    pub fn eval_sorted(&self, times: impl IntoIterator<Item = f64>) -> Vec<f64> {
+        let mut i = 0;
+        times.into_iter().map(|t| {
+            while i + 1 < self.coeffs.len() && t >= self.knots[i + 1] { i += 1; }
+            self.eval_segment(i, t)
+        }).collect()
+    }
+}
+
+// second derivatives from the tridiagonal system with M_0 = M_n = 0, as slopes
+fn natural_slopes(t: &[f64], y: &[f64]) -> Vec<f64> {
+    let n = t.len();
+    let h: Vec<f64> = t.windows(2).map(|w| w[1] - w[0]).collect();
+    let d: Vec<f64> = (0..n - 1).map(|i| (y[i + 1] - y[i]) / h[i]).collect();
+    let mut m2 = vec![0.0; n];
+    if n > 2 {
+        // Thomas algorithm on the interior knots
+        let k = n - 2;
+        let mut diag: Vec<f64> = (0..k).map(|i| 2.0 * (h[i] + h[i + 1])).collect();
+        let mut rhs: Vec<f64> = (0..k).map(|i| 6.0 * (d[i + 1] - d[i])).collect();
+        for i in 1..k {
+            let w = h[i] / diag[i - 1];
+            diag[i] -= w * h[i];
+            rhs[i] -= w * rhs[i - 1];
+        }
+        m2[k] = rhs[k - 1] / diag[k - 1];
+        for i in (1..k).rev() {
+            m2[i] = (rhs[i - 1] - h[i] * m2[i + 1]) / diag[i - 1];
+        }
+    }
+    let mut slopes: Vec<f64> = (0..n - 1).map(|i| d[i] - h[i] * (2.0 * m2[i] + m2[i + 1]) / 6.0).collect();
+    slopes.push(d[n - 2] + h[n - 2] * (m2[n - 2] + 2.0 * m2[n - 1]) / 6.0);
+    slopes
+}
+
+fn monotone_slopes(t: &[f64], y: &[f64]) -> Vec<f64> {
+    let n = t.len();
+    let h: Vec<f64> = t.windows(2).map(|w| w[1] - w[0]).collect();
+    let d: Vec<f64> = (0..n - 1).map(|i| (y[i + 1] - y[i]) / h[i]).collect();
+    if n == 2 { return vec![d[0]; 2]; }
+    let mut m = vec![0.0; n];
+    for i in 1..n - 1 {
+        if d[i - 1] * d[i] > 0.0 {
+            // weighted harmonic mean
+            let w1 = 2.0 * h[i] + h[i - 1];
+            let w2 = h[i] + 2.0 * h[i - 1];
+            m[i] = (w1 + w2) / (w1 / d[i - 1] + w2 / d[i]);
+        }
+    }
+    // one sided three point ends, limited to keep the shape
+    let end = |h0: f64, h1: f64, d0: f64, d1: f64| {
+        let s = ((2.0 * h0 + h1) * d0 - h0 * d1) / (h0 + h1);
+        if s.signum() != d0.signum() || d0 == 0.0 { 0.0 }
+        else if d0.signum() != d1.signum() && s.abs() > 3.0 * d0.abs() { 3.0 * d0 }
+        else { s }
+    };
+    m[0] = end(h[0], h[1], d[0], d[1]);
+    m[n - 1] = end(h[n - 2], h[n - 3], d[n - 2], d[n - 3]);
+    m
+}
@@ -0,0 +1,96 @@
+// src/timing.rs
+
+use crate::basis::TrapModel;
+use crate::channels::{ChannelMap, ChannelWaveform};
+use crate::spline::{CubicSpline, SplineKind};
+use crate::types::{IonwaveError, Result, Waypoint};
+
+/// Channel voltages with a time stamp per waypoint row.
+#[derive(Clone, Debug)]
+pub struct TimedWaveform {
+    pub times: Vec<f64>,            // s, strictly increasing
+    pub channels: Vec<usize>,
+    pub volts: Vec<Vec<f64>>,       // one row per waypoint
+}
+
+/// `n` equally spaced times from 0 to `duration`
+pub fn uniform_times(n: usize, duration: f64) -> Vec<f64> {
+    if n < 2 { return vec![0.0; n]; }
+    (0..n).map(|i| duration * i as f64 / (n - 1) as f64).collect()
+}
+
+impl TimedWaveform {
+    pub fn new(times: Vec<f64>, channels: Vec<usize>, volts: Vec<Vec<f64>>) -> Result<Self> {
+        if times.len() != volts.len() {
+            return Err(IonwaveError::InvalidInput(format!("{} times for {} waveform rows", times.len(), volts.len())));
+        }
+        if times.len() < 2 || times.iter().any(|t| !t.is_finite()) || times.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(IonwaveError::InvalidInput("need at least two finite, strictly increasing times".to_string()));
+        }
+        if let Some(row) = volts.iter().find(|r| r.len() != channels.len()) {
+            return Err(IonwaveError::InvalidInput(format!("{} voltages for {} channels", row.len(), channels.len())));
+        }
+        Ok(Self { times, channels, volts })
+    }
+
+    pub fn from_channels(wf: &ChannelWaveform, times: Vec<f64>) -> Result<Self> {
+        Self::new(times, wf.channels.clone(), wf.channel_volts.clone())
+    }
+
+    /// The model's channel map, once the waveform is known to be for its
+    /// channels with a time stamp per waypoint.
+    pub fn channel_map(&self, model: &TrapModel, waypoints: &[Waypoint]) -> Result<ChannelMap> {
+        if waypoints.len() != self.times.len() {
+            return Err(IonwaveError::InvalidInput(format!("{} waypoints for {} time stamps", waypoints.len(), self.times.len())));
+        }
+        let map = ChannelMap::from_model(model)?;
+        if map.channels != self.channels {
+            return Err(IonwaveError::InvalidInput("waveform channels do not match the model".to_string()));
+        }
+        Ok(map)
+    }
+
+    pub fn duration(&self) -> f64 { self.times[self.times.len() - 1] - self.times[0] }
+
+    /// one spline through the waypoint voltages of every channel
+    pub fn splines(&self, kind: SplineKind) -> Result<Vec<CubicSpline>> {
+        (0..self.channels.len()).map(|c| {
+            let y: Vec<f64> = self.volts.iter().map(|row| row[c]).collect();
+            CubicSpline::new(kind, &self.times, &y)
+        }).collect()
+    }
+
+    /// Sample every channel at `rate` (Hz) from the first to the last time
+    /// stamp, interpolating between waypoints with `kind`.
+    pub fn resample(&self, rate: f64, kind: SplineKind) -> Result<SampleStreams> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(IonwaveError::InvalidInput(format!("sample rate {} must be positive", rate)));
+        }
+        let t0 = self.times[0];
+        // tolerate rounding so a duration that is a whole number of periods keeps its last sample
+        let n = (self.duration() * rate * (1.0 + 1e-12)).floor() as usize + 1;
+        let samples = self.splines(kind)?.iter()
+            .map(|s| s.eval_sorted((0..n).map(|k| t0 + k as f64 / rate)))
+            .collect();
+        Ok(SampleStreams { channels: self.channels.clone(), rate, t0, samples })
+    }
+}
+
+/// Fixed-rate samples, one stream per DAC channel.
+#[derive(Clone, Debug)]
+pub struct SampleStreams {
+    pub channels: Vec<usize>,
+    pub rate: f64,                  // Hz
+    pub t0: f64,                    // s, time of the first sample
+    pub samples: Vec<Vec<f64>>,     // [channel][sample]
+}
+
+impl SampleStreams {
+    pub fn n_samples(&self) -> usize { self.samples.first().map_or(0, |s| s.len()) }
+    pub fn time(&self, k: usize) -> f64 { self.t0 + k as f64 / self.rate }
+
+    /// rows of channel voltages per sample, the layout of `ChannelWaveform`
+    pub fn rows(&self) -> Vec<Vec<f64>> {
+        (0..self.n_samples()).map(|k| self.samples.iter().map(|s| s[k]).collect()).collect()
+    }
+}
@@ -0,0 +1,247 @@
+// src/trajectory.rs
+
+use serde::{Deserialize, Serialize};
+use crate::species::IonSpecies;
+use crate::types::{IonwaveError, Result, Vec3, Waypoint};
+
+/// Progress along the path against normalised time τ = t / T, s(0) = 0 and
+/// s(1) = 1. The shape of s sets how hard the ion is pushed on the way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum VelocityProfile {
+    /// constant velocity, the acceleration is concentrated in the end points
+    #[default]
+    Linear,
+    /// velocity ∝ sin²(πτ), starts and stops with zero velocity
+    SinSquared,
+    /// s = 10τ³ - 15τ⁴ + 6τ⁵, zero velocity and acceleration at both ends
+    MinimumJerk,
+    /// Gaussian velocity of rms `width` (in units of T) centred at τ = 1/2
+    Erf { width: f64 },
+    /// constant acceleration for the first half, constant deceleration after
+    BangBang,
+}
+
+impl VelocityProfile {
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            VelocityProfile::Erf { width } if !(width.is_finite() && width > 0.0) =>
+                Err(IonwaveError::InvalidInput(format!("erf profile width {} must be positive", width))),
+            _ => Ok(()),
+        }
+    }
+
+    /// s, ds/dτ and d²s/dτ² at τ, clamped to [0, 1]
+    pub fn eval(&self, tau: f64) -> (f64, f64, f64) {
+        use std::f64::consts::{PI, SQRT_2};
+        let x = tau.clamp(0.0, 1.0);
+        match *self {
+            VelocityProfile::Linear => (x, 1.0, 0.0),
+            VelocityProfile::SinSquared => (
+                x - (2.0 * PI * x).sin() / (2.0 * PI),
+                1.0 - (2.0 * PI * x).cos(),
+                2.0 * PI * (2.0 * PI * x).sin(),
+            ),
+            VelocityProfile::MinimumJerk => (
+                x * x * x * (10.0 - 15.0 * x + 6.0 * x * x),
+                30.0 * x * x * (1.0 - x) * (1.0 - x),
+                60.0 * x * (1.0 - x) * (1.0 - 2.0 * x),
+            ),
+            VelocityProfile::Erf { width } => {
+                // normalised so the truncated Gaussian still covers the whole path
+                let k = 1.0 / (SQRT_2 * width);
+                let norm = 2.0 * erf(0.5 * k);
+                let u = (x - 0.5) * k;
+                let v = (-u * u).exp() * 2.0 / PI.sqrt() * k / norm;
+                ((erf(u) + erf(0.5 * k)) / norm, v, -2.0 * u * k * v)
+            }
+            VelocityProfile::BangBang => {
+                if x < 0.5 { (2.0 * x * x, 4.0 * x, 4.0) } else { (1.0 - 2.0 * (1.0 - x).powi(2), 4.0 * (1.0 - x), -4.0) }
+            }
+        }
+    }
+}
+
+/// erf to about 1e-15, Taylor series near zero and a continued fraction for erfc beyond
+pub fn erf(x: f64) -> f64 {
+    let a = x.abs();
+    let r = if a < 2.0 {
+        // erf(x) = 2/√π Σ (-1)^n x^(2n+1) / (n! (2n+1))
+        let mut term = a;
+        let mut sum = a;
+        let mut n = 0.0;
+        while term.abs() > 1e-17 * sum.abs() {
+            n += 1.0;
+            term *= -a * a / n;
+            sum += term / (2.0 * n + 1.0);
+        }
+        sum * 2.0 / std::f64::consts::PI.sqrt()
+    } else {
+        // Lentz evaluation of erfc(x) = e^{-x²}/√π · 1/(x + 1/2/(x + 1/(x + 3/2/(x + ...))))
+        let tiny = 1e-300;
+        let mut f = a;
+        let mut c = a;
+        let mut d = 0.0;
+        for k in 1..200 {
+            let an = k as f64 / 2.0;
+            d = a + an * d;
+            d = if d.abs() < tiny { tiny } else { d };
+            c = a + an / c;
+            c = if c.abs() < tiny { tiny } else { c };
+            d = 1.0 / d;
+            let delta = c * d;
+            f *= delta;
+            if (delta - 1.0).abs() < 1e-16 { break; }
+        }
+        1.0 - (-a * a).exp() / (f * std::f64::consts::PI.sqrt())
+    };
+    r.copysign(x)
+}
+
+/// Piecewise straight path through `points`, parametrised by arc length.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransportPath {
+    pub points: Vec<Vec3>,
+    cumulative: Vec<f64>,
+}
+
+impl TransportPath {
+    pub fn new(points: Vec<Vec3>) -> Result<Self> {
+        if points.len() < 2 {
+            return Err(IonwaveError::InvalidInput("a path needs at least two points".to_string()));
+        }
+        let mut cumulative = vec![0.0];
+        for w in points.windows(2) {
+            let l = (w[1] - w[0]).norm();
+            if !(l.is_finite() && l > 0.0) {
+                return Err(IonwaveError::InvalidInput("path points must be finite and distinct".to_string()));
+            }
+            cumulative.push(cumulative[cumulative.len() - 1] + l);
+        }
+        Ok(Self { points, cumulative })
+    }
+
+    pub fn line(start: Vec3, end: Vec3) -> Result<Self> { Self::new(vec![start, end]) }
+
+    pub fn length(&self) -> f64 { self.cumulative[self.cumulative.len() - 1] }
+
+    /// position and unit tangent at arc length `l` from the start
+    pub fn at(&self, l: f64) -> (Vec3, Vec3) {
+        let i = self.cumulative.partition_point(|&c| c <= l).clamp(1, self.points.len() - 1) - 1;
+        let (a, b) = (self.points[i], self.points[i + 1]);
+        let seg = self.cumulative[i + 1] - self.cumulative[i];
+        let tangent = (b - a) / seg;
+        (a + tangent * (l - self.cumulative[i]), tangent)
+    }
+}
+
+/// s = 35τ⁴ - 84τ⁵ + 70τ⁶ - 20τ⁷, ds/dτ and d²s/dτ², at rest up to the
+/// jerk at both ends so the shortcut's trap centre also starts and stops at rest
+fn septic(tau: f64) -> (f64, f64, f64) {
+    let x = tau.clamp(0.0, 1.0);
+    (
+        x.powi(4) * (35.0 - 84.0 * x + 70.0 * x * x - 20.0 * x.powi(3)),
+        140.0 * x.powi(3) * (1.0 - x).powi(3),
+        420.0 * x * x * (1.0 - x).powi(2) * (1.0 - 2.0 * x),
+    )
+}
+
+/// Waypoints at equally spaced times with the ion's velocity and
+/// acceleration along the path.
+#[derive(Clone, Debug)]
+pub struct Trajectory {
+    pub times: Vec<f64>,            // s
+    pub waypoints: Vec<Waypoint>,
+    pub velocity: Vec<Vec3>,        // m/s
+    pub acceleration: Vec<Vec3>,    // m/s^2, along the path; corners are not smoothed
+}
+
+impl Trajectory {
+    /// `n` waypoints from t = 0 to `duration`, the trap's axis following the
+    /// path tangent.
+    pub fn generate(
+        path: &TransportPath,
+        duration: f64,
+        profile: VelocityProfile,
+        n: usize,
+        omega_axial: f64,
+        species: IonSpecies,
+    ) -> Result<Self> {
+        profile.validate()?;
+        if !(duration.is_finite() && duration > 0.0) || n < 2 {
+            return Err(IonwaveError::InvalidInput("trajectory needs a positive duration and at least two waypoints".to_string()));
+        }
+        let length = path.length();
+        let mut traj = Trajectory {
+            times: Vec::with_capacity(n),
+            waypoints: Vec::with_capacity(n),
+            velocity: Vec::with_capacity(n),
+            acceleration: Vec::with_capacity(n),
+        };
+        for i in 0..n {
+            let tau = i as f64 / (n - 1) as f64;
+            let (s, ds, d2s) = profile.eval(tau);
+            let (r, tangent) = path.at(s * length);
+            traj.times.push(tau * duration);
+            traj.waypoints.push(Waypoint { r, omega_axial, axial_dir: tangent, species });
+            traj.velocity.push(tangent * (ds * length / duration));
+            traj.acceleration.push(tangent * (d2s * length / (duration * duration)));
+        }
+        Ok(traj)
+    }
+
+    /// Shortcut to adiabaticity from Lewis–Riesenfeld invariants, for a trap
+    /// of constant `omega_axial`. The ion is made to follow s_c(t), at rest to
+    /// the jerk at both ends, and the trap centre is put at s_c + s̈_c / ω²:
+    /// the invariant is then centred on the ion, which ends in the ground
+    /// state whatever the duration. Waypoints are the trap centres, velocity
+    /// and acceleration those of the ion; short transports move the centre
+    /// well ahead of the ion, beyond the path ends along its end segments.
+    pub fn shortcut(
+        path: &TransportPath,
+        duration: f64,
+        n: usize,
+        omega_axial: f64,
+        species: IonSpecies,
+    ) -> Result<Self> {
+        if !(duration.is_finite() && duration > 0.0) || n < 2 {
+            return Err(IonwaveError::InvalidInput("trajectory needs a positive duration and at least two waypoints".to_string()));
+        }
+        if !(omega_axial.is_finite() && omega_axial > 0.0) {
+            return Err(IonwaveError::InvalidInput(format!("axial frequency {} must be positive", omega_axial)));
+        }
+        let length = path.length();
+        let wt2 = (omega_axial * duration).powi(2);
+        let mut traj = Trajectory {
+            times: Vec::with_capacity(n),
+            waypoints: Vec::with_capacity(n),
+            velocity: Vec::with_capacity(n),
+            acceleration: Vec::with_capacity(n),
+        };
+        for i in 0..n {
+            let tau = i as f64 / (n - 1) as f64;
+            let (s, ds, d2s) = septic(tau);
+            let (_, along) = path.at(s * length);
+            let (r, tangent) = path.at((s + d2s / wt2) * length);
+            traj.times.push(tau * duration);
+            traj.waypoints.push(Waypoint { r, omega_axial, axial_dir: tangent, species });
+            traj.velocity.push(along * (ds * length / duration));
+            traj.acceleration.push(along * (d2s * length / (duration * duration)));
+        }
+        Ok(traj)
+    }
+
+    /// Furthest the trap centre runs ahead of the ion in a `shortcut`,
+    /// ‖s̈_c‖ / ω², from the peak ion acceleration.
+    pub fn max_lead(&self) -> f64 {
+        self.waypoints.iter().zip(&self.acceleration)
+            .map(|(wp, a)| a.norm() / (wp.omega_axial * wp.omega_axial))
+            .fold(0.0, f64::max)
+    }
+
+    pub fn duration(&self) -> f64 { self.times[self.times.len() - 1] }
+
+    pub fn max_speed(&self) -> f64 { self.velocity.iter().map(|v| v.norm()).fold(0.0, f64::max) }
+
+    pub fn max_acceleration(&self) -> f64 { self.acceleration.iter().map(|a| a.norm()).fold(0.0, f64::max) }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use crate::species::IonSpecies;
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -8,6 +9,7 @@ pub struct Vec3 {
 }
 
 impl Vec3 {
+    pub const ZERO: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
     pub fn dot(self, o: Vec3) -> f64 { self.x*o.x + self.y*o.y + self.z*o.z }
     pub fn norm(self) -> f64 { self.dot(self).sqrt() }
     pub fn unit(self) -> Vec3 {
@@ -34,18 +36,28 @@ impl Div<f64> for Vec3 {
     fn div(self, s: f64) -> Vec3 { Vec3 { x: self.x/s, y: self.y/s, z: self.z/s } }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Hess {
     pub xx: f64, pub yy: f64, pub zz: f64,
     pub xy: f64, pub xz: f64, pub yz: f64,
 }
 
 impl Hess {
+    pub const ZERO: Hess = Hess { xx: 0.0, yy: 0.0, zz: 0.0, xy: 0.0, xz: 0.0, yz: 0.0 };
     pub fn quad(self, u: Vec3) -> f64 {
         // u^T H u
         let x = u.x; let y = u.y; let z = u.z;
         self.xx*x*x + self.yy*y*y + self.zz*z*z + 2.0*(self.xy*x*y + self.xz*x*z + self.yz*y*z)
     }
+    pub fn apply(self, u: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.xx*u.x + self.xy*u.y + self.xz*u.z,
+            y: self.xy*u.x + self.yy*u.y + self.yz*u.z,
+            z: self.xz*u.x + self.yz*u.y + self.zz*u.z,
+        }
+    }
+    // inherent for callers that predate `impl Add`
+    #[allow(clippy::should_implement_trait)]
     pub fn add(self, o: Hess) -> Hess {
         Hess {
             xx: self.xx + o.xx, yy: self.yy + o.yy, zz: self.zz + o.zz,
@@ -57,11 +69,17 @@ impl Hess {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Add for Hess {
+    type Output = Hess;
+    fn add(self, o: Hess) -> Hess { Hess::add(self, o) }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Waypoint {
     pub r: Vec3,
     pub omega_axial: f64,      // target angular frequency
     pub axial_dir: Vec3,       // unit vector
+    pub species: IonSpecies,   // ion the target frequency refers to
 }
 
 #[derive(Clone, Debug)]
@@ -76,6 +94,12 @@ pub enum IonwaveError {
     InvalidInput(String),
     #[error("solver failure: {0}")]
     Solver(String),
+    #[error("config error: {0}")]
+    Config(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("data frame error: {0}")]
+    Polars(#[from] polars::prelude::PolarsError),
 }
 
 pub type Result<T> = std::result::Result<T, IonwaveError>;
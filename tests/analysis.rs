@@ -0,0 +1,24 @@
+use ionwave::analysis::{analyze, summarize};
+use ionwave::basis::{PotentialBasis, QuadraticBasis, RfPseudo, TrapModel};
+use ionwave::species::IonSpecies;
+use ionwave::types::{Hess, Vec3, Waypoint};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+#[test]
+fn radial_modes_are_picked_by_direction_not_stiffness() {
+    let species = IonSpecies::yb171();
+    let k = |hz: f64| (TWO_PI * hz).powi(2) / species.charge_to_mass();
+    // the axial mode sits between the two radial ones
+    let dc: Vec<Box<dyn PotentialBasis>> = vec![
+        Box::new(QuadraticBasis { center: Vec3::ZERO, curvature: Hess { xx: k(1e6), yy: k(2e6), ..Hess::ZERO } }),
+    ];
+    let model = TrapModel::new(Box::new(RfPseudo { kr: 0.0, kz: k(1.5e6) }), dc, None);
+    let wp = Waypoint { r: Vec3::ZERO, omega_axial: TWO_PI * 1.5e6, axial_dir: Vec3 { x: 0.0, y: 0.0, z: 1.0 }, species };
+    let volts = vec![vec![1.0]];
+    let reports = analyze(&model, &[wp], &volts).unwrap();
+    let r = &reports[0];
+    assert!((r.axial_hz / 1.5e6 - 1.0).abs() < 1e-9);
+    assert!((r.radial_hz[0] / 1e6 - 1.0).abs() < 1e-9 && (r.radial_hz[1] / 2e6 - 1.0).abs() < 1e-9, "{:?}", r.radial_hz);
+    assert!((summarize(&reports, &volts).min_radial_hz / 1e6 - 1.0).abs() < 1e-9);
+}
@@ -10,12 +10,10 @@ fn c2lr_swap_holds() {
     let model = build_model(omega_axial);
     let wps = make_waypoints(9, omega_axial);
 
-    let q = 1.602e-19;
-    let m = 2.84e-25;
 
     let opts = LsqOptions { lambda: 1e-2, voltage_limit: Some(5.0), ..Default::default() };
-    let vr = solve_waveform(&model, &wps, q, m, false, &opts).expect("right");
-    let vl = solve_waveform(&model, &wps, q, m, true,  &opts).expect("left");
+    let vr = solve_waveform(&model, &wps, false, &opts).expect("right");
+    let vl = solve_waveform(&model, &wps, true,  &opts).expect("left");
 
     // first two electrodes are the rails so they should swap across all waypoints
     for i in 0..wps.len() {
@@ -29,16 +27,14 @@ fn axial_frequency_near_target() {
     let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
     let model = build_model(omega_axial);
     let wps = make_waypoints(9, omega_axial);
-    let q = 1.602e-19;
-    let m = 2.84e-25;
     let opts = LsqOptions { lambda: 1e-2, voltage_limit: Some(5.0), ..Default::default() };
-    let vr = solve_waveform(&model, &wps, q, m, false, &opts).expect("solve");
+    let vr = solve_waveform(&model, &wps, false, &opts).expect("solve");
 
     let u = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
     let mut max_dev_hz = 0.0;
     for (i, wp) in wps.iter().enumerate() {
         let h = model.hess_total(wp.r, &vr[i]);
-        let w = freq_along_axis(h, u, q, m);
+        let w = freq_along_axis(h, u, &wps[i].species);
         let f_hz = w / (2.0*std::f64::consts::PI);
         let dev = (f_hz - omega_axial/(2.0*std::f64::consts::PI)).abs();
         if dev > max_dev_hz { max_dev_hz = dev; }
@@ -52,9 +48,7 @@ fn constraint_shape_is_six_by_n() {
     let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
     let model = build_model(omega_axial);
     let wps = make_waypoints(1, omega_axial);
-    let q = 1.602e-19;
-    let m = 2.84e-25;
-    let (a, b) = ionwave::constraints::build_constraints(&model, &wps[0], q, m);
+    let (a, b) = ionwave::constraints::build_constraints(&model, &wps[0]);
     assert_eq!(a.nrows(), 6);
     assert_eq!(a.ncols(), model.n_electrodes());
     assert_eq!(b.len(), 6);
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{freq_along_axis, make_waypoints};
+use ionwave::basis::{Background, PotentialBasis, QuadraticBasis, RfPseudo, StrayFieldBasis, TrapModel};
+use ionwave::c2lr::solve_waveform;
+use ionwave::lsq::LsqOptions;
+use ionwave::types::{Hess, Vec3};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+fn uniform(field: Vec3) -> Box<dyn PotentialBasis> {
+    Box::new(StrayFieldBasis { field, gradient: Hess::ZERO, origin: Vec3::ZERO })
+}
+
+// one electrode per constraint row: three curvatures and three uniform fields
+fn ideal_trap() -> TrapModel {
+    let curvature = |curvature| Box::new(QuadraticBasis { center: Vec3::ZERO, curvature }) as Box<dyn PotentialBasis>;
+    let dc = vec![
+        curvature(Hess { zz: 1e8, ..Hess::ZERO }),
+        curvature(Hess { xx: 1e8, ..Hess::ZERO }),
+        curvature(Hess { yy: 1e8, ..Hess::ZERO }),
+        uniform(Vec3 { x: 1e4, y: 0.0, z: 0.0 }),
+        uniform(Vec3 { x: 0.0, y: 1e4, z: 0.0 }),
+        uniform(Vec3 { x: 0.0, y: 0.0, z: 1e4 }),
+    ];
+    TrapModel::new(Box::new(RfPseudo { kr: 1e10, kz: 0.0 }), dc, None)
+}
+
+#[test]
+fn waveforms_null_a_calibrated_stray_field() {
+    let omega = TWO_PI * 1.5e6;
+    let (field, gradient) = (Vec3 { x: 5.0, y: -10.0, z: 20.0 }, Hess { zz: 1e6, xz: 2e5, ..Hess::ZERO });
+    let model = ideal_trap().with_background(Background {
+        basis: Box::new(StrayFieldBasis { field, gradient, origin: Vec3::ZERO }),
+        volts: 1.0,
+    });
+    let wps = make_waypoints(5, omega);
+    let opts = LsqOptions { lambda: 1e-12, voltage_limit: None, iters: 2000, ..Default::default() };
+
+    let volts = solve_waveform(&model, &wps, false, &opts).unwrap();
+    for (wp, v) in wps.iter().zip(&volts) {
+        let g = model.grad_total(wp.r, v);
+        assert!(g.norm() < 1e-4 * field.norm(), "{:?}", g);
+        let w = freq_along_axis(model.hess_total(wp.r, v), wp.axial_dir, &wp.species);
+        assert!((w / omega - 1.0).abs() < 1e-6, "{}", w / TWO_PI);
+    }
+
+    // a waveform solved without the background leaves the stray field on the ion
+    let plain = solve_waveform(&ideal_trap(), &wps, false, &opts).unwrap();
+    for (wp, v) in wps.iter().zip(&plain) {
+        let stray = field + gradient.apply(wp.r);
+        assert!((model.grad_total(wp.r, v) + stray).norm() < 1e-4 * field.norm());
+        let w = freq_along_axis(model.hess_total(wp.r, v), wp.axial_dir, &wp.species);
+        assert!(w < omega * (1.0 - 1e-3));
+    }
+}
@@ -0,0 +1,25 @@
+/* exercised by tests/ffi.rs: solve the demo config through the C ABI */
+#include <stdio.h>
+#include <stdlib.h>
+#include "ionwave.h"
+
+int main(int argc, char **argv) {
+    IonwaveModel *model = NULL;
+    if (ionwave_model_load("does/not/exist.toml", &model) != IONWAVE_STATUS_IO || ionwave_last_error() == NULL) {
+        return 2;
+    }
+    IonwaveStatus status = ionwave_model_load(argv[1], &model);
+    if (status != IONWAVE_STATUS_OK) {
+        fprintf(stderr, "%s: %s\n", ionwave_status_name(status), ionwave_last_error());
+        return 1;
+    }
+    size_t n = ionwave_model_n_waypoints(model) * ionwave_model_n_electrodes(model);
+    double *volts = malloc(n * sizeof(double));
+    if (ionwave_solve(model, false, volts, n - 1) != IONWAVE_STATUS_BUFFER_TOO_SMALL) return 3;
+    status = ionwave_solve(model, false, volts, n);
+    if (status != IONWAVE_STATUS_OK) return 4;
+    printf("%s %zu %s %.17g\n", ionwave_version(), n, ionwave_model_electrode_name(model, 1), volts[n - 1]);
+    free(volts);
+    ionwave_model_free(model);
+    return 0;
+}
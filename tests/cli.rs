@@ -0,0 +1,119 @@
+use std::process::Command;
+
+fn ionwave(args: &[&str]) -> String {
+    let out = Command::new(env!("CARGO_BIN_EXE_ionwave")).args(args).output().expect("run ionwave");
+    assert!(out.status.success(), "ionwave {:?} failed: {}", args, String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn solve_then_analyze_and_export() {
+    let config = concat!(env!("CARGO_MANIFEST_DIR"), "/configs/demo.toml");
+    let dir = std::env::temp_dir().join("ionwave_cli");
+    let dir = dir.to_str().unwrap();
+
+    let solved: serde_json::Value = serde_json::from_str(&ionwave(&["--json", "solve", config, "-o", dir])).unwrap();
+    assert_eq!(solved["summary"]["n_waypoints"], 15);
+
+    let waveform = format!("{}/waveforms.csv", dir);
+    let analyzed: serde_json::Value = serde_json::from_str(&ionwave(&["--json", "analyze", config, &waveform])).unwrap();
+    assert_eq!(analyzed, solved["summary"]);
+    assert!(analyzed["max_axial_dev_hz"].as_f64().unwrap() < 1e3);
+
+    let exported = format!("{}/dac.csv", dir);
+    let written: serde_json::Value = serde_json::from_str(&ionwave(&["--json", "export", config, &waveform, "-o", &exported])).unwrap();
+    assert_eq!(written["rows"], 15);
+    let text = std::fs::read_to_string(&exported).unwrap();
+    assert!(text.starts_with("ch0,ch1,"));
+    assert_eq!(text.lines().count(), 16);
+
+    let image = format!("{}/dac.hex", dir);
+    let report: serde_json::Value = serde_json::from_str(
+        &ionwave(&["--json", "export", config, &waveform, "-f", "dac-hex", "-o", &image])).unwrap();
+    // channel 2 of the demo board has 18 bits, five hex digits
+    assert_eq!(report["bits"][1], 16);
+    assert_eq!(report["bits"][2], 18);
+    let hex = std::fs::read_to_string(&image).unwrap();
+    assert_eq!(hex.lines().count(), 15);
+    assert_eq!(hex.lines().next().unwrap().split(' ').map(str::len).take(3).collect::<Vec<_>>(), [4, 4, 5]);
+
+    let streams = format!("{}/predistorted.npz", dir);
+    let predistorted: serde_json::Value = serde_json::from_str(&ionwave(&[
+        "--json", "resample", config, &waveform, "--duration", "10e-6", "--rate", "50e6", "--predistort", "-o", &streams])).unwrap();
+    let error = |k: &str| predistorted[k]["max_error_volts"].as_f64().unwrap();
+    assert!(error("corrected") < error("uncorrected"));
+    assert!(std::path::Path::new(&streams).exists());
+
+    let sim: serde_json::Value = serde_json::from_str(
+        &ionwave(&["--json", "simulate", config, &waveform, "--duration", "10e-6", "--integrator", "rk45"])).unwrap();
+    assert!(sim["steps"].as_u64().unwrap() > 100);
+    assert!(sim["final_energy"].as_f64().unwrap() >= 0.0);
+
+    let stability: serde_json::Value = serde_json::from_str(&ionwave(&["--json", "stability", config, &waveform])).unwrap();
+    assert_eq!(stability["stable"], true);
+    assert!((stability["max_abs_q"].as_f64().unwrap() - 0.338).abs() < 1e-3);
+
+    let excitation: serde_json::Value = serde_json::from_str(
+        &ionwave(&["--json", "excitation", config, &waveform, "--duration", "10e-6"])).unwrap();
+    assert!(excitation["mean_phonons"].as_f64().unwrap() >= 0.0);
+
+    let compensated = format!("{}/compensated.csv", dir);
+    let compensation: serde_json::Value = serde_json::from_str(&ionwave(&[
+        "--json", "compensate", config, &waveform, "--stray-field", "-3,0,0", "-o", &compensated])).unwrap();
+    let (before, after) = (compensation["max_micromotion_before"].as_f64().unwrap(), compensation["max_micromotion_after"].as_f64().unwrap());
+    assert!(before > 0.0 && after <= before);
+    // the offsets null the applied 3 V/m, not just dent it
+    let residual = compensation["max_residual_field"].as_f64().unwrap();
+    assert!(residual < 1e-3 * 3.0, "{}", residual);
+    assert_eq!(std::fs::read_to_string(&compensated).unwrap().lines().count(), 16);
+
+    let heating: serde_json::Value = serde_json::from_str(&ionwave(&["--json", "heating", config, &waveform])).unwrap();
+    assert!(heating["max_heating_rate"].as_f64().unwrap() > 0.0);
+    assert!(heating["noisiest_channel"].as_u64().is_some());
+
+    let optimized = format!("{}/optimized.csv", dir);
+    let control: serde_json::Value = serde_json::from_str(&ionwave(&[
+        "--json", "optimize", config, &waveform, "--duration", "10e-6", "--iterations", "5", "-o", &optimized])).unwrap();
+    assert!(control["final_quanta"].as_f64().unwrap() <= control["initial_quanta"].as_f64().unwrap());
+    assert_eq!(std::fs::read_to_string(&optimized).unwrap().lines().count(), 16);
+    let meta: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(format!("{}/optimized.meta.json", dir)).unwrap()).unwrap();
+    assert_eq!(meta["playback"], serde_json::json!({ "kind": "natural", "duration": 10e-6 }));
+
+    let designed: serde_json::Value = serde_json::from_str(
+        &ionwave(&["--json", "solve", config, "-o", &format!("{}/designed", dir), "--min-excitation", "5e-6"])).unwrap();
+    assert_eq!(designed["profiles"].as_array().unwrap().len(), 1);
+    assert!(designed["profiles"][0]["mean_phonons"].as_f64().unwrap() < 1.0);
+
+    let shortcut: serde_json::Value = serde_json::from_str(
+        &ionwave(&["--json", "solve", config, "-o", &format!("{}/shortcut", dir), "--shortcut", "2e-6"])).unwrap();
+    assert_eq!(shortcut["summary"]["n_waypoints"], 15);
+    assert!(shortcut["shortcuts"][0]["max_lead"].as_f64().unwrap() > 0.0);
+
+    let sweep: serde_json::Value = serde_json::from_str(
+        &ionwave(&["--json", "sweep", config, "-p", "lambda", "--values", "1e-3,1e-1"])).unwrap();
+    assert_eq!(sweep.as_array().unwrap().len(), 2);
+    assert_eq!(sweep[1]["value"], 0.1);
+}
+
+#[test]
+fn resample_refuses_inconsistent_ganging() {
+    let config = concat!(env!("CARGO_MANIFEST_DIR"), "/configs/demo.toml");
+    let dir = std::env::temp_dir().join("ionwave_cli_ganging");
+    let dir = dir.to_str().unwrap();
+    ionwave(&["--json", "solve", config, "-o", dir]);
+
+    // gang the first electrode with the centre lobe e2, which the solve drove apart
+    let demo = std::fs::read_to_string(config).unwrap();
+    let at = demo.find("[[trap.electrodes]]").unwrap();
+    let at = at + demo[at..].find('\n').unwrap() + 1;
+    let at = at + demo[at..].find('\n').unwrap() + 1;
+    let ganged = format!("{}/ganged.toml", dir);
+    std::fs::write(&ganged, format!("{}dac_channel = 2\n{}", &demo[..at], &demo[at..])).unwrap();
+
+    let waveform = format!("{}/waveforms.csv", dir);
+    let out = Command::new(env!("CARGO_BIN_EXE_ionwave"))
+        .args(["resample", &ganged, &waveform, "--no-verify", "--duration", "10e-6", "--rate", "50e6", "-o", &format!("{}/s.npz", dir)])
+        .output().expect("run ionwave");
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("inconsistently"), "{}", String::from_utf8_lossy(&out.stderr));
+}
@@ -1,15 +1,17 @@
 // Shared helpers for tests
+#![allow(dead_code)]
 
-use ionwave::basis::{TrapModel, RfPseudo, GaussianBasis, PotentialBasis};
-use ionwave::types::Vec3;
+use ionwave::basis::{TrapModel, RfPseudo, GaussianBasis, PotentialBasis, QuadraticBasis, RfDrive};
+use ionwave::species::IonSpecies;
+use ionwave::types::{Hess, Vec3, Waypoint};
 
 pub fn build_model(omega_axial: f64) -> TrapModel {
-    // physical constants
-    let q = 1.602e-19;
-    let m = 2.84e-25;
+    build_model_with_cutoff(omega_axial, None)
+}
 
+pub fn build_model_with_cutoff(omega_axial: f64, cutoff: Option<f64>) -> TrapModel {
     // rf curvature from target
-    let target_curv = m * omega_axial * omega_axial / q;
+    let target_curv = omega_axial * omega_axial / IonSpecies::yb171().charge_to_mass();
     let rf = RfPseudo { kr: 1.0e10, kz: target_curv };
 
     // gentle dc basis set
@@ -18,12 +20,12 @@ pub fn build_model(omega_axial: f64) -> TrapModel {
     let dc_scale = 0.002;
     let z_positions: Vec<f64> = (-4..=4).map(|k| k as f64 * 63e-6).collect();
     for (idx, zc) in z_positions.iter().enumerate() {
-        let left = GaussianBasis { center: Vec3 { x: -50e-6, y: 0.0, z: *zc }, sigma, scale: dc_scale };
-        let right = GaussianBasis { center: Vec3 { x:  50e-6, y: 0.0, z: *zc }, sigma, scale: dc_scale };
+        let left = GaussianBasis { center: Vec3 { x: -50e-6, y: 0.0, z: *zc }, sigma, scale: dc_scale, cutoff };
+        let right = GaussianBasis { center: Vec3 { x:  50e-6, y: 0.0, z: *zc }, sigma, scale: dc_scale, cutoff };
         dc.push(Box::new(left));
         dc.push(Box::new(right));
         if idx % 2 == 0 {
-            let center = GaussianBasis { center: Vec3 { x: 0.0, y: 0.0, z: *zc + 0.5*63e-6 }, sigma, scale: 0.8 * dc_scale };
+            let center = GaussianBasis { center: Vec3 { x: 0.0, y: 0.0, z: *zc + 0.5*63e-6 }, sigma, scale: 0.8 * dc_scale, cutoff };
             dc.push(Box::new(center));
         }
     }
@@ -38,12 +40,48 @@ pub fn make_waypoints(n_wp: usize, omega_axial: f64) -> Vec<ionwave::types::Wayp
     (0..n_wp).map(|i| {
         let t = i as f64 / (n_wp as f64 - 1.0);
         let z = z0 + t * dz;
-        ionwave::types::Waypoint { r: Vec3 { x: 0.0, y: 0.0, z }, omega_axial, axial_dir }
+        ionwave::types::Waypoint { r: Vec3 { x: 0.0, y: 0.0, z }, omega_axial, axial_dir, species: IonSpecies::yb171() }
     }).collect()
 }
 
-pub fn freq_along_axis(h: ionwave::types::Hess, u: Vec3, q: f64, m: f64) -> f64 {
+pub fn freq_along_axis(h: ionwave::types::Hess, u: Vec3, species: &IonSpecies) -> f64 {
     let curv = h.quad(u);
-    let w = (q * curv / m).max(0.0).sqrt();
-    w
+    (species.charge_to_mass() * curv).max(0.0).sqrt()
+}
+
+/// uniform field along z, one volt gives phi = z
+pub struct Tilt;
+impl PotentialBasis for Tilt {
+    fn phi(&self, r: Vec3) -> f64 { r.z }
+    fn grad(&self, _r: Vec3) -> Vec3 { Vec3 { x: 0.0, y: 0.0, z: 1.0 } }
+    fn hess(&self, _r: Vec3) -> Hess { Hess { xx: 0.0, yy: 0.0, zz: 0.0, xy: 0.0, xz: 0.0, yz: 0.0 } }
+}
+
+/// harmonic well of axial frequency omega whose bottom sits at -V / kz
+pub fn tilted_well(omega: f64) -> (TrapModel, f64) {
+    let kz = omega * omega / IonSpecies::yb171().charge_to_mass();
+    (TrapModel::new(Box::new(RfPseudo { kr: 4.0 * kz, kz }), vec![Box::new(Tilt)], None), kz)
+}
+
+/// 171Yb+ at the origin with its axis along z
+pub fn waypoint(omega_axial: f64) -> Waypoint {
+    Waypoint { r: Vec3::ZERO, omega_axial, axial_dir: Vec3 { x: 0.0, y: 0.0, z: 1.0 }, species: IonSpecies::yb171() }
+}
+
+/// rf drive frequency of `linear_trap`, Hz
+pub const RF_FREQUENCY: f64 = 40e6;
+
+/// Linear quadrupole with radial q = 0.3 for 171Yb+, its null along z, as an
+/// explicit drive and the matching pseudopotential, with the given dc
+/// electrodes. Returns the model and the radial pseudopotential curvature.
+pub fn linear_trap(dc: Vec<Box<dyn PotentialBasis>>) -> (TrapModel, f64) {
+    let species = IonSpecies::yb171();
+    let omega_rf = 2.0 * std::f64::consts::PI * RF_FREQUENCY;
+    let drive = RfDrive {
+        basis: Box::new(QuadraticBasis { center: Vec3::ZERO, curvature: Hess { xx: -1.0, yy: 1.0, ..Hess::ZERO } }),
+        amplitude: 0.3 * omega_rf * omega_rf / (2.0 * species.charge_to_mass()),
+        frequency: RF_FREQUENCY,
+    };
+    let kr = drive.pseudo_hess(Vec3::ZERO, &species).xx;
+    (TrapModel::new(Box::new(RfPseudo { kr, kz: 0.0 }), dc, None).with_rf_drive(drive), kr)
 }
@@ -0,0 +1,102 @@
+mod common;
+use common::{linear_trap, waypoint};
+use ionwave::basis::{PotentialBasis, QuadraticBasis, TrapModel};
+use ionwave::compensation::{apply_compensation, compensate, rf_null};
+use ionwave::lsq::LsqOptions;
+use ionwave::species::IonSpecies;
+use ionwave::types::{Hess, Vec3};
+use ionwave::units::Units;
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+fn quadratic(center: Vec3, curvature: Hess) -> QuadraticBasis { QuadraticBasis { center, curvature } }
+
+// the linear trap with an axial electrode and two electrodes 100 um off axis
+// that push along x and y but also curve; also the axial electrode's volts for 1 MHz
+fn trap() -> (TrapModel, f64) {
+    let dc: Vec<Box<dyn PotentialBasis>> = vec![
+        Box::new(quadratic(Vec3::ZERO, Hess { zz: 1e8, ..Hess::ZERO })),
+        Box::new(quadratic(Vec3 { x: -1e-4, y: 0.0, z: 0.0 }, Hess { xx: 1e8, zz: 1e8, ..Hess::ZERO })),
+        Box::new(quadratic(Vec3 { x: 0.0, y: -1e-4, z: 0.0 }, Hess { yy: 1e8, zz: -5e7, ..Hess::ZERO })),
+    ];
+    let kz = (TWO_PI * 1e6).powi(2) / IonSpecies::yb171().charge_to_mass();
+    (linear_trap(dc).0, kz / 1e8)
+}
+
+fn exact() -> LsqOptions { LsqOptions { lambda: 1e-14, voltage_limit: None, ..Default::default() } }
+
+#[test]
+fn rf_null_of_a_linear_trap_is_its_axis() {
+    let (model, _) = trap();
+    let null = rf_null(model.rf_drive.as_ref().unwrap(), Vec3 { x: 3e-6, y: -2e-6, z: 5e-6 }).unwrap();
+    assert!((null - Vec3 { x: 0.0, y: 0.0, z: 5e-6 }).norm() < 1e-15, "{:?}", null);
+}
+
+#[test]
+fn offsets_null_a_stray_field_and_keep_the_axial_frequency() {
+    let (model, v_axial) = trap();
+    let wps = [waypoint(TWO_PI * 1e6), waypoint(TWO_PI * 1e6)];
+    let volts = vec![vec![v_axial, 0.0, 0.0]; 2];
+    let stray = [Vec3 { x: 30.0, y: -10.0, z: 0.0 }, Vec3 { x: 0.0, y: 50.0, z: 0.0 }];
+    let comps = compensate(&model, &wps, &volts, &stray, &exact()).unwrap();
+    let species = IonSpecies::yb171();
+
+    let compensated = apply_compensation(&volts, &comps);
+    for (c, v) in comps.iter().zip(&compensated) {
+        assert!(c.micromotion_before > 1e-9, "{}", c.micromotion_before);
+        assert!(c.micromotion_after < 1e-6 * c.micromotion_before, "{} {}", c.micromotion_before, c.micromotion_after);
+        assert!(c.residual_field < 1e-6, "{}", c.residual_field);
+        assert!(c.rf_null.norm() < 1e-15);
+        let axial = (species.charge_to_mass() * model.hess_total(c.rf_null, v).zz).sqrt() / TWO_PI;
+        assert!((axial - 1e6).abs() < 1.0, "{}", axial);
+    }
+    // the push along x needs E / 1e4 V on its electrode, the axial one takes back its curvature
+    assert!((comps[0].offset[1] - 30.0 / 1e4).abs() < 1e-9, "{:?}", comps[0].offset);
+    assert!((comps[0].offset[0] + 30.0 / 1e4 + 0.5 * 10.0 / 1e4).abs() < 1e-9, "{:?}", comps[0].offset);
+    // the waveform's λ is not the offsets' λ
+    let waveform_lambda = LsqOptions { voltage_limit: None, ..Default::default() };
+    for (c, d) in comps.iter().zip(compensate(&model, &wps, &volts, &stray, &waveform_lambda).unwrap()) {
+        assert!(c.offset.iter().zip(&d.offset).all(|(a, b)| (a - b).abs() < 1e-9), "{:?} {:?}", c.offset, d.offset);
+    }
+
+    // field vectors give a unit field at the null without curving it axially
+    for (k, fv) in comps[0].field_vectors.iter().take(2).enumerate() {
+        let e = model.grad_dc(Vec3::ZERO, fv) * -1.0;
+        let want = if k == 0 { Vec3 { x: 1.0, y: 0.0, z: 0.0 } } else { Vec3 { x: 0.0, y: 1.0, z: 0.0 } };
+        assert!((e - want).norm() < 1e-9, "{:?}", e);
+        assert!(model.hess_dc(Vec3::ZERO, fv).zz.abs() < 1e-3);
+    }
+}
+
+#[test]
+fn offsets_respect_the_voltage_limit() {
+    let (model, v_axial) = trap();
+    let volts = vec![vec![v_axial, 0.0, 0.0]];
+    // 1.5 V would null the field, the push electrode stops at 1 V
+    let opts = LsqOptions { voltage_limit: Some(1.0), ..exact() };
+    let comps = compensate(&model, &[waypoint(TWO_PI * 1e6)], &volts, &[Vec3 { x: 1.5e4, y: 0.0, z: 0.0 }], &opts).unwrap();
+    let c = &comps[0];
+    assert!((c.offset[1] - 1.0).abs() < 1e-12, "{:?}", c.offset);
+    assert!((c.residual_field - 0.5e4).abs() < 1.0, "{}", c.residual_field);
+    assert!(c.micromotion_after < 0.5 * c.micromotion_before);
+}
+
+#[test]
+fn bound_offsets_land_on_the_limit_in_any_units() {
+    // 1.4 V comes back from 0.3 V units a bit larger
+    let (model, v_axial) = trap();
+    let model = model.with_units(Units::new(1e-6, 0.3, 1e7));
+    let opts = LsqOptions { voltage_limit: Some(1.4), ..exact() };
+    let comps = compensate(&model, &[waypoint(TWO_PI * 1e6)], &[vec![v_axial, 0.0, 0.0]], &[Vec3 { x: 1.5e4, y: 0.0, z: 0.0 }], &opts).unwrap();
+    assert_eq!(comps[0].offset[1], 1.4);
+}
+
+#[test]
+fn compensation_needs_a_drive_and_matching_strays() {
+    let (model, v_axial) = trap();
+    let volts = vec![vec![v_axial, 0.0, 0.0]; 2];
+    assert!(compensate(&model, &[waypoint(TWO_PI * 1e6), waypoint(TWO_PI * 1e6)], &volts, &[Vec3::ZERO; 3], &exact()).is_err());
+    let (mut bare, _) = trap();
+    bare.rf_drive = None;
+    assert!(compensate(&bare, &[waypoint(TWO_PI * 1e6), waypoint(TWO_PI * 1e6)], &volts, &[], &exact()).is_err());
+}
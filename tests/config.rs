@@ -0,0 +1,142 @@
+use ionwave::basis::{GaussianBasis, PotentialBasis};
+use ionwave::c2lr::solve_waveform;
+use ionwave::config::Config;
+use ionwave::lsq::LsqOptions;
+use ionwave::types::{IonwaveError, Vec3};
+
+fn demo_path() -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("configs/demo.toml")
+}
+
+#[test]
+fn demo_config_builds_and_solves() {
+    let cfg = Config::load(demo_path()).expect("load");
+    let (model, wps) = cfg.build().expect("build");
+    assert_eq!(model.n_electrodes(), 23);
+    assert_eq!(model.c2lr_pair, Some((0, 1)));
+    assert_eq!(wps.len(), 15);
+    assert_eq!(model.electrodes[4].name, "e4");
+
+    let opts = LsqOptions::default();
+    let v = solve_waveform(&model, &wps, false, &opts).expect("solve");
+    assert_eq!(v.len(), wps.len());
+}
+
+#[test]
+fn toml_and_json_round_trip() {
+    let cfg = Config::load(demo_path()).expect("load");
+    let json = cfg.to_json_string().expect("json");
+    assert_eq!(Config::from_json_str(&json).expect("reparse json"), cfg);
+    let toml = cfg.to_toml_string().expect("toml");
+    assert_eq!(Config::from_toml_str(&toml).expect("reparse toml"), cfg);
+}
+
+fn base(electrode: &str, extra: &str) -> String {
+    format!(r#"
+[trap]
+{extra}
+[trap.rf]
+type = "rf_pseudo"
+kr = 1.0e10
+kz = 1.0e8
+
+[[trap.electrodes]]
+name = "a"
+basis = {{ type = "gaussian", center = {{ x = 0.0, y = 0.0, z = 0.0 }}, sigma = 4.0e-5, scale = 1.0e-3 }}
+
+[[trap.electrodes]]
+{electrode}
+"#)
+}
+
+fn config_error(text: &str) -> String {
+    match Config::from_toml_str(text) {
+        Err(IonwaveError::Config(msg)) => msg,
+        other => panic!("expected config error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn invalid_configs_are_reported() {
+    let good = r#"name = "b"
+v_min = 0.0
+v_max = 20.0
+dac_channel = 7
+basis = { type = "gaussian", center = { x = 0.0, y = 0.0, z = 6.3e-5 }, sigma = 4.0e-5, scale = 1.0e-3, cutoff = 3.0 }"#;
+    let model = Config::from_toml_str(&base(good, "")).expect("good").trap.build().expect("build");
+    assert_eq!(model.electrodes[1].dac_channel, Some(7));
+    assert_eq!(model.electrodes[1].v_max, 20.0);
+
+    let neg_sigma = r#"basis = { type = "gaussian", center = { x = 0.0, y = 0.0, z = 0.0 }, sigma = -1.0, scale = 1.0 }"#;
+    assert!(config_error(&base(neg_sigma, "")).contains("electrode 1: gaussian sigma"));
+
+    let unknown = r#"basis = { type = "fem_grid", file = "x.h5" }"#;
+    assert!(config_error(&base(unknown, "")).contains("fem_grid"));
+
+    let dup = good.replace("\"b\"", "\"a\"");
+    assert!(config_error(&base(&dup, "")).contains("duplicate electrode name a"));
+
+    assert!(config_error(&base(good, "c2lr_pair = [0, 5]")).contains("c2lr pair"));
+
+    let inverted = good.replace("v_max = 20.0", "v_max = -1.0");
+    assert!(config_error(&base(&inverted, "")).contains("limits"));
+
+    let bad_species = base(good, "") + r#"
+[[waypoints]]
+r = { x = 0.0, y = 0.0, z = 0.0 }
+omega_axial = 1.0e7
+axial_dir = { x = 0.0, y = 0.0, z = 1.0 }
+species = "40Xx+"
+"#;
+    assert!(config_error(&bad_species).contains("40Xx+"));
+}
+
+#[test]
+fn background_terms_enter_the_model() {
+    let electrode = r#"basis = { type = "gaussian", center = { x = 0.0, y = 0.0, z = 6.3e-5 }, sigma = 4.0e-5, scale = 1.0e-3 }"#;
+    let plain = Config::from_toml_str(&base(electrode, "")).expect("plain");
+    let text = base(electrode, "") + r#"
+[[trap.background]]
+basis = { type = "stray_field", field = { x = 0.0, y = 20.0, z = -5.0 } }
+
+[[trap.background]]
+volts = 3.0
+basis = { type = "gaussian", center = { x = 0.0, y = 1.0e-4, z = 0.0 }, sigma = 4.0e-5, scale = 1.0e-3 }
+"#;
+    let cfg = Config::from_toml_str(&text).expect("background");
+    let model = cfg.trap.build().expect("build");
+    assert_eq!(model.background.len(), 2);
+    let r = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+    let v = [0.0, 0.0];
+    let patch = GaussianBasis { center: Vec3 { x: 0.0, y: 1.0e-4, z: 0.0 }, sigma: 4.0e-5, scale: 1.0e-3, cutoff: None };
+    let want = Vec3 { x: 0.0, y: -20.0, z: 5.0 } + patch.grad(r) * 3.0;
+    assert!((model.grad_total(r, &v) - want).norm() < 1e-12);
+    assert!((model.hess_total(r, &v).yy - 1.0e10 - 3.0 * patch.hess(r).yy).abs() < 1e-3);
+
+    let plain_model = plain.trap.build().expect("build");
+    assert_ne!(model.geometry_hash(), plain_model.geometry_hash());
+    assert_eq!(model.to_config().expect("spec").background, cfg.trap.background);
+    assert_eq!(Config::from_toml_str(&cfg.to_toml_string().expect("toml")).expect("reparse"), cfg);
+
+    let bad = base(electrode, "") + "[[trap.background]]\nvolts = nan\nbasis = { type = \"stray_field\", field = { x = 0.0, y = 0.0, z = 0.0 } }\n";
+    assert!(config_error(&bad).contains("background 0"));
+}
+
+#[test]
+fn rf_drive_must_obey_laplace_and_match_the_pseudopotential() {
+    let demo = std::fs::read_to_string(demo_path()).unwrap();
+    let (model, wps) = Config::from_toml_str(&demo).unwrap().build().expect("demo");
+    let drive = model.rf_drive.as_ref().unwrap();
+    let k = drive.hess(wps[0].r);
+    assert_eq!(k.xx + k.yy + k.zz, 0.0);
+
+    let build_error = |text: &str| match Config::from_toml_str(text).unwrap().build() {
+        Err(IonwaveError::Config(msg)) => msg,
+        other => panic!("expected config error, got {:?}", other.map(|_| ())),
+    };
+    let curved = demo.replacen("yy = -1182716650.1185615, zz = 0.0", "yy = -1182716650.1185615, zz = 148367125.33797777", 1);
+    assert_ne!(curved, demo);
+    assert!(build_error(&curved).contains("Laplace"));
+    let stronger = demo.replacen("amplitude = 100.0", "amplitude = 110.0", 1);
+    assert!(build_error(&stronger).contains("differs from [trap.rf]"));
+}
@@ -0,0 +1,115 @@
+mod common;
+use common::{tilted_well, Tilt};
+use ionwave::basis::{GaussianBasis, RfPseudo, TrapModel};
+use ionwave::control::{excitation_gradient, optimize_transport, ControlOptions};
+use ionwave::simulate::{simulate, Integrator, SimulationOptions};
+use ionwave::species::IonSpecies;
+use ionwave::spline::{CubicSpline, SplineKind};
+use ionwave::timing::TimedWaveform;
+use ionwave::trajectory::{TransportPath, Trajectory, VelocityProfile};
+use ionwave::types::{Vec3, Waypoint};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+// 20 um along z in `duration`, sudden start and stop
+fn transport(duration: f64, n: usize) -> (TrapModel, Vec<Waypoint>, TimedWaveform) {
+    let omega = TWO_PI * 1e6;
+    let (model, kz) = tilted_well(omega);
+    let path = TransportPath::line(Vec3::ZERO, Vec3 { x: 0.0, y: 0.0, z: 20e-6 }).unwrap();
+    let traj = Trajectory::generate(&path, duration, VelocityProfile::Linear, n, omega, IonSpecies::yb171()).unwrap();
+    let volts = traj.waypoints.iter().map(|wp| vec![-kz * wp.r.z]).collect();
+    (model, traj.waypoints.clone(), TimedWaveform::new(traj.times.clone(), vec![0], volts).unwrap())
+}
+
+#[test]
+fn adjoint_gradient_matches_finite_differences() {
+    // a second, curved electrode so the gradient also runs through the curvature
+    let omega = TWO_PI * 1e6;
+    let kz = omega * omega / IonSpecies::yb171().charge_to_mass();
+    let bump = GaussianBasis { center: Vec3 { x: 0.0, y: 0.0, z: 30e-6 }, sigma: 20e-6, scale: 1e-3, cutoff: None };
+    let model = TrapModel::new(Box::new(RfPseudo { kr: 4.0 * kz, kz }), vec![Box::new(Tilt), Box::new(bump)], None);
+    let (_, wps, timed) = transport(3.3e-6, 7);
+    let volts = timed.volts.iter().enumerate().map(|(i, row)| vec![row[0], 0.05 * (i as f64).sin()]).collect();
+    let timed = TimedWaveform::new(timed.times.clone(), vec![0, 1], volts).unwrap();
+
+    let (quanta, grad) = excitation_gradient(&model, &wps, &timed, 40.0).unwrap();
+    assert!(quanta > 1.0);
+    assert!(grad[0].iter().chain(&grad[6]).all(|g| *g == 0.0));
+    for (j, c) in [(1, 0), (3, 0), (5, 0), (2, 1), (4, 1)] {
+        let h = 1e-4 * timed.volts.iter().map(|r| r[c].abs()).fold(1e-3, f64::max);
+        let shifted = |d: f64| {
+            let mut t = timed.clone();
+            t.volts[j][c] += d;
+            excitation_gradient(&model, &wps, &t, 40.0).unwrap().0
+        };
+        let numeric = (shifted(h) - shifted(-h)) / (2.0 * h);
+        assert!((grad[j][c] - numeric).abs() < 1e-5 * numeric.abs().max(1e-3 * quanta / h), "{} {}: {} vs {}", j, c, grad[j][c], numeric);
+    }
+}
+
+#[test]
+fn cost_is_the_simulated_final_excitation() {
+    let (model, wps, timed) = transport(3.3e-6, 9);
+    let (quanta, _) = excitation_gradient(&model, &wps, &timed, 40.0).unwrap();
+    let opts = SimulationOptions { kind: SplineKind::Natural, ..Default::default() };
+    let sim = simulate(&model, &wps, &timed, &opts).unwrap();
+    assert!((quanta / sim.final_quanta - 1.0).abs() < 1e-9, "{} vs {}", quanta, sim.final_quanta);
+}
+
+#[test]
+fn optimised_transport_leaves_the_ion_cold() {
+    let (model, wps, timed) = transport(3.3e-6, 9);
+    let opts = ControlOptions { steps_per_period: 100.0, ..Default::default() };
+    let result = optimize_transport(&model, &wps, &timed, &opts).unwrap();
+    assert!(result.initial_quanta > 100.0, "{}", result.initial_quanta);
+    assert!(result.final_quanta < 1e-3 * result.initial_quanta, "{} -> {}", result.initial_quanta, result.final_quanta);
+    assert!(result.history.windows(2).all(|w| w[1] <= w[0]));
+    assert_eq!(result.timed.volts[0], timed.volts[0]);
+    assert_eq!(result.timed.volts[8], timed.volts[8]);
+
+    // an independent integration agrees
+    let check = SimulationOptions { integrator: Integrator::Rk45, kind: SplineKind::Natural, ..Default::default() };
+    let sim = simulate(&model, &wps, &result.timed, &check).unwrap();
+    assert!(sim.final_quanta < 1e-2 * result.initial_quanta, "{}", sim.final_quanta);
+}
+
+#[test]
+fn bounds_and_slew_limits_hold() {
+    let (model, wps, timed) = transport(6.6e-6, 13);
+    let v_max = timed.volts.iter().map(|r| r[0].abs()).fold(0.0, f64::max);
+    // cooling the ion overshoots the mean slope, so halfway back binds
+    let free = optimize_transport(&model, &wps, &timed, &ControlOptions::default()).unwrap();
+    let mean_slew = (timed.volts[12][0] - timed.volts[0][0]).abs() / 6.6e-6;
+    assert!(free.max_slew > 1.1 * mean_slew);
+    let slew_limit = 0.5 * (mean_slew + free.max_slew);
+    let opts = ControlOptions { voltage_limit: Some(1.05 * v_max), slew_limit: Some(slew_limit), ..Default::default() };
+    let limited = optimize_transport(&model, &wps, &timed, &opts).unwrap();
+    assert!(limited.timed.volts.iter().all(|r| r[0].abs() <= 1.05 * v_max + 1e-15));
+    assert!(limited.max_slew <= slew_limit * (1.0 + 1e-9), "{} vs {}", limited.max_slew, slew_limit);
+    assert!(limited.max_slew > 0.99 * slew_limit);
+    assert!(limited.final_quanta < 0.1 * limited.initial_quanta, "{} -> {}", limited.initial_quanta, limited.final_quanta);
+    assert!(limited.final_quanta > free.final_quanta);
+
+    // no waveform between the held rows is slower than the straight ramp
+    for s in [0.0, 0.9 * mean_slew] {
+        assert!(optimize_transport(&model, &wps, &timed, &ControlOptions { slew_limit: Some(s), ..Default::default() }).is_err());
+    }
+}
+
+#[test]
+fn voltage_limits_hold_between_the_knots() {
+    let (model, wps, mut timed) = transport(6.6e-6, 13);
+    let v_max = timed.volts.iter().map(|r| r[0].abs()).fold(0.0, f64::max);
+    // knots zigzagging past the limit: clamped alone, the spline would swing beyond it
+    for (i, row) in timed.volts.iter_mut().enumerate().skip(1).take(11) {
+        row[0] += if i % 2 == 0 { 2.0 * v_max } else { -2.0 * v_max };
+    }
+    let opts = ControlOptions { voltage_limit: Some(v_max), iterations: 0, ..Default::default() };
+    let result = optimize_transport(&model, &wps, &timed, &opts).unwrap();
+    let column: Vec<f64> = result.timed.volts.iter().map(|r| r[0]).collect();
+    let spline = CubicSpline::new(SplineKind::Natural, &timed.times, &column).unwrap();
+    let t1 = timed.times[12];
+    let peak = (0..=10_000).map(|k| spline.eval(t1 * k as f64 / 1e4).abs()).fold(0.0, f64::max);
+    // the integration steps are dense enough that the spline barely slips past between them
+    assert!(peak <= v_max * (1.0 + 1e-3), "{} vs {}", peak, v_max);
+}
@@ -0,0 +1,112 @@
+mod common;
+use common::{build_model, make_waypoints};
+use ionwave::c2lr::solve_channels;
+use ionwave::channels::ChannelWaveform;
+use ionwave::dac::{quantization_report, quantize, DacChannel, DacConfig, DacImageFormat};
+use ionwave::io::write_dac_image;
+use ionwave::lsq::LsqOptions;
+
+#[test]
+fn codes_invert_calibration_and_clip() {
+    let wf = ChannelWaveform {
+        channels: vec![0, 3],
+        channel_volts: vec![vec![0.0, 1.0], vec![-10.0, 12.0]],
+        electrode_volts: vec![vec![0.0, 1.0], vec![-10.0, 12.0]],
+    };
+    let mut dac = DacConfig::new(16, -10.0, 10.0);
+    dac.channels.push(DacChannel::calibrated(3, 2.0, 0.5));
+    let codes = quantize(&wf, &dac).expect("quantize");
+
+    // channel 0 is ideal: 0 V sits half way up the range
+    assert_eq!(codes.codes[0][0], 32768);
+    assert_eq!(codes.codes[1][0], 0);
+    // channel 3 outputs 2 * v + 0.5, so 1 V needs 0.25 V before calibration
+    let k = ((0.25 + 10.0) / dac.converter(3).lsb()).round() as u32;
+    assert_eq!(codes.codes[0][1], k);
+    assert!((codes.channel_volts[0][1] - 1.0).abs() <= dac.converter(3).lsb());
+    // 12 V needs 5.75 V, inside the range; 2 * 10 + 0.5 = 20.5 V is the most
+    assert_eq!(codes.clipped, 0);
+    let wf_hot = ChannelWaveform { channel_volts: vec![vec![11.0, 21.0]], ..wf.clone() };
+    let hot = quantize(&wf_hot, &dac).expect("quantize");
+    assert_eq!(hot.clipped, 2);
+    assert_eq!(hot.codes[0], vec![65535, 65535]);
+    // a sample that is not a number is refused, not clipped to code 0
+    for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let wf_bad = ChannelWaveform { channel_volts: vec![vec![0.0, bad]], ..wf.clone() };
+        assert!(quantize(&wf_bad, &dac).is_err(), "{}", bad);
+    }
+
+    dac.channels.push(DacChannel::calibrated(3, 1.0, 0.0));
+    assert!(quantize(&wf, &dac).is_err());
+    assert!(quantize(&wf, &DacConfig::new(0, -1.0, 1.0)).is_err());
+}
+
+#[test]
+fn memory_images() {
+    let wf = ChannelWaveform { channels: vec![0, 1], channel_volts: vec![vec![-1.0, 1.0]], electrode_volts: vec![] };
+    let dir = std::env::temp_dir().join("ionwave_dac");
+
+    let codes = quantize(&wf, &DacConfig::new(12, -1.0, 1.0)).unwrap();
+    let bin = dir.join("a.bin");
+    write_dac_image(bin.to_str().unwrap(), &codes, DacImageFormat::Binary).unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap(), vec![0x00, 0x00, 0xff, 0x0f]);
+    let hex = dir.join("a.hex");
+    write_dac_image(hex.to_str().unwrap(), &codes, DacImageFormat::Hex).unwrap();
+    assert_eq!(std::fs::read_to_string(&hex).unwrap(), "000 fff\n");
+
+    let wide = quantize(&wf, &DacConfig::new(20, -1.0, 1.0)).unwrap();
+    write_dac_image(bin.to_str().unwrap(), &wide, DacImageFormat::Binary).unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap().len(), 8);
+}
+
+#[test]
+fn channels_keep_their_own_range_and_bits() {
+    // a ±10 V 16 bit board with one 0-20 V 18 bit channel
+    let mut dac = DacConfig::new(16, -10.0, 10.0);
+    dac.channels.push(DacChannel { bits: Some(18), v_min: Some(0.0), v_max: Some(20.0), ..DacChannel::calibrated(1, 1.0, 0.0) });
+    let wf = ChannelWaveform { channels: vec![0, 1], channel_volts: vec![vec![-10.0, 0.0], vec![10.0, 20.0], vec![0.0, 10.0]], electrode_volts: vec![] };
+    let codes = quantize(&wf, &dac).unwrap();
+    assert_eq!(codes.bits, vec![16, 18]);
+    assert_eq!(codes.clipped, 0);
+    assert_eq!(codes.codes[0], vec![0, 0]);
+    assert_eq!(codes.codes[1], vec![65535, 262143]);
+    assert_eq!(codes.codes[2], vec![32768, 131072]);
+    assert_eq!(dac.converter(1).lsb(), 20.0 / 262143.0);
+    // -1 V is below the 0-20 V channel only
+    let low = ChannelWaveform { channel_volts: vec![vec![-1.0, -1.0]], ..wf.clone() };
+    assert_eq!(quantize(&low, &dac).unwrap().clipped, 1);
+
+    let dir = std::env::temp_dir().join("ionwave_dac_mixed");
+    let hex = dir.join("a.hex");
+    write_dac_image(hex.to_str().unwrap(), &codes, DacImageFormat::Hex).unwrap();
+    assert_eq!(std::fs::read_to_string(&hex).unwrap().lines().nth(1).unwrap(), "ffff 3ffff");
+    let bin = dir.join("a.bin");
+    write_dac_image(bin.to_str().unwrap(), &codes, DacImageFormat::Binary).unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap().len(), 3 * (2 + 4));
+
+    dac.channels[0].bits = Some(40);
+    assert!(quantize(&wf, &dac).is_err());
+}
+
+#[test]
+fn quantisation_error_shrinks_with_resolution() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let wps = make_waypoints(7, omega_axial);
+    let wf = solve_channels(&model, &wps, false, &LsqOptions::default()).expect("solve");
+    let span = wf.channel_volts.iter().flatten().map(|v| v.abs()).fold(0.0, f64::max) * 1.1;
+
+    let report = |bits| {
+        let dac = DacConfig::new(bits, -span, span);
+        let codes = quantize(&wf, &dac).unwrap();
+        quantization_report(&model, &wps, &wf, &codes, &dac).unwrap()
+    };
+    let coarse = report(10);
+    let fine = report(18);
+    assert_eq!(coarse.clipped, 0);
+    assert!(coarse.max_voltage_error <= 0.5 * coarse.lsb[0] * (1.0 + 1e-9));
+    assert!(fine.max_voltage_error < coarse.max_voltage_error);
+    assert!(fine.max_axial_error_hz < coarse.max_axial_error_hz);
+    assert!(fine.max_field_error < coarse.max_field_error);
+    assert!(fine.max_axial_error_hz < 1e3, "{:?}", fine);
+}
@@ -0,0 +1,144 @@
+mod common;
+use common::{build_model, build_model_with_cutoff, make_waypoints};
+use ionwave::c2lr::solve_waveform;
+use ionwave::config::Config;
+use ionwave::electrode::Electrode;
+use ionwave::io::write_model_csv;
+use ionwave::lsq::{tikhonov, tikhonov_bounded, LsqOptions};
+use ndarray::{Array1, Array2};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[test]
+fn solver_respects_per_electrode_limits() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let base = build_model(omega_axial);
+    let n = base.n_electrodes();
+
+    // mix of ±10 V and 0-20 V boards, one electrode broken
+    let electrodes: Vec<Electrode> = (0..n).map(|i| {
+        let e = Electrode::new(format!("dc{:02}", i), Some(i));
+        let e = if i % 2 == 0 { e.with_limits(-10.0, 10.0) } else { e.with_limits(0.0, 20.0) };
+        if i == 5 { e.disabled() } else { e }
+    }).collect();
+    let model = base.with_electrodes(electrodes).expect("descriptors");
+
+    let wps = make_waypoints(9, omega_axial);
+    let opts = LsqOptions { lambda: 1e-2, voltage_limit: None, ..Default::default() };
+    let v = solve_waveform(&model, &wps, false, &opts).expect("solve");
+    for vi in &v {
+        model.check_voltages(vi).expect("within limits");
+        assert_eq!(vi[5], 0.0);
+        assert!(vi.iter().skip(1).step_by(2).all(|x| *x >= 0.0));
+    }
+
+    let dir = std::env::temp_dir().join("ionwave_electrode_limits");
+    let path = dir.join("w.csv");
+    write_model_csv(path.to_str().unwrap(), &model, &v).expect("write");
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("dc00,dc01,"));
+
+    // rows outside the limits are refused
+    let mut bad = v.clone();
+    bad[0][1] = -1.0;
+    assert!(write_model_csv(path.to_str().unwrap(), &model, &bad).is_err());
+}
+
+#[test]
+fn descriptor_count_and_limits_are_validated() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let n = build_model(omega_axial).n_electrodes();
+    let short = vec![Electrode::new("a", Some(0)); n - 1];
+    assert!(build_model(omega_axial).with_electrodes(short).is_err());
+    let mut inverted = vec![Electrode::new("a", Some(0)); n];
+    inverted[3] = Electrode::new("b", Some(3)).with_limits(5.0, -5.0);
+    assert!(build_model(omega_axial).with_electrodes(inverted).is_err());
+
+    // a 0-20 V board above a 5 V clamp leaves no legal voltage
+    let mut high: Vec<Electrode> = (0..n).map(|j| Electrode::new(format!("e{}", j), Some(j))).collect();
+    high[2] = high[2].clone().with_limits(8.0, 20.0);
+    let model = build_model(omega_axial).with_electrodes(high).unwrap();
+    let opts = LsqOptions { voltage_limit: Some(5.0), ..Default::default() };
+    assert!(solve_waveform(&model, &make_waypoints(3, omega_axial), false, &opts).is_err());
+
+    let demo = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/configs/demo.toml")).unwrap();
+    let mut cfg = Config::from_toml_str(&demo).unwrap();
+    cfg.solver.voltage_limit = Some(5.0);
+    (cfg.trap.electrodes[2].v_min, cfg.trap.electrodes[2].v_max) = (Some(8.0), Some(20.0));
+    assert!(cfg.validate().is_err());
+    cfg.trap.electrodes[2].enabled = false;
+    assert!(cfg.validate().is_ok());
+}
+
+#[test]
+fn unwired_electrodes_are_held_at_zero_whatever_their_limits() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let base = build_model(omega_axial);
+    let mut electrodes: Vec<Electrode> = (0..base.n_electrodes()).map(|j| Electrode::new(format!("e{}", j), Some(j))).collect();
+    electrodes[4] = Electrode::new("e4", None).with_limits(1.0, 5.0);
+    let model = base.with_electrodes(electrodes).unwrap();
+    let v = solve_waveform(&model, &make_waypoints(5, omega_axial), false, &LsqOptions::default()).expect("solve");
+    for vi in &v {
+        assert_eq!(vi[4], 0.0);
+        model.check_voltages(vi).expect("held at 0 V");
+    }
+    let path = std::env::temp_dir().join("ionwave_unwired_limits.csv");
+    write_model_csv(path.to_str().unwrap(), &model, &v).expect("write");
+    let mut driven = v[0].clone();
+    driven[4] = 2.0;
+    assert!(model.check_voltages(&driven).is_err());
+}
+
+#[test]
+fn electrodes_out_of_reach_sit_nearest_zero_within_their_limits() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let base = build_model_with_cutoff(omega_axial, Some(3.0));
+    let electrodes = (0..base.n_electrodes()).map(|j| Electrode::new(format!("e{}", j), Some(j)).with_limits(0.5, 10.0)).collect();
+    let model = base.with_electrodes(electrodes).unwrap();
+    let wps = make_waypoints(5, omega_axial);
+    let v = solve_waveform(&model, &wps, false, &LsqOptions::default()).expect("solve");
+    for (wp, vi) in wps.iter().zip(&v) {
+        model.check_voltages(vi).expect("within limits");
+        let active = model.active_electrodes(wp.r);
+        assert!(active.len() < vi.len());
+        for (j, x) in vi.iter().enumerate() {
+            if !active.contains(&j) { assert_eq!(*x, 0.5); }
+        }
+    }
+}
+
+#[test]
+fn bounded_solve_matches_brute_force() {
+    let mut rng = StdRng::seed_from_u64(29);
+    let opts = LsqOptions { lambda: 1e-3, voltage_limit: None, ..Default::default() };
+    let (m, n) = (4, 5);
+    for _ in 0..8 {
+        let a = Array2::from_shape_fn((m, n), |_| rng.gen_range(-1.0..1.0));
+        let b = Array1::from_shape_fn(m, |_| rng.gen_range(-2.0..2.0));
+        let lo: Vec<f64> = (0..n).map(|_| rng.gen_range(-0.5..0.0)).collect();
+        let hi: Vec<f64> = (0..n).map(|_| rng.gen_range(0.0..0.5)).collect();
+        let cost = |x: &[f64]| {
+            let r = a.dot(&Array1::from(x.to_vec())) - &b;
+            r.dot(&r) + opts.lambda * x.iter().map(|v| v * v).sum::<f64>()
+        };
+
+        // every unknown at its lower bound, its upper bound or free
+        let mut best = (f64::INFINITY, vec![]);
+        for code in 0..3usize.pow(n as u32) {
+            let state: Vec<usize> = (0..n).map(|j| code / 3usize.pow(j as u32) % 3).collect();
+            let free: Vec<usize> = (0..n).filter(|&j| state[j] == 2).collect();
+            let mut x: Vec<f64> = (0..n).map(|j| [lo[j], hi[j], 0.0][state[j]]).collect();
+            let r = &b - &a.dot(&Array1::from(x.clone()));
+            let xf = tikhonov(&a.select(ndarray::Axis(1), &free), &r, &opts);
+            for (k, &j) in free.iter().enumerate() { x[j] = xf[k]; }
+            if (0..n).all(|j| x[j] >= lo[j] - 1e-12 && x[j] <= hi[j] + 1e-12) && cost(&x) < best.0 {
+                best = (cost(&x), x);
+            }
+        }
+
+        let csr = sprs::CsMat::csr_from_dense(a.view(), 0.0);
+        let x = tikhonov_bounded(&csr, &b, &lo, &hi, &opts);
+        assert!((0..n).all(|j| x[j] >= lo[j] && x[j] <= hi[j]));
+        assert!(cost(&x) <= best.0 * (1.0 + 1e-9), "{} vs {}", cost(&x), best.0);
+        for (p, q) in x.iter().zip(&best.1) { assert!((p - q).abs() < 1e-6, "{:?} vs {:?}", x, best.1); }
+    }
+}
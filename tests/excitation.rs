@@ -0,0 +1,112 @@
+mod common;
+use common::{tilted_well, Tilt};
+use ionwave::basis::{PotentialBasis, RfPseudo, TrapModel};
+use ionwave::excitation::{candidate_profiles, optimize_profile, trajectory_excitation, transport_excitation, ExcitationOptions};
+use ionwave::simulate::{simulate, Integrator, SimulationOptions, HBAR};
+use ionwave::species::IonSpecies;
+use ionwave::spline::SplineKind;
+use ionwave::timing::TimedWaveform;
+use ionwave::trajectory::{TransportPath, Trajectory, VelocityProfile};
+use ionwave::types::{Hess, Vec3};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+fn line(d: f64) -> TransportPath {
+    TransportPath::line(Vec3 { x: 0.0, y: 0.0, z: 0.0 }, Vec3 { x: 0.0, y: 0.0, z: d }).unwrap()
+}
+
+#[test]
+fn sudden_transport_matches_the_analytic_displacement() {
+    let omega = TWO_PI * 1e6;
+    let (d, duration) = (10e-6, 10.25e-6);
+    let traj = Trajectory::generate(&line(d), duration, VelocityProfile::Linear, 101, omega, IonSpecies::yb171()).unwrap();
+    let report = trajectory_excitation(&traj).unwrap();
+    let v = d / duration;
+    let m = IonSpecies::yb171().mass();
+    let expected = 2.0 * m * v * v * (omega * duration / 2.0).sin().powi(2) / (HBAR * omega);
+    assert!((report.mean_phonons / expected - 1.0).abs() < 1e-9, "{} vs {}", report.mean_phonons, expected);
+    assert_eq!(report.samples.len(), 101);
+    assert!((report.samples[100].position - d).abs() < 1e-18);
+    assert!((report.max_axial_hz - 1e6).abs() < 1e-6);
+}
+
+#[test]
+fn waveform_estimate_agrees_with_the_classical_simulation() {
+    let omega = TWO_PI * 1e6;
+    let (model, kz) = tilted_well(omega);
+    let traj = Trajectory::generate(&line(20e-6), 2.6e-6, VelocityProfile::SinSquared, 27, omega, IonSpecies::yb171()).unwrap();
+    let volts = traj.waypoints.iter().map(|wp| vec![-kz * wp.r.z]).collect();
+    let timed = TimedWaveform::new(traj.times.clone(), vec![0], volts).unwrap();
+    let quantum = transport_excitation(&model, &traj.waypoints, &timed, &ExcitationOptions { samples_per_period: 200.0, ..Default::default() }).unwrap();
+    let classical = simulate(&model, &traj.waypoints, &timed, &SimulationOptions { integrator: Integrator::Rk45, ..Default::default() }).unwrap();
+    assert!(quantum.mean_phonons > 1.0);
+    assert!((quantum.mean_phonons / classical.final_quanta - 1.0).abs() < 1e-3,
+        "{} vs {}", quantum.mean_phonons, classical.final_quanta);
+
+    for bad in [0.5, f64::NAN, f64::INFINITY, 1e300] {
+        let opts = ExcitationOptions { samples_per_period: bad, ..Default::default() };
+        assert!(transport_excitation(&model, &traj.waypoints, &timed, &opts).is_err(), "{}", bad);
+    }
+}
+
+/// phi = z² / 2, one volt adds kz = 1 V/m²
+struct Squeeze;
+impl PotentialBasis for Squeeze {
+    fn phi(&self, r: Vec3) -> f64 { 0.5 * r.z * r.z }
+    fn grad(&self, r: Vec3) -> Vec3 { Vec3 { x: 0.0, y: 0.0, z: r.z } }
+    fn hess(&self, _r: Vec3) -> Hess { Hess { xx: 0.0, yy: 0.0, zz: 1.0, xy: 0.0, xz: 0.0, yz: 0.0 } }
+}
+
+#[test]
+fn frequency_changes_along_the_path_are_followed() {
+    // axial frequency rises from 1 to 1.5 MHz while the well moves 10 um
+    let qm = IonSpecies::yb171().charge_to_mass();
+    let k0 = (TWO_PI * 1e6).powi(2) / qm;
+    let model = TrapModel::new(Box::new(RfPseudo { kr: 10.0 * k0, kz: k0 }), vec![Box::new(Tilt), Box::new(Squeeze)], None);
+    let traj = Trajectory::generate(&line(10e-6), 4e-6, VelocityProfile::SinSquared, 41, TWO_PI * 1e6, IonSpecies::yb171()).unwrap();
+    let volts: Vec<Vec<f64>> = traj.times.iter().zip(&traj.waypoints).map(|(t, wp)| {
+        let extra = 1.25 * k0 * t / 4e-6;
+        vec![-(k0 + extra) * wp.r.z, extra]
+    }).collect();
+    let timed = TimedWaveform::new(traj.times.clone(), vec![0, 1], volts).unwrap();
+    let quantum = transport_excitation(&model, &traj.waypoints, &timed, &ExcitationOptions { samples_per_period: 200.0, ..Default::default() }).unwrap();
+    assert!((quantum.min_axial_hz - 1e6).abs() < 1.0 && (quantum.max_axial_hz - 1.5e6).abs() < 1.0);
+
+    let classical = simulate(&model, &traj.waypoints, &timed, &SimulationOptions { integrator: Integrator::Rk45, ..Default::default() }).unwrap();
+    assert!((quantum.mean_phonons / classical.final_quanta - 1.0).abs() < 0.05,
+        "{} vs {}", quantum.mean_phonons, classical.final_quanta);
+}
+
+#[test]
+fn optimized_profile_beats_every_candidate() {
+    let omega = TWO_PI * 1.5e6;
+    let path = line(100e-6);
+    let (best, report) = optimize_profile(&path, 5e-6, omega, IonSpecies::ca40()).unwrap();
+    assert!(report.mean_phonons < 1.0, "{:?}: {}", best, report.mean_phonons);
+    for p in candidate_profiles() {
+        let traj = Trajectory::generate(&path, 5e-6, p, 301, omega, IonSpecies::ca40()).unwrap();
+        assert!(report.mean_phonons <= trajectory_excitation(&traj).unwrap().mean_phonons * 1.01, "{:?}", p);
+    }
+    assert_ne!(best, VelocityProfile::Linear);
+}
+
+#[test]
+fn shortcut_leaves_the_ion_in_the_ground_state() {
+    let omega = TWO_PI * 1e6;
+    // 20 um in 1.3 secular periods
+    let (d, duration) = (20e-6, 1.3e-6);
+    let fast = Trajectory::generate(&line(d), duration, VelocityProfile::MinimumJerk, 2001, omega, IonSpecies::yb171()).unwrap();
+    let shortcut = Trajectory::shortcut(&line(d), duration, 2001, omega, IonSpecies::yb171()).unwrap();
+    let (plain, designed) = (trajectory_excitation(&fast).unwrap(), trajectory_excitation(&shortcut).unwrap());
+    assert!(plain.mean_phonons > 10.0, "{}", plain.mean_phonons);
+    assert!(designed.mean_phonons < 1e-9, "{}", designed.mean_phonons);
+
+    // the classical ion driven by the trap centres agrees
+    let (model, kz) = tilted_well(omega);
+    let shortcut = Trajectory::shortcut(&line(d), duration, 61, omega, IonSpecies::yb171()).unwrap();
+    let volts = shortcut.waypoints.iter().map(|wp| vec![-kz * wp.r.z]).collect();
+    let timed = TimedWaveform::new(shortcut.times.clone(), vec![0], volts).unwrap();
+    let opts = SimulationOptions { integrator: Integrator::Rk45, kind: SplineKind::Natural, ..Default::default() };
+    let sim = simulate(&model, &shortcut.waypoints, &timed, &opts).unwrap();
+    assert!(sim.final_quanta < 1e-2, "{}", sim.final_quanta);
+}
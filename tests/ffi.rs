@@ -0,0 +1,133 @@
+use std::ffi::{c_int, CStr, CString};
+use std::process::Command;
+use ionwave::c2lr::solve_waveform;
+use ionwave::config::Config;
+use ionwave::ffi::*;
+
+const DEMO: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/configs/demo.toml");
+
+fn last_error() -> String {
+    let p = ionwave_last_error();
+    assert!(!p.is_null());
+    unsafe { CStr::from_ptr(p) }.to_str().unwrap().to_string()
+}
+
+#[test]
+fn solve_through_the_c_abi() {
+    let (model, wps) = Config::load(DEMO).unwrap().build().unwrap();
+    let solver = Config::load(DEMO).unwrap().solver;
+    let expected = solve_waveform(&model, &wps, true, &solver).unwrap();
+
+    let path = CString::new(DEMO).unwrap();
+    let mut h: *mut IonwaveModel = std::ptr::null_mut();
+    unsafe {
+        assert_eq!(ionwave_model_load(path.as_ptr(), &mut h), IonwaveStatus::Ok);
+        assert!(ionwave_last_error().is_null());
+        let n_el = ionwave_model_n_electrodes(h);
+        let n_wp = ionwave_model_n_waypoints(h);
+        assert_eq!((n_el, n_wp), (model.n_electrodes(), wps.len()));
+        assert_eq!(CStr::from_ptr(ionwave_model_electrode_name(h, 2)).to_str().unwrap(), model.electrodes[2].name);
+        assert!(ionwave_model_electrode_name(h, n_el).is_null());
+
+        let mut out = vec![0.0; n_el * n_wp];
+        assert_eq!(ionwave_solve(h, true, out.as_mut_ptr(), out.len()), IonwaveStatus::Ok);
+        assert_eq!(out, expected.concat());
+
+        // caller supplied waypoints
+        let positions: Vec<f64> = wps.iter().flat_map(|w| [w.r.x, w.r.y, w.r.z]).collect();
+        let omegas: Vec<f64> = wps.iter().map(|w| w.omega_axial).collect();
+        let mut out2 = vec![0.0; n_el * n_wp];
+        let status = ionwave_solve_waypoints(h, positions.as_ptr(), omegas.as_ptr(), wps.len(),
+            std::ptr::null(), true, out2.as_mut_ptr(), out2.len());
+        assert_eq!(status, IonwaveStatus::Ok);
+        assert_eq!(out2, out);
+
+        assert_eq!(ionwave_solve(h, false, out.as_mut_ptr(), 3), IonwaveStatus::BufferTooSmall);
+        assert!(last_error().contains("needs"));
+        let bad = CString::new("3Xx+").unwrap();
+        let status = ionwave_solve_waypoints(h, positions.as_ptr(), omegas.as_ptr(), 1, bad.as_ptr(), false, out.as_mut_ptr(), out.len());
+        assert_eq!(status, IonwaveStatus::InvalidInput);
+        // caller waypoints are checked like config ones, not solved into NaN
+        let mut nan = omegas.clone();
+        nan[1] = f64::NAN;
+        let status = ionwave_solve_waypoints(h, positions.as_ptr(), nan.as_ptr(), wps.len(), std::ptr::null(), false, out2.as_mut_ptr(), out2.len());
+        assert_eq!(status, IonwaveStatus::InvalidInput);
+        assert!(last_error().contains("waypoint 1") && last_error().contains("omega_axial"), "{}", last_error());
+        assert_eq!(ionwave_solve(std::ptr::null(), false, out.as_mut_ptr(), out.len()), IonwaveStatus::NullPointer);
+        ionwave_model_free(h);
+    }
+}
+
+#[test]
+fn errors_map_to_distinct_codes() {
+    let mut h: *mut IonwaveModel = std::ptr::null_mut();
+    let missing = CString::new("does/not/exist.toml").unwrap();
+    assert_eq!(unsafe { ionwave_model_load(missing.as_ptr(), &mut h) }, IonwaveStatus::Io);
+    assert!(h.is_null());
+
+    let dir = std::env::temp_dir().join("ionwave_ffi");
+    std::fs::create_dir_all(&dir).unwrap();
+    let bad = dir.join("bad.toml");
+    std::fs::write(&bad, "[trap]\nrf = { type = \"rf_pseudo\", kr = 1.0, kz = 1.0 }\nelectrodes = []\nc2lr_pair = [0, 5]\n").unwrap();
+    let bad = CString::new(bad.to_str().unwrap()).unwrap();
+    let status = unsafe { ionwave_model_load(bad.as_ptr(), &mut h) };
+    assert_eq!(status, IonwaveStatus::Config, "{}", last_error());
+
+    let names: Vec<&str> = [IonwaveStatus::Ok as c_int, IonwaveStatus::Solver as c_int, IonwaveStatus::Panic as c_int, 9, -1].iter()
+        .map(|&s| unsafe { CStr::from_ptr(ionwave_status_name(s)) }.to_str().unwrap())
+        .collect();
+    assert_eq!(names, ["ok", "solver failure", "panic", "unknown", "unknown"]);
+}
+
+#[test]
+fn header_declares_every_export() {
+    let header = include_str!("../include/ionwave.h");
+    let source = include_str!("../src/ffi.rs");
+    let names = |text: &str, marker: &str| -> Vec<String> {
+        let mut v: Vec<String> = text.split(marker).skip(1)
+            .filter_map(|s| s.split('(').next())
+            .map(|s| s.trim_start_matches('*').to_string())
+            .filter(|s| s.starts_with("ionwave_") && !s.contains(char::is_whitespace))
+            .collect();
+        v.sort();
+        v
+    };
+    let exports = names(source, "extern \"C\" fn ");
+    assert_eq!(exports.len(), 11);
+    // declarations start at the beginning of a line after the return type
+    let declared: Vec<String> = {
+        let mut v: Vec<String> = header.lines()
+            .filter(|l| !l.starts_with("//") && l.contains("ionwave_") && l.contains('('))
+            .flat_map(|l| names(l, " "))
+            .collect();
+        v.sort();
+        v
+    };
+    assert_eq!(declared, exports);
+}
+
+#[test]
+fn c_program_links_against_the_cdylib() {
+    // the cdylib built alongside this test sits next to it in target/<profile>/deps
+    let lib_dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    if !cfg!(target_os = "linux") || !lib_dir.join("libionwave.so").exists() || Command::new("cc").arg("--version").output().is_err() {
+        eprintln!("skipping: no cc or cdylib");
+        return;
+    }
+    let exe = std::env::temp_dir().join("ionwave_ffi_solve");
+    let root = env!("CARGO_MANIFEST_DIR");
+    let cc = Command::new("cc")
+        .args(["-std=c99", "-Wall", "-Werror", "-o", exe.to_str().unwrap()])
+        .arg(format!("{}/tests/c/solve.c", root))
+        .arg(format!("-I{}/include", root))
+        .arg(format!("-L{}", lib_dir.display()))
+        .arg("-lionwave")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .output().unwrap();
+    assert!(cc.status.success(), "{}", String::from_utf8_lossy(&cc.stderr));
+    let run = Command::new(&exe).arg(DEMO).env("LD_LIBRARY_PATH", &lib_dir).output().unwrap();
+    assert!(run.status.success(), "exit {:?}: {}", run.status.code(), String::from_utf8_lossy(&run.stderr));
+    let stdout = String::from_utf8(run.stdout).unwrap();
+    assert!(stdout.starts_with(env!("CARGO_PKG_VERSION")), "{}", stdout);
+    assert!(stdout.contains(" e1 "));
+}
@@ -0,0 +1,137 @@
+mod common;
+use common::tilted_well;
+use ionwave::config::Config;
+use ionwave::filter::{filtered, predistort, ChannelFilter, FilterConfig, FilterSpec, LinearFilter, PredistortOptions};
+use ionwave::spline::SplineKind;
+use ionwave::timing::{SampleStreams, TimedWaveform};
+use ionwave::trajectory::{TransportPath, Trajectory, VelocityProfile};
+use ionwave::species::IonSpecies;
+use ionwave::types::Vec3;
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+fn rc(cutoff: f64) -> FilterConfig { FilterConfig { default: Some(FilterSpec::Rc { cutoff }), ..Default::default() } }
+
+#[test]
+fn rc_filter_follows_a_held_step() {
+    let (rate, cutoff) = (100e6, 1e6);
+    let step: Vec<f64> = (0..200).map(|k| if k == 0 { 0.0 } else { 1.0 }).collect();
+    let streams = SampleStreams { channels: vec![0], rate, t0: 0.0, samples: vec![step] };
+    let seen = filtered(&streams, &rc(cutoff)).unwrap();
+    // the DAC steps at the first sample, the electrode answers from there
+    for (k, y) in seen.samples[0].iter().enumerate().skip(1) {
+        let t = (k - 1) as f64 / rate;
+        assert!((y - (1.0 - (-TWO_PI * cutoff * t).exp())).abs() < 1e-12, "{}: {}", k, y);
+    }
+    assert_eq!(seen.samples[0][0], 0.0);
+}
+
+#[test]
+fn inversion_is_exact_within_loose_limits() {
+    let y: Vec<f64> = (0..300).map(|k| 1.0 + 0.5 * (k as f64 / 40.0).sin() + if k > 150 { 0.3 } else { 0.0 }).collect();
+    let opts = PredistortOptions::default();
+    for f in [
+        FilterSpec::Rc { cutoff: 2e6 }.discretize(100e6).unwrap(),
+        LinearFilter::new(vec![0.5, 0.3, 0.2], vec![1.0]).unwrap(),
+        LinearFilter::new(vec![0.0, 0.0, 0.2], vec![1.0, -0.9, 0.1]).unwrap(),
+    ] {
+        let x = f.invert(&y, -100.0, 100.0, &opts).unwrap();
+        let seen = f.apply(&x);
+        let delay = f.b.iter().position(|b| *b != 0.0).unwrap();
+        // outputs before the filter's delay cannot be reached
+        for (k, (a, b)) in seen.iter().zip(&y).enumerate().skip(delay + 1) {
+            assert!((a - b).abs() < 1e-9, "{:?} {}: {} vs {}", f, k, a, b);
+        }
+    }
+}
+
+#[test]
+fn filters_that_cannot_be_inverted_are_refused() {
+    let opts = PredistortOptions::default();
+    let ramp: Vec<f64> = (0..60).map(|k| k as f64 / 60.0).collect();
+    // a zero at -0.7 / 0.3: the recursive inverse grows by 7/3 every sample
+    let late = LinearFilter::new(vec![0.3, 0.7], vec![1.0]).unwrap();
+    assert!(late.invert(&ramp, f64::NEG_INFINITY, f64::INFINITY, &opts).is_err());
+    // the same taps the other way round are minimum phase
+    let early = LinearFilter::new(vec![0.7, 0.3], vec![1.0]).unwrap();
+    let x = early.invert(&ramp, f64::NEG_INFINITY, f64::INFINITY, &opts).unwrap();
+    assert!(x.iter().all(|v| v.abs() < 2.0), "{:?}", x);
+
+    // at gain 0.5 the settled 8 V needs 16 V held on the DAC
+    let half = LinearFilter::new(vec![0.5], vec![1.0]).unwrap();
+    assert!(half.invert(&[8.0, 8.0, 8.0], -10.0, 10.0, &opts).is_err());
+    assert_eq!(half.invert(&[4.0, 4.0, 4.0], -10.0, 10.0, &opts).unwrap(), vec![8.0; 3]);
+}
+
+#[test]
+fn limits_bind_and_least_squares_takes_over() {
+    let f = FilterSpec::Rc { cutoff: 1e6 }.discretize(100e6).unwrap();
+    let y: Vec<f64> = (0..300).map(|k| if k < 100 { 0.0 } else { 1.0 }).collect();
+    // the exact inverse kicks far beyond 2 V to step the output
+    let free = f.invert(&y, -1e3, 1e3, &PredistortOptions::default()).unwrap();
+    assert!(free.iter().cloned().fold(0.0, f64::max) > 2.0);
+    let x = f.invert(&y, -2.0, 2.0, &PredistortOptions::default()).unwrap();
+    assert!(x.iter().all(|v| (-2.0..=2.0).contains(v)));
+    let err = |x: &[f64]| f.apply(x).iter().zip(&y).map(|(a, b)| (a - b).powi(2)).sum::<f64>();
+    let clamped: Vec<f64> = free.iter().map(|v| v.clamp(-2.0, 2.0)).collect();
+    assert!(err(&x) < 0.5 * err(&clamped), "{} vs {}", err(&x), err(&clamped));
+    assert!(err(&x) < 0.2 * err(&y), "{} vs {}", err(&x), err(&y));
+}
+
+#[test]
+fn predistorted_transport_puts_the_field_back() {
+    let omega = TWO_PI * 1e6;
+    let (model, kz) = tilted_well(omega);
+    let path = TransportPath::line(Vec3::ZERO, Vec3 { x: 0.0, y: 0.0, z: 20e-6 }).unwrap();
+    let traj = Trajectory::generate(&path, 3e-6, VelocityProfile::Linear, 9, omega, IonSpecies::yb171()).unwrap();
+    let volts = traj.waypoints.iter().map(|wp| vec![-kz * wp.r.z]).collect();
+    let timed = TimedWaveform::new(traj.times.clone(), vec![0], volts).unwrap();
+
+    let opts = PredistortOptions { kind: SplineKind::Natural, ..Default::default() };
+    let filters = FilterConfig { channels: vec![ChannelFilter { channel: 0, filter: FilterSpec::Rc { cutoff: 2e6 } }], default: None };
+    let p = predistort(&model, &traj.waypoints, &timed, 100e6, &filters, &opts).unwrap();
+    assert!(p.uncorrected.max_field_error > 1.0, "{}", p.uncorrected.max_field_error);
+    assert!(p.corrected.max_field_error < 1e-6 * p.uncorrected.max_field_error, "{:?}", p.corrected);
+    // only the sample within the filter's delay is out of reach
+    assert!(p.corrected.max_error_volts < p.uncorrected.max_error_volts);
+    let reachable = p.seen.samples[0].iter().zip(&p.desired.samples[0]).skip(2);
+    assert!(reachable.map(|(a, b)| (a - b).abs()).fold(0.0, f64::max) < 1e-9);
+    assert_eq!(p.limited, 0);
+
+    // the predicted voltages are the filtered DAC streams
+    assert_eq!(filtered(&p.dac, &filters).unwrap().samples, p.seen.samples);
+
+    // a voltage limit just above the waveform leaves no room to lead the filter
+    let v_max = timed.volts.iter().map(|r| r[0].abs()).fold(0.0, f64::max);
+    let tight = PredistortOptions { voltage_limit: Some(1.01 * v_max), ..opts };
+    let q = predistort(&model, &traj.waypoints, &timed, 100e6, &filters, &tight).unwrap();
+    assert!(q.limited > 0);
+    assert!(q.dac.samples[0].iter().all(|v| v.abs() <= 1.01 * v_max));
+    assert!(q.corrected.max_field_error < p.uncorrected.max_field_error);
+}
+
+#[test]
+fn filter_table_loads_and_checks_rates() {
+    let demo = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/configs/demo.toml")).unwrap();
+    let base = demo.split("[filter]").next().unwrap().to_string();
+    let text = base.clone() + "\n[filter]\ndefault = { type = \"rc\", cutoff = 1e6 }\nchannels = [\n    { channel = 2, filter = { type = \"fir\", taps = [0.5, 0.5], sample_rate = 50e6 } },\n]\n";
+    let cfg = Config::from_toml_str(&text).unwrap();
+    let filters = cfg.filter.as_ref().unwrap();
+    assert_eq!(filters.channel(0), Some(&FilterSpec::Rc { cutoff: 1e6 }));
+    assert!(filters.channel(2).unwrap().discretize(50e6).is_ok());
+    assert!(filters.channel(2).unwrap().discretize(100e6).is_err());
+    assert_eq!(Config::from_toml_str(&cfg.to_toml_string().unwrap()).unwrap(), cfg);
+
+    for bad in [
+        "default = { type = \"rc\", cutoff = 0.0 }",
+        "default = { type = \"iir\", b = [1.0], a = [0.0, 1.0], sample_rate = 1e6 }",
+        // a pole at 1.5, one on the unit circle, and one at 2 next to a stable one at 0.2
+        "default = { type = \"iir\", b = [1.0], a = [1.0, -1.5], sample_rate = 1e6 }",
+        "default = { type = \"iir\", b = [1.0], a = [1.0, -1.0], sample_rate = 1e6 }",
+        "default = { type = \"iir\", b = [1.0], a = [1.0, -2.2, 0.4], sample_rate = 1e6 }",
+    ] {
+        assert!(Config::from_toml_str(&(base.clone() + "\n[filter]\n" + bad + "\n")).is_err(), "{}", bad);
+    }
+    let stable = "default = { type = \"iir\", b = [0.0, 0.0, 0.2], a = [1.0, -0.9, 0.1, 0.0], sample_rate = 1e6 }";
+    assert!(Config::from_toml_str(&(base.clone() + "\n[filter]\n" + stable + "\n")).is_ok());
+}
@@ -0,0 +1,79 @@
+mod common;
+use common::{build_model, make_waypoints, freq_along_axis};
+use ionwave::c2lr::solve_channels;
+use ionwave::channels::ChannelMap;
+use ionwave::electrode::Electrode;
+use ionwave::constraints::build_constraints;
+use ionwave::lsq::{tikhonov_bounded, LsqOptions};
+use sprs::CsMat;
+use ionwave::types::Vec3;
+
+#[test]
+fn ganged_electrodes_share_one_channel() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let base = build_model(omega_axial);
+    let n = base.n_electrodes();
+
+    // 13 is shorted to 12, 20 is not wired to any DAC
+    let electrodes: Vec<Electrode> = (0..n).map(|j| match j {
+        13 => Electrode::new("e13", Some(12)),
+        20 => Electrode::new("e20", None),
+        _ => Electrode::new(format!("e{}", j), Some(j)),
+    }).collect();
+    let model = base.with_electrodes(electrodes).expect("descriptors");
+    let map = ChannelMap::from_model(&model).expect("map");
+    assert_eq!(map.n_channels(), n - 2);
+    assert!(map.members.contains(&vec![12, 13]));
+
+    let wps = make_waypoints(9, omega_axial);
+    let opts = LsqOptions { lambda: 1e-2, voltage_limit: Some(5.0), ..Default::default() };
+    let wf = solve_channels(&model, &wps, false, &opts).expect("solve");
+    assert_eq!(wf.channels.len(), map.n_channels());
+    let u = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    for (i, wp) in wps.iter().enumerate() {
+        let v = &wf.electrode_volts[i];
+        assert_eq!(v[12], v[13]);
+        assert_eq!(v[20], 0.0);
+        assert_eq!(wf.channel_volts[i].len(), n - 2);
+        let w = freq_along_axis(model.hess_total(wp.r, v), u, &wp.species);
+        assert!((w - omega_axial).abs() / omega_axial < 0.05);
+    }
+}
+
+#[test]
+fn one_channel_per_electrode_matches_dense_solve() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let wps = make_waypoints(5, omega_axial);
+    let opts = LsqOptions::default();
+    let wf = solve_channels(&model, &wps, true, &opts).expect("channels");
+    assert_eq!(wf.channel_volts, wf.electrode_volts);
+
+    // against the dense per-electrode system, the C2LR pair swapped by hand
+    let (p, q) = model.c2lr_pair.expect("pair");
+    let internal = opts.to_internal(&model.units);
+    let l = internal.voltage_limit.unwrap();
+    for (wp, v) in wps.iter().zip(&wf.electrode_volts) {
+        let (mut a, b) = build_constraints(&model, wp);
+        for mut row in a.rows_mut() { row.swap(p, q); }
+        let n = a.ncols();
+        let x = tikhonov_bounded(&CsMat::csr_from_dense(a.view(), 0.0), &b, &vec![-l; n], &vec![l; n], &internal);
+        for (vi, xi) in v.iter().zip(&x) {
+            assert!((vi - model.units.voltage_to_si(*xi)).abs() < 1e-9, "{} vs {}", vi, xi);
+        }
+    }
+}
+
+#[test]
+fn disjoint_limits_on_a_shared_channel_are_rejected() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let base = build_model(omega_axial);
+    let n = base.n_electrodes();
+    let electrodes: Vec<Electrode> = (0..n).map(|j| match j {
+        0 => Electrode::new("a", Some(0)).with_limits(-10.0, -1.0),
+        1 => Electrode::new("b", Some(0)).with_limits(0.0, 20.0),
+        _ => Electrode::new(format!("e{}", j), Some(j)),
+    }).collect();
+    let model = base.with_electrodes(electrodes).expect("descriptors");
+    assert!(ChannelMap::from_model(&model).is_err());
+}
@@ -1,4 +1,4 @@
-use ionwave::basis::{GaussianBasis, PotentialBasis};
+use ionwave::basis::{GaussianBasis, PotentialBasis, StrayFieldBasis};
 use ionwave::types::{Vec3, Hess};
 
 fn approx_eq(a: f64, b: f64, rtol: f64, atol: f64) -> bool {
@@ -63,6 +63,7 @@ fn gaussian_grad_and_hess_match_finite_differences() {
         center: Vec3 { x: -50e-6, y: 0.0, z: 0.0 },
         sigma: 40e-6,
         scale: 2e-3, // small scale keeps numbers well conditioned
+        cutoff: None,
     };
     // pick a point not exactly at the center
     let r = Vec3 { x: -40e-6, y: 10e-6, z: 12e-6 };
@@ -94,3 +95,21 @@ fn gaussian_grad_and_hess_match_finite_differences() {
     assert!(approx_eq(h_analytic.xz, h_numeric.xz, rtol_h, atol_h), "hess xz mismatch: analytic {} numeric {}", h_analytic.xz, h_numeric.xz);
     assert!(approx_eq(h_analytic.yz, h_numeric.yz, rtol_h, atol_h), "hess yz mismatch: analytic {} numeric {}", h_analytic.yz, h_numeric.yz);
 }
+
+#[test]
+fn stray_field_grad_and_hess_match_finite_differences() {
+    let basis = StrayFieldBasis {
+        field: Vec3 { x: 12.0, y: -3.0, z: 40.0 },
+        gradient: Hess { xx: 2e5, yy: -5e5, zz: 3e5, xy: 1e5, xz: -4e4, yz: 7e4 },
+        origin: Vec3 { x: 0.0, y: 0.0, z: 1e-4 },
+    };
+    let r = Vec3 { x: -40e-6, y: 10e-6, z: 12e-6 };
+    let (g, g_numeric) = (basis.grad(r), num_grad(&basis, r, 1e-7));
+    assert!((g - g_numeric).norm() < 1e-6 * g.norm(), "analytic {:?} numeric {:?}", g, g_numeric);
+    // the field is -∇φ, exactly `field` at the origin
+    assert_eq!(basis.grad(basis.origin) * -1.0, basis.field);
+    let (h, h_numeric) = (basis.hess(r), num_hess(&basis, r, 1e-6));
+    for (a, b) in [(h.xx, h_numeric.xx), (h.yy, h_numeric.yy), (h.zz, h_numeric.zz), (h.xy, h_numeric.xy), (h.xz, h_numeric.xz), (h.yz, h_numeric.yz)] {
+        assert!(approx_eq(a, b, 1e-3, 1.0), "hess mismatch: analytic {} numeric {}", a, b);
+    }
+}
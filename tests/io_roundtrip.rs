@@ -0,0 +1,80 @@
+mod common;
+use common::{build_model, make_waypoints};
+use ionwave::c2lr::solve_waveform;
+use ionwave::io::{read_model_csv, read_waveform, sidecar_path, write_table_csv, write_waveform, WaveformMetadata};
+use ionwave::lsq::LsqOptions;
+
+fn tmp(name: &str) -> String {
+    let dir = std::env::temp_dir().join("ionwave_io_roundtrip");
+    dir.join(name).to_str().unwrap().to_string()
+}
+
+#[test]
+fn waveform_and_metadata_round_trip() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let wps = make_waypoints(5, omega_axial);
+    let opts = LsqOptions::default();
+    let v = solve_waveform(&model, &wps, true, &opts).expect("solve");
+
+    let path = tmp("w.csv");
+    let meta = WaveformMetadata::new(&model, &wps, &opts, true);
+    write_waveform(&path, &model, &v, &meta).expect("write");
+    assert!(sidecar_path(&path).ends_with("w.meta.json"));
+
+    let (back, back_meta) = read_waveform(&path, &model).expect("read");
+    assert_eq!(back, v);
+    assert_eq!(back_meta, meta);
+    assert_eq!(back_meta.crate_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(back_meta.waypoints[3].r, wps[3].r);
+
+    // a different geometry is refused
+    let other = build_model(2.0 * omega_axial);
+    assert_ne!(other.geometry_hash(), model.geometry_hash());
+    assert!(read_waveform(&path, &other).is_err());
+
+    // without its sidecar the csv is only readable unverified
+    std::fs::remove_file(sidecar_path(&path)).unwrap();
+    assert!(read_waveform(&path, &model).is_err());
+    assert_eq!(read_model_csv(&path, &model).unwrap(), v);
+}
+
+#[test]
+fn columns_are_matched_by_name() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let n = model.n_electrodes();
+
+    // reversed column order
+    let header: Vec<String> = (0..n).rev().map(|j| format!("e{}", j)).collect();
+    let row: Vec<f64> = (0..n).rev().map(|j| j as f64).collect();
+    let path = tmp("reversed.csv");
+    write_table_csv(&path, &header, &[row]).unwrap();
+    let back = read_model_csv(&path, &model).expect("read");
+    assert_eq!(back[0], (0..n).map(|j| j as f64).collect::<Vec<_>>());
+
+    let missing = tmp("missing.csv");
+    write_table_csv(&missing, &header[1..], &[vec![0.0; n - 1]]).unwrap();
+    assert!(read_model_csv(&missing, &model).is_err());
+}
+
+#[test]
+fn geometry_hash_follows_the_config() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    assert_eq!(model.geometry_hash(), build_model(omega_axial).geometry_hash());
+    let rebuilt = model.to_config().expect("spec").build().expect("build");
+    assert_eq!(rebuilt.geometry_hash(), model.geometry_hash());
+
+    // infinite limits of either sign are told apart
+    let limited = |v_min: f64, v_max: f64| {
+        let mut electrodes = model.electrodes.clone();
+        electrodes[0] = electrodes[0].clone().with_limits(v_min, v_max);
+        build_model(omega_axial).with_electrodes(electrodes).unwrap().geometry_hash()
+    };
+    let (inf, ninf) = (f64::INFINITY, f64::NEG_INFINITY);
+    assert_eq!(limited(ninf, inf), model.geometry_hash());
+    assert_ne!(limited(ninf, ninf), limited(ninf, inf));
+    assert_ne!(limited(inf, inf), limited(ninf, inf));
+    assert_ne!(limited(-1.0, inf), limited(-1.0, 1.0));
+}
@@ -0,0 +1,130 @@
+mod common;
+use common::{linear_trap, waypoint, RF_FREQUENCY};
+use ionwave::basis::{PotentialBasis, QuadraticBasis, TrapModel};
+use ionwave::dynamics::eigen;
+use ionwave::mathieu::{mathieu_beta, rf_stability};
+use ionwave::simulate::{simulate, Integrator, SimulationOptions};
+use ionwave::species::IonSpecies;
+use ionwave::timing::TimedWaveform;
+use ionwave::types::{Hess, Vec3};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+#[test]
+fn mathieu_exponents_and_stability_edges() {
+    let q: f64 = 0.05;
+    let beta = mathieu_beta(0.0, q).unwrap();
+    assert!((beta / (q * q / 2.0).sqrt() - 1.0).abs() < 2e-3, "{}", beta);
+    assert!((mathieu_beta(0.2, 0.0).unwrap() - 0.2f64.sqrt()).abs() < 1e-9);
+    // the first region ends at q = 0.908 on the q axis
+    assert!(mathieu_beta(0.0, 0.90).is_some());
+    assert!(mathieu_beta(0.0, 0.92).is_none());
+    assert!(mathieu_beta(-0.1, 0.1).is_none());
+    assert!(mathieu_beta(-0.01, 0.3).is_some());
+}
+
+#[test]
+fn eigenvectors_match_eigenvalues() {
+    let h = Hess { xx: 2.0, yy: -1.0, zz: 0.5, xy: 0.3, xz: -0.7, yz: 0.2 };
+    let (vals, vecs) = eigen(h);
+    for (l, v) in vals.iter().zip(vecs) {
+        assert!((v.norm() - 1.0).abs() < 1e-12);
+        assert!((h.apply(v) - v * *l).norm() < 1e-9);
+    }
+}
+
+#[test]
+fn coupled_hessians_give_reference_eigenvalues() {
+    // equal diagonals coupled off it, eigenvalues 1 ± 0.5 and 2
+    let (vals, _) = eigen(Hess { xx: 1.0, yy: 1.0, zz: 2.0, xy: 0.5, xz: 0.0, yz: 0.0 });
+    let mut vals = vals.to_vec();
+    vals.sort_by(f64::total_cmp);
+    for (got, want) in vals.iter().zip([0.5, 1.5, 2.0]) {
+        assert!((got - want).abs() < 1e-12, "{:?}", vals);
+    }
+
+    // a trap-sized Hessian, V/m², diag(-4e8, 1e8, 3e8) turned 30° about z and
+    // then 20° about x, so every pair is coupled
+    let (c1, s1) = (30f64.to_radians().cos(), 30f64.to_radians().sin());
+    let (c2, s2) = (20f64.to_radians().cos(), 20f64.to_radians().sin());
+    let r = [
+        [c1, -s1, 0.0],
+        [c2 * s1, c2 * c1, -s2],
+        [s2 * s1, s2 * c1, c2],
+    ];
+    let d = [-4e8, 1e8, 3e8];
+    let m = |i: usize, j: usize| (0..3).map(|k| r[i][k] * d[k] * r[j][k]).sum::<f64>();
+    let h = Hess { xx: m(0, 0), yy: m(1, 1), zz: m(2, 2), xy: m(0, 1), xz: m(0, 2), yz: m(1, 2) };
+    assert!(h.xy.abs() > 1e7 && h.xz.abs() > 1e7 && h.yz.abs() > 1e7, "{:?}", h);
+    let mut vals = eigen(h).0.to_vec();
+    vals.sort_by(f64::total_cmp);
+    for (got, want) in vals.iter().zip(d) {
+        assert!((got / want - 1.0).abs() < 1e-12, "{:?}", vals);
+    }
+}
+
+/// uniform field along x, one volt gives phi = x
+struct TiltX;
+impl PotentialBasis for TiltX {
+    fn phi(&self, r: Vec3) -> f64 { r.x }
+    fn grad(&self, _r: Vec3) -> Vec3 { Vec3 { x: 1.0, y: 0.0, z: 0.0 } }
+    fn hess(&self, _r: Vec3) -> Hess { Hess::ZERO }
+}
+
+// the linear trap with axial curvature from a dc electrode and a push along x
+fn trap() -> (TrapModel, f64) {
+    linear_trap(vec![Box::new(QuadraticBasis { center: Vec3::ZERO, curvature: Hess { zz: 1.0, ..Hess::ZERO } }), Box::new(TiltX)])
+}
+
+#[test]
+fn stray_fields_show_up_as_micromotion() {
+    let (model, kr) = trap();
+    let omega_rf = TWO_PI * RF_FREQUENCY;
+    let kz = (TWO_PI * 1e6).powi(2) / IonSpecies::yb171().charge_to_mass();
+    let report = rf_stability(&model, &[waypoint(TWO_PI * 1e6), waypoint(TWO_PI * 1e6)], &[vec![kz, 0.0], vec![kz, 20.0]]).unwrap();
+
+    let nulled = &report[0];
+    assert!(nulled.stable && nulled.micromotion_amplitude == 0.0);
+    let qs: Vec<f64> = nulled.modes.iter().map(|m| m.q.abs()).collect();
+    assert!(qs.iter().filter(|q| (**q - 0.3).abs() < 1e-12).count() == 2 && qs.contains(&0.0));
+    // the axial mode is held by the dc alone, a = 4 ω_z² / Ω²
+    let axial = nulled.modes.iter().find(|m| m.q == 0.0).unwrap();
+    assert!((axial.a - 4.0 * (TWO_PI * 1e6 / omega_rf).powi(2)).abs() < 1e-12);
+    assert!((axial.secular_hz - 1e6).abs() < 1.0);
+
+    // a 20 V/m push along x moves the ion off the null by E / kr
+    let pushed = &report[1];
+    let x = 20.0 / kr;
+    assert!((pushed.r.x + x).abs() < 1e-15);
+    assert!((pushed.micromotion_amplitude / (0.15 * x) - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn explicit_rf_motion_has_the_mathieu_secular_frequency() {
+    let (model, _) = trap();
+    let omega_rf = TWO_PI * RF_FREQUENCY;
+    let kz = (TWO_PI * 1e6).powi(2) / IonSpecies::yb171().charge_to_mass();
+    let volts = vec![vec![kz, 0.0], vec![kz, 0.0]];
+    let timed = TimedWaveform::new(vec![0.0, 5e-6], vec![0, 1], volts.clone()).unwrap();
+    let opts = SimulationOptions {
+        integrator: Integrator::Rk45,
+        explicit_rf: true,
+        initial_offset: Vec3 { x: 0.5e-6, y: 0.0, z: 0.0 },
+        ..Default::default()
+    };
+    let sim = simulate(&model, &[waypoint(TWO_PI * 1e6), waypoint(TWO_PI * 1e6)], &timed, &opts).unwrap();
+    // upward zero crossings of x give the secular period
+    let crossings: Vec<f64> = sim.samples.windows(2)
+        .filter(|w| w[0].r.x < 0.0 && w[1].r.x >= 0.0)
+        .map(|w| w[0].t + (w[1].t - w[0].t) * -w[0].r.x / (w[1].r.x - w[0].r.x))
+        .collect();
+    let measured = (crossings.len() - 1) as f64 / (crossings[crossings.len() - 1] - crossings[0]);
+    let radial = rf_stability(&model, &[waypoint(TWO_PI * 1e6)], &volts[..1]).unwrap()[0].modes.iter()
+        .find(|m| m.q != 0.0).unwrap().secular_hz;
+    assert!((measured / radial - 1.0).abs() < 2e-3, "{} vs {}", measured, radial);
+    // the pseudopotential alone would predict q Ω / 2√2
+    let pseudo = 0.3 * omega_rf / (2.0 * 2f64.sqrt()) / TWO_PI;
+    assert!((radial / pseudo - 1.0).abs() < 0.03);
+    // micromotion rides on the secular motion
+    assert!(sim.samples.iter().map(|s| s.r.x.abs()).fold(0.0, f64::max) > 1.1 * 0.5e-6);
+}
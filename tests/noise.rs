@@ -0,0 +1,95 @@
+mod common;
+use common::{tilted_well, waypoint, Tilt};
+use ionwave::basis::{PotentialBasis, QuadraticBasis, RfPseudo, TrapModel};
+use ionwave::config::Config;
+use ionwave::filter::{FilterConfig, FilterSpec};
+use ionwave::noise::{heating_rates, ChannelNoise, NoiseConfig, VoltageNoise};
+use ionwave::simulate::HBAR;
+use ionwave::species::IonSpecies;
+use ionwave::types::{Hess, Vec3};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+// q² S_E / 4 m ħ ω for yb171+
+fn rate(field_psd: f64, omega: f64) -> f64 {
+    let s = IonSpecies::yb171();
+    s.charge().powi(2) * field_psd / (4.0 * s.mass() * HBAR * omega)
+}
+
+#[test]
+fn white_noise_on_a_uniform_field_electrode() {
+    let omega = TWO_PI * 1e6;
+    let (model, _) = tilted_well(omega);
+    // 1 V on the tilt gives 1 V/m, so 10 nV/√Hz is 1e-16 (V/m)²/Hz
+    let noise = NoiseConfig { default: VoltageNoise::white(10e-9), ..Default::default() };
+    let reports = heating_rates(&model, &[waypoint(omega)], &[vec![0.0]], &noise, None).unwrap();
+    let r = &reports[0];
+    assert!((r.axial_hz - 1e6).abs() < 1e-6);
+    assert!((r.field_psd / 1e-16 - 1.0).abs() < 1e-12, "{}", r.field_psd);
+    assert!((r.heating_rate / rate(1e-16, omega) - 1.0).abs() < 1e-12);
+
+    // an RC filter cut off at the axial frequency halves the noise power
+    let rc = FilterConfig { default: Some(FilterSpec::Rc { cutoff: 1e6 }), ..Default::default() };
+    let f = heating_rates(&model, &[waypoint(omega)], &[vec![0.0]], &noise, Some(&rc)).unwrap();
+    assert!((f[0].heating_rate / r.heating_rate - 0.5).abs() < 1e-12);
+    // a two tap average at 4 MHz has |H|² = cos²(π f / fs) = 1/2 at 1 MHz too
+    let fir = FilterConfig { default: Some(FilterSpec::Fir { taps: vec![0.5, 0.5], sample_rate: 4e6 }), ..Default::default() };
+    let g = heating_rates(&model, &[waypoint(omega)], &[vec![0.0]], &noise, Some(&fir)).unwrap();
+    assert!((g[0].heating_rate / r.heating_rate - 0.5).abs() < 1e-12);
+}
+
+#[test]
+fn channels_add_in_power_and_curving_ones_do_not_heat() {
+    let omega = TWO_PI * 1e6;
+    let kz = omega * omega / IonSpecies::yb171().charge_to_mass();
+    // the tilt twice, and a well centred on the ion with no field there
+    let dc: Vec<Box<dyn PotentialBasis>> = vec![
+        Box::new(Tilt),
+        Box::new(Tilt),
+        Box::new(QuadraticBasis { center: Vec3::ZERO, curvature: Hess { zz: 1.0, ..Hess::ZERO } }),
+    ];
+    let model = TrapModel::new(Box::new(RfPseudo { kr: 4.0 * kz, kz: 0.0 }), dc, None);
+    let noise = NoiseConfig {
+        default: VoltageNoise::white(10e-9),
+        channels: vec![ChannelNoise { channel: 1, noise: VoltageNoise::white(30e-9) }],
+    };
+    let r = &heating_rates(&model, &[waypoint(omega)], &[vec![0.0, 0.0, kz]], &noise, None).unwrap()[0];
+    assert!((r.axial_hz - 1e6).abs() < 1e-3, "{}", r.axial_hz);
+    assert!((r.channel_rates[1] / r.channel_rates[0] - 9.0).abs() < 1e-9);
+    assert_eq!(r.channel_rates[2], 0.0);
+    assert!((r.heating_rate - r.channel_rates.iter().sum::<f64>()).abs() < 1e-12 * r.heating_rate);
+    assert!((r.field_psd / 10e-16 - 1.0).abs() < 1e-9, "{}", r.field_psd);
+}
+
+#[test]
+fn spectra_interpolate_log_log_and_flicker_rises_below_the_corner() {
+    let measured = VoltageNoise { spectrum: vec![[1e3, 100e-9], [1e5, 10e-9]], ..Default::default() };
+    assert!((measured.psd(1e4).sqrt() / (10f64.sqrt() * 10e-9) - 1.0).abs() < 1e-12);
+    assert!((measured.psd(1e2) / 1e-14 - 1.0).abs() < 1e-12);
+    assert!((measured.psd(1e7) / 1e-16 - 1.0).abs() < 1e-12);
+
+    let flicker = VoltageNoise { flicker_corner: 1e4, ..VoltageNoise::white(1e-8) };
+    assert!((flicker.psd(1e4) / 2e-16 - 1.0).abs() < 1e-12);
+    assert!((flicker.psd(1e2) / flicker.psd(1e3) - 101.0 / 11.0).abs() < 1e-12);
+}
+
+#[test]
+fn noise_table_loads_and_is_checked() {
+    let base = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/configs/demo.toml")).unwrap();
+    let cfg = Config::from_toml_str(&base).unwrap();
+    let noise = cfg.noise.as_ref().unwrap();
+    assert_eq!(noise.channel(0).density, 60e-9);
+    assert_eq!(noise.channel(5).density, 20e-9);
+    assert_eq!(Config::from_toml_str(&cfg.to_toml_string().unwrap()).unwrap(), cfg);
+
+    for bad in [
+        "[noise]\ndefault = { density = -1e-9 }\n",
+        "[noise]\ndefault = { spectrum = [[1e4, 1e-8], [1e3, 1e-8]] }\n",
+        "[noise]\nchannels = [{ channel = 1, noise = {} }, { channel = 1, noise = {} }]\n",
+        // the filter between DAC and electrodes belongs to [filter]
+        "[noise]\ndefault = { density = 1e-9, filter_cutoff = 50e3 }\n",
+    ] {
+        let text = base.split("[noise]").next().unwrap().to_string() + bad;
+        assert!(Config::from_toml_str(&text).is_err(), "{}", bad);
+    }
+}
@@ -0,0 +1,149 @@
+mod common;
+use common::{build_model, make_waypoints};
+use ionwave::c2lr::solve_waveform;
+use ionwave::constraints::build_constraints;
+use ionwave::io::{read_npy, read_npz, write_constraints_npz, write_npy, write_waveform_npz};
+use ionwave::lsq::LsqOptions;
+use ionwave::npy::{self, NpyArray};
+
+fn tmp(name: &str) -> String {
+    let dir = std::env::temp_dir().join("ionwave_npy");
+    dir.join(name).to_str().unwrap().to_string()
+}
+
+#[test]
+fn npy_header_is_aligned_and_round_trips() {
+    let arr = NpyArray::f64(vec![2, 3], vec![1.0, -2.5, 3.0, 0.0, 1e-300, f64::MAX]).unwrap();
+    let bytes = arr.to_bytes();
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    let hlen = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    assert_eq!((10 + hlen) % 64, 0);
+    assert_eq!(bytes[10 + hlen - 1], b'\n');
+    let header = std::str::from_utf8(&bytes[10..10 + hlen]).unwrap();
+    assert!(header.starts_with("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 3), }"));
+    assert_eq!(bytes.len(), 10 + hlen + 6 * 8);
+
+    let path = tmp("a.npy");
+    write_npy(&path, &arr).unwrap();
+    assert_eq!(read_npy(&path).unwrap(), arr);
+
+    let names = NpyArray::Str { shape: vec![2], data: vec!["e0".into(), "rf_long".into()] };
+    assert_eq!(NpyArray::from_bytes(&names.to_bytes()).unwrap(), names);
+    assert!(NpyArray::f64(vec![2, 2], vec![0.0; 3]).is_err());
+}
+
+#[test]
+fn waveform_and_constraints_npz() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let wps = make_waypoints(4, omega_axial);
+    let v = solve_waveform(&model, &wps, false, &LsqOptions::default()).expect("solve");
+    let n_el = model.n_electrodes();
+
+    let path = tmp("waveform.npz");
+    write_waveform_npz(&path, &model, &wps, &v).expect("write");
+    let members = read_npz(&path).expect("read");
+    let get = |name: &str| members.iter().find(|(n, _)| n == name).map(|(_, a)| a.clone()).unwrap();
+    match get("voltages") {
+        NpyArray::F64 { shape, data } => {
+            assert_eq!(shape, vec![wps.len(), n_el]);
+            assert_eq!(data[2 * n_el + 3], v[2][3]);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    match get("electrodes") {
+        NpyArray::Str { data, .. } => assert_eq!(data[1], model.electrodes[1].name),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(get("r").shape(), &[wps.len(), 3]);
+
+    let path = tmp("constraints.npz");
+    write_constraints_npz(&path, &model, &wps).expect("write");
+    let members = read_npz(&path).expect("read");
+    let (a, b) = build_constraints(&model, &wps[1]);
+    match &members[0] {
+        (name, NpyArray::F64 { shape, data }) => {
+            assert_eq!(name, "A");
+            assert_eq!(shape, &vec![wps.len(), 6, n_el]);
+            let k = 6 * n_el + 4 * n_el + 2;
+            assert_eq!(data[k], a[[4, 2]]);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    match &members[1] {
+        (_, NpyArray::F64 { data, .. }) => assert_eq!(&data[6..12], b.as_slice().unwrap()),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // mismatched shapes are refused
+    assert!(write_waveform_npz(&tmp("bad.npz"), &model, &wps[..2], &v).is_err());
+}
+
+// A member as np.savez stores it through zipfile with force_zip64: version
+// 4.5, both sizes 0xFFFFFFFF in the local header and the real ones in a zip64
+// extra field. The crc is left zero, the reader does not check it.
+fn zip64_member(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+    out.extend_from_slice(&45u16.to_le_bytes());
+    out.extend_from_slice(&[0u8; 6]);                 // flags, stored, time
+    out.extend_from_slice(&0x21u16.to_le_bytes());
+    out.extend_from_slice(&[0u8; 4]);                 // crc
+    out.extend_from_slice(&[0xff; 8]);
+    out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    out.extend_from_slice(&20u16.to_le_bytes());
+    out.extend_from_slice(name.as_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+#[test]
+fn numpy_zip64_members_are_read() {
+    let v = NpyArray::f64(vec![2, 2], vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    let names = NpyArray::Str { shape: vec![2], data: vec!["e0".into(), "e1".into()] };
+    let mut archive = zip64_member("voltages.npy", &v.to_bytes());
+    archive.extend(zip64_member("electrodes.npy", &names.to_bytes()));
+    archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0u8; 18]);
+    let members = npy::read_npz(&archive).expect("read");
+    assert_eq!(members, vec![("voltages".to_string(), v.clone()), ("electrodes".to_string(), names)]);
+
+    // the same arrays round trip through our own archives
+    let mut ours = Vec::new();
+    npy::write_npz(&mut ours, &[("voltages", v.clone())]).unwrap();
+    assert_eq!(npy::read_npz(&ours).unwrap(), vec![("voltages".to_string(), v.clone())]);
+
+    // zip64 sizes with no extra field to hold them
+    let mut broken = zip64_member("voltages.npy", &v.to_bytes());
+    broken[28] = 0;
+    assert!(npy::read_npz(&broken).is_err());
+}
+
+#[test]
+fn malformed_npy_is_an_error_not_a_panic() {
+    let good = NpyArray::f64(vec![3], vec![1.0, 2.0, 3.0]).unwrap().to_bytes();
+    let header = |h: &str| {
+        let mut b = b"\x93NUMPY\x01\x00".to_vec();
+        b.extend_from_slice(&(h.len() as u16).to_le_bytes());
+        b.extend_from_slice(h.as_bytes());
+        b
+    };
+    let cases: Vec<Vec<u8>> = vec![
+        b"".to_vec(),
+        b"\x93NUMPY\x02\x00\x00\x00".to_vec(),
+        b"\x93NUMPY\x01\x00\x00".to_vec(),
+        good[..good.len() - 1].to_vec(),
+        header("{'descr': '<f8', 'fortran_order': False, 'shape': ), }"),
+        header("{'descr': '<f8', 'fortran_order': False, 'shape': 3, }"),
+        header("{'descr': '<f8', 'fortran_order': False, 'shape': (4611686018427387904, 4), }"),
+        header("{'descr': '<U4611686018427387904', 'fortran_order': False, 'shape': (2,), }"),
+        header("{'descr': '<U1', 'fortran_order': False, 'shape': (4611686018427387904,), }"),
+    ];
+    for bytes in cases {
+        assert!(NpyArray::from_bytes(&bytes).is_err(), "{:?}", String::from_utf8_lossy(&bytes));
+    }
+}
@@ -0,0 +1,55 @@
+mod common;
+use common::{build_model, make_waypoints};
+use ionwave::c2lr::solve_waveform;
+use ionwave::io::{diagnostics_frame, scan_parquet, write_parquet};
+use ionwave::lsq::LsqOptions;
+use polars::prelude::*;
+
+#[test]
+fn diagnostics_round_trip_through_parquet() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let wps = make_waypoints(7, omega_axial);
+    let v = solve_waveform(&model, &wps, false, &LsqOptions::default()).expect("solve");
+    let times: Vec<f64> = (0..wps.len()).map(|i| i as f64 * 1e-6).collect();
+
+    let df = diagnostics_frame(&model, &wps, &v, Some(&times)).expect("frame");
+    assert_eq!(df.height(), wps.len());
+    assert_eq!(df.width(), 5 + model.n_electrodes() + 7);
+    assert_eq!(df.column("v_e3").unwrap().f64().unwrap().get(2), Some(v[2][3]));
+
+    let dir = std::env::temp_dir().join("ionwave_parquet");
+    for (k, run) in ["a", "b"].iter().enumerate() {
+        let path = dir.join(format!("run_{}.parquet", run));
+        let t = if k == 0 { Some(&times[..]) } else { None };
+        write_parquet(path.to_str().unwrap(), &model, &wps, &v, t).expect("write");
+    }
+
+    // a glob over both files, filtered and aggregated lazily
+    let pattern = dir.join("run_*.parquet");
+    let out = scan_parquet(pattern.to_str().unwrap()).expect("scan")
+        .filter(col("z").gt(lit(30e-6)))
+        .select([col("axial_hz").max(), col("time").null_count()])
+        .collect()
+        .expect("collect");
+    let max_axial = out.column("axial_hz").unwrap().f64().unwrap().get(0).unwrap();
+    assert!((max_axial - 1.5e6).abs() < 50e3);
+    // only the second run lacks times, 4 of 7 waypoints pass the filter per run
+    assert_eq!(out.column("time").unwrap().u32().unwrap().get(0), Some(4));
+}
+
+#[test]
+fn electrode_columns_cannot_shadow_fixed_ones() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let mut electrodes = build_model(omega_axial).electrodes;
+    electrodes[0].name = "x".to_string();
+    electrodes[1].name = "residual".to_string();
+    let model = build_model(omega_axial).with_electrodes(electrodes).unwrap();
+    let wps = make_waypoints(3, omega_axial);
+    let v = solve_waveform(&model, &wps, false, &LsqOptions::default()).expect("solve");
+
+    let df = diagnostics_frame(&model, &wps, &v, None).expect("frame");
+    assert_eq!(df.width(), 5 + model.n_electrodes() + 7);
+    assert_eq!(df.column("v_x").unwrap().f64().unwrap().get(1), Some(v[1][0]));
+    assert_eq!(df.column("x").unwrap().f64().unwrap().get(1), Some(wps[1].r.x));
+}
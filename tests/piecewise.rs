@@ -0,0 +1,54 @@
+mod common;
+use common::{build_model, make_waypoints};
+use ionwave::c2lr::solve_channels;
+use ionwave::lsq::LsqOptions;
+use ionwave::piecewise::{fit_piecewise, PiecewiseOptions};
+use ionwave::spline::SplineKind;
+use ionwave::timing::{uniform_times, TimedWaveform};
+
+#[test]
+fn segments_meet_the_tolerance() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let wps = make_waypoints(17, omega_axial);
+    let wf = solve_channels(&model, &wps, false, &LsqOptions::default()).expect("solve");
+    let timed = TimedWaveform::from_channels(&wf, uniform_times(wps.len(), 32e-6)).unwrap();
+    let span = wf.channel_volts.iter().flatten().map(|v| v.abs()).fold(0.0, f64::max);
+
+    let fit = |tolerance: f64| {
+        let opts = PiecewiseOptions { tolerance, ..Default::default() };
+        fit_piecewise(&model, &wps, &timed, &opts).expect("fit")
+    };
+    let loose = fit(span * 1e-2);
+    let tight = fit(span * 1e-4);
+    assert!(loose.max_error_volts <= span * 1e-2);
+    assert!(tight.max_error_volts <= span * 1e-4);
+    assert!(loose.n_segments() < tight.n_segments());
+    assert!(tight.n_segments() <= wf.channels.len() * (wps.len() - 1));
+    assert!(tight.max_axial_error_hz < loose.max_axial_error_hz.max(1e-6));
+    assert!(tight.max_axial_error_hz < 1.0, "{}", tight.max_axial_error_hz);
+
+    // knots are whole ticks of the 50 MHz clock and segments tile the duration
+    for ch in &tight.channels {
+        assert_eq!(ch.knots.first(), Some(&0));
+        assert_eq!(ch.knots.last(), Some(&1600));
+        assert_eq!(ch.knots.len(), ch.coeffs.len() + 1);
+    }
+    // playback hits the solved voltages at the waypoints
+    let c = 4;
+    for (i, &t) in timed.times.iter().enumerate() {
+        let v = tight.channels[c].eval(t, 50e6, 24);
+        assert!((v - wf.channel_volts[i][c]).abs() <= span * 1e-4 + 1e-7);
+    }
+}
+
+#[test]
+fn knots_closer_than_a_tick_are_refused() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let wps = make_waypoints(5, omega_axial);
+    let wf = solve_channels(&model, &wps, false, &LsqOptions::default()).expect("solve");
+    let timed = TimedWaveform::from_channels(&wf, uniform_times(wps.len(), 40e-9)).unwrap();
+    let opts = PiecewiseOptions { kind: SplineKind::Natural, ..Default::default() };
+    assert!(fit_piecewise(&model, &wps, &timed, &opts).is_err());
+}
@@ -0,0 +1,53 @@
+# Python binding tests, run with `pytest tests/python` after `maturin develop`.
+
+import os
+
+import numpy as np
+import pytest
+
+import ionwave as iw
+
+DEMO = os.path.join(os.path.dirname(__file__), "..", "..", "configs", "demo.toml")
+
+
+def test_solve_waveform_from_config():
+    model, waypoints = iw.TrapModel.from_config(DEMO)
+    v = iw.solve_waveform(model, waypoints)
+    assert v.shape == (len(waypoints), model.n_electrodes)
+    assert np.all(np.isfinite(v))
+
+    # the solved waveform confines the ion axially at every waypoint
+    for wp, row in zip(waypoints, v):
+        h = model.hess_total(wp.r, row)
+        assert np.all(iw.secular_freqs(h, wp.species) > 0)
+
+
+def test_voltage_limit_none_is_unclamped():
+    model, waypoints = iw.TrapModel.from_config(DEMO)
+    free = iw.solve_waveform(model, waypoints, voltage_limit=None)
+    assert np.array_equal(free, iw.solve_waveform(model, waypoints, voltage_limit=float("inf")))
+    limit = 0.5 * np.abs(free).max()
+    clamped = iw.solve_waveform(model, waypoints, voltage_limit=limit)
+    assert np.abs(clamped).max() <= limit * (1 + 1e-12)
+
+
+def test_electrodes_are_set_from_python():
+    rf = iw.Basis.rf_pseudo(1e10, 1.6e8)
+    dc = [iw.Basis.gaussian((0, 0, z), 50e-6, 1.0) for z in (-100e-6, 0, 100e-6)]
+    electrodes = [iw.Electrode("left", 0), iw.Electrode("mid", 1, v_min=-1.0, v_max=1.0), iw.Electrode("right", 2)]
+    model = iw.TrapModel(rf, dc, electrodes=electrodes)
+    assert model.electrode_names == ["left", "mid", "right"]
+    assert model.electrodes[1].v_max == 1.0
+
+    wp = iw.Waypoint((0, 0, 0), 2 * np.pi * 1e6)
+    v = iw.solve_waveform(model, [wp])
+    assert -1.0 <= v[0, 1] <= 1.0
+
+    # a disabled electrode is held at 0 V
+    model.electrodes = [iw.Electrode("left", 0), iw.Electrode("mid", 1, enabled=False), iw.Electrode("right", 2)]
+    assert iw.solve_waveform(model, [wp])[0, 1] == 0.0
+
+    with pytest.raises(ValueError):
+        model.electrodes = electrodes[:2]
+    with pytest.raises(ValueError):
+        iw.Electrode("bad", 0, v_min=1.0, v_max=-1.0)
@@ -0,0 +1,77 @@
+mod common;
+use common::{build_model, make_waypoints};
+use ionwave::c2lr::solve_channels;
+use ionwave::io::{read_npz, write_streams_npz};
+use ionwave::lsq::LsqOptions;
+use ionwave::npy::NpyArray;
+use ionwave::spline::{CubicSpline, SplineKind};
+use ionwave::timing::{uniform_times, TimedWaveform};
+
+#[test]
+fn natural_spline_is_smooth_and_accurate() {
+    let t: Vec<f64> = (0..12).map(|i| i as f64 * 0.5).collect();
+    let y: Vec<f64> = t.iter().map(|x| x.sin()).collect();
+    let s = CubicSpline::new(SplineKind::Natural, &t, &y).unwrap();
+    for (ti, yi) in t.iter().zip(&y) { assert!((s.eval(*ti) - yi).abs() < 1e-12); }
+    // first and second derivatives agree across interior knots
+    for i in 1..t.len() - 1 {
+        let (l, r) = (s.coeffs[i - 1], s.coeffs[i]);
+        let h = t[i] - t[i - 1];
+        assert!((l[1] + 2.0 * l[2] * h + 3.0 * l[3] * h * h - r[1]).abs() < 1e-10);
+        assert!((2.0 * l[2] + 6.0 * l[3] * h - 2.0 * r[2]).abs() < 1e-9);
+    }
+    assert!(s.coeffs[0][2].abs() < 1e-12);
+    // away from the free ends
+    let err = (0..150).map(|k| 1.5 + k as f64 * 0.02).map(|x| (s.eval(x) - x.sin()).abs()).fold(0.0, f64::max);
+    assert!(err < 1e-3, "{}", err);
+
+    // straight lines are reproduced exactly
+    let lin = CubicSpline::new(SplineKind::Natural, &[0.0, 1.0, 3.0], &[1.0, 3.0, 7.0]).unwrap();
+    assert!((lin.eval(2.2) - 5.4).abs() < 1e-12);
+    assert!((lin.derivative(0.3) - 2.0).abs() < 1e-12);
+    assert!(CubicSpline::new(SplineKind::Natural, &[0.0, 0.0], &[1.0, 2.0]).is_err());
+}
+
+#[test]
+fn monotone_spline_does_not_overshoot_a_step() {
+    let t = [0.0, 1.0, 2.0, 3.0, 4.0];
+    let y = [0.0, 0.0, 1.0, 1.0, 1.0];
+    let mono = CubicSpline::new(SplineKind::Monotone, &t, &y).unwrap();
+    let nat = CubicSpline::new(SplineKind::Natural, &t, &y).unwrap();
+    let xs: Vec<f64> = (0..=400).map(|k| k as f64 * 0.01).collect();
+    let vm = mono.eval_sorted(xs.iter().copied());
+    assert!(vm.iter().all(|v| (-1e-15..=1.0 + 1e-15).contains(v)));
+    assert!(vm.windows(2).all(|w| w[1] >= w[0] - 1e-15));
+    assert!(nat.eval_sorted(xs.iter().copied()).iter().any(|v| *v > 1.0 + 1e-3 || *v < -1e-3));
+}
+
+#[test]
+fn waveform_resampled_to_dac_rate() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let wps = make_waypoints(9, omega_axial);
+    let wf = solve_channels(&model, &wps, false, &LsqOptions::default()).expect("solve");
+
+    let duration = 16e-6;
+    let timed = TimedWaveform::from_channels(&wf, uniform_times(wps.len(), duration)).unwrap();
+    let streams = timed.resample(50e6, SplineKind::Monotone).unwrap();
+    assert_eq!(streams.n_samples(), 801);
+    assert_eq!(streams.channels, wf.channels);
+    // waypoints fall on every 100th sample
+    for (i, row) in wf.channel_volts.iter().enumerate() {
+        for (c, v) in row.iter().enumerate() {
+            assert!((streams.samples[c][100 * i] - v).abs() < 1e-12);
+        }
+    }
+    assert!((streams.time(800) - duration).abs() < 1e-15);
+    assert_eq!(streams.rows()[5].len(), wf.channels.len());
+
+    let path = std::env::temp_dir().join("ionwave_resample").join("s.npz");
+    write_streams_npz(path.to_str().unwrap(), &streams).unwrap();
+    let back = read_npz(path.to_str().unwrap()).unwrap();
+    assert_eq!(back[0].1.shape(), &[wf.channels.len(), 801]);
+    assert_eq!(back[2].1, NpyArray::f64(vec![], vec![50e6]).unwrap());
+
+    assert!(TimedWaveform::from_channels(&wf, vec![0.0; wps.len()]).is_err());
+    assert!(timed.resample(0.0, SplineKind::Natural).is_err());
+}
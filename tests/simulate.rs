@@ -0,0 +1,79 @@
+mod common;
+use common::tilted_well;
+use ionwave::simulate::{simulate, Integrator, SimulationOptions, HBAR};
+use ionwave::species::IonSpecies;
+use ionwave::timing::TimedWaveform;
+use ionwave::trajectory::{TransportPath, Trajectory, VelocityProfile};
+use ionwave::types::{Vec3, Waypoint};
+
+const TWO_PI: f64 = 2.0 * std::f64::consts::PI;
+
+fn waypoints(zs: &[f64], omega: f64) -> Vec<Waypoint> {
+    zs.iter().map(|&z| Waypoint {
+        r: Vec3 { x: 0.0, y: 0.0, z }, omega_axial: omega,
+        axial_dir: Vec3 { x: 0.0, y: 0.0, z: 1.0 }, species: IonSpecies::yb171(),
+    }).collect()
+}
+
+#[test]
+fn constant_velocity_transport_matches_the_analytic_kick() {
+    let omega = TWO_PI * 1e6;
+    let (model, kz) = tilted_well(omega);
+    // 10 um in 10.25 periods, sudden start and stop
+    let (d, duration) = (10e-6, 10.25e-6);
+    let zs: Vec<f64> = (0..5).map(|i| d * i as f64 / 4.0).collect();
+    let times: Vec<f64> = (0..5).map(|i| duration * i as f64 / 4.0).collect();
+    let volts = zs.iter().map(|z| vec![-kz * z]).collect();
+    let timed = TimedWaveform::new(times, vec![0], volts).unwrap();
+    let wps = waypoints(&zs, omega);
+
+    let v = d / duration;
+    let m = IonSpecies::yb171().mass();
+    let expected = 2.0 * m * v * v * (omega * duration / 2.0).sin().powi(2);
+    for (integrator, steps, tol) in [(Integrator::Rk45, 40.0, 1e-5), (Integrator::Verlet, 200.0, 1e-2)] {
+        let opts = SimulationOptions { integrator, steps_per_period: steps, ..Default::default() };
+        let sim = simulate(&model, &wps, &timed, &opts).unwrap();
+        assert!((sim.final_energy / expected - 1.0).abs() < tol, "{:?}: {} vs {}", integrator, sim.final_energy, expected);
+        assert!((sim.energy_gain - sim.final_energy).abs() < 1e-6 * expected);
+        assert!((sim.max_lag / (v / omega) - 1.0).abs() < 1e-2, "{:?} lag {}", integrator, sim.max_lag);
+        assert!((sim.final_quanta - expected / (HBAR * omega)).abs() < tol * sim.final_quanta);
+        assert!((sim.samples.last().unwrap().well.z - d).abs() < 1e-15);
+        assert_eq!(sim.samples.last().unwrap().t, duration);
+    }
+}
+
+#[test]
+fn energy_is_conserved_in_a_static_well() {
+    let omega = TWO_PI * 1.5e6;
+    let (model, _) = tilted_well(omega);
+    let timed = TimedWaveform::new(vec![0.0, 5e-6], vec![0], vec![vec![0.0], vec![0.0]]).unwrap();
+    let wps = waypoints(&[0.0, 0.0], omega);
+    let offset = Vec3 { x: 0.2e-6, y: 0.0, z: 1e-6 };
+    let m = IonSpecies::yb171().mass();
+    let e0 = 0.5 * m * omega * omega * (4.0 * offset.x * offset.x + offset.z * offset.z);
+    for integrator in [Integrator::Verlet, Integrator::Rk45] {
+        let opts = SimulationOptions { integrator, initial_offset: offset, ..Default::default() };
+        let sim = simulate(&model, &wps, &timed, &opts).unwrap();
+        assert!((sim.final_energy / e0 - 1.0).abs() < 1e-3, "{:?}", integrator);
+        assert!(sim.energy_gain.abs() < 1e-3 * e0);
+        assert!(sim.samples.iter().all(|s| (s.energy / e0 - 1.0).abs() < 1e-2));
+    }
+}
+
+#[test]
+fn smooth_profiles_excite_less_than_linear() {
+    let omega = TWO_PI * 1e6;
+    let (model, kz) = tilted_well(omega);
+    let path = TransportPath::line(Vec3 { x: 0.0, y: 0.0, z: 0.0 }, Vec3 { x: 0.0, y: 0.0, z: 20e-6 }).unwrap();
+    let quanta = |profile: VelocityProfile| {
+        let traj = Trajectory::generate(&path, 8.25e-6, profile, 41, omega, IonSpecies::yb171()).unwrap();
+        let volts = traj.waypoints.iter().map(|wp| vec![-kz * wp.r.z]).collect();
+        let timed = TimedWaveform::new(traj.times.clone(), vec![0], volts).unwrap();
+        simulate(&model, &traj.waypoints, &timed, &SimulationOptions::default()).unwrap()
+    };
+    let linear = quanta(VelocityProfile::Linear);
+    let smooth = quanta(VelocityProfile::MinimumJerk);
+    assert!(linear.final_quanta > 100.0);
+    assert!(smooth.final_quanta < 1e-2 * linear.final_quanta, "{} vs {}", smooth.final_quanta, linear.final_quanta);
+    assert!(smooth.max_lag < linear.max_lag);
+}
@@ -0,0 +1,81 @@
+mod common;
+use common::{build_model, build_model_with_cutoff, make_waypoints};
+use ionwave::c2lr::solve_waveform;
+use ionwave::constraints::{build_constraints, build_constraints_local};
+use ionwave::electrode::Electrode;
+use ionwave::lsq::LsqOptions;
+use ionwave::types::Vec3;
+
+#[test]
+fn local_assembly_matches_dense_without_cutoff() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega_axial);
+    let wps = make_waypoints(3, omega_axial);
+
+    let (a, b) = build_constraints(&model, &wps[1]);
+    let local = build_constraints_local(&model, &wps[1], None);
+    assert_eq!(local.cols.len(), model.n_electrodes());
+    let dense = local.a.to_dense();
+    for i in 0..a.nrows() {
+        assert!((b[i] - local.b[i]).abs() <= 1e-12 * b[i].abs().max(1.0));
+        for j in 0..a.ncols() {
+            assert_eq!(a[[i, j]], dense[[i, j]]);
+        }
+    }
+}
+
+#[test]
+fn disabled_electrodes_leave_zero_columns() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let base = build_model(omega_axial);
+    let electrodes = (0..base.n_electrodes()).map(|j| {
+        let e = Electrode::new(format!("e{}", j), Some(j));
+        if j == 4 { e.disabled() } else { e }
+    }).collect();
+    let model = base.with_electrodes(electrodes).unwrap();
+    let wps = make_waypoints(3, omega_axial);
+    let (a, _) = build_constraints(&model, &wps[1]);
+    let local = build_constraints_local(&model, &wps[1], None);
+    assert!(a.column(4).iter().all(|v| *v == 0.0));
+    assert!(!local.cols.contains(&4));
+    assert_eq!(local.a.to_dense(), a.select(ndarray::Axis(1), &local.cols));
+}
+
+#[test]
+fn cutoff_drops_distant_electrodes() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model_with_cutoff(omega_axial, Some(3.0));
+    let wps = make_waypoints(5, omega_axial);
+
+    for wp in &wps {
+        let local = build_constraints_local(&model, wp, None);
+        assert!(local.cols.len() < model.n_electrodes());
+        assert_eq!(local.cols, model.active_electrodes(wp.r));
+        assert_eq!(local.a.cols(), local.cols.len());
+    }
+
+    // inactive electrodes come back as exact zeros
+    let opts = LsqOptions { lambda: 1e-2, voltage_limit: Some(5.0), ..Default::default() };
+    let v = solve_waveform(&model, &wps, false, &opts).expect("solve");
+    for (wp, vi) in wps.iter().zip(&v) {
+        let active = model.active_electrodes(wp.r);
+        for (j, x) in vi.iter().enumerate() {
+            if !active.contains(&j) { assert_eq!(*x, 0.0); }
+        }
+    }
+}
+
+#[test]
+fn cutoff_only_prunes_assembly() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let full = build_model(omega_axial);
+    let cut = build_model_with_cutoff(omega_axial, Some(3.0));
+    let v: Vec<f64> = (0..full.n_electrodes()).map(|j| 0.3 * (j as f64).sin()).collect();
+    // the potential is the same on both sides of every support boundary
+    for k in 0..50 {
+        let r = Vec3 { x: 3e-6, y: -2e-6, z: -200e-6 + 8e-6 * k as f64 };
+        assert_eq!(cut.phi_total(r, &v), full.phi_total(r, &v));
+        assert_eq!(cut.grad_total(r, &v), full.grad_total(r, &v));
+        assert_eq!(cut.hess_total(r, &v), full.hess_total(r, &v));
+    }
+}
@@ -0,0 +1,49 @@
+use ionwave::dynamics::secular_freqs;
+use ionwave::species::{Element, IonSpecies, ATOMIC_MASS_UNIT, ELEMENTARY_CHARGE};
+use ionwave::types::Hess;
+
+#[test]
+fn catalogue_masses_and_charges() {
+    let yb = IonSpecies::yb171();
+    assert!((yb.mass() - 2.8384e-25).abs() < 1e-28);
+    assert_eq!(yb.charge(), ELEMENTARY_CHARGE);
+
+    // every entry parses back from its own name
+    for s in IonSpecies::catalogue() {
+        let back: IonSpecies = s.to_string().parse().expect("round trip");
+        assert_eq!(back, s);
+        assert!((s.mass() / ATOMIC_MASS_UNIT - s.mass_number() as f64).abs() < 0.1);
+    }
+}
+
+#[test]
+fn charge_states_parse_and_adjust_mass() {
+    let sr2: IonSpecies = "88Sr2+".parse().unwrap();
+    assert_eq!(sr2.element(), Element::Sr);
+    assert_eq!(sr2.charge_state(), 2);
+    let anion: IonSpecies = "27Al-".parse().unwrap();
+    assert_eq!(anion.charge_state(), -1);
+    assert!(anion.charge() < 0.0);
+    assert!(anion.mass() > IonSpecies::new(Element::Al, 27, 1).unwrap().mass());
+    assert_eq!(anion.to_string(), "27Al-");
+
+    assert!("40Ca".parse::<IonSpecies>().is_err());
+    assert!("41Ca+".parse::<IonSpecies>().is_err());
+    // a non-ASCII sign or a signed count is rejected, not split mid-character
+    for bad in ["40Ca2\u{2212}", "40Ca\u{207a}", "40Ca-2+", "40Ca+2+"] {
+        assert!(bad.parse::<IonSpecies>().is_err(), "{}", bad);
+    }
+    assert!(IonSpecies::new(Element::Ca, 40, 0).is_err());
+}
+
+#[test]
+fn secular_freqs_respect_charge_sign() {
+    let h = Hess { xx: 1e8, yy: 1e8, zz: 1e8, xy: 0.0, xz: 0.0, yz: 0.0 };
+    let cation = IonSpecies::ca40();
+    let anion = IonSpecies::new(Element::Ca, 40, -1).unwrap();
+    let w = secular_freqs(h, &cation);
+    assert!((w[2] - (cation.charge_to_mass() * 1e8).sqrt()).abs() < 1e-6 * w[2]);
+    // a well for cations is a hill for anions
+    assert_eq!(secular_freqs(h, &anion), [0.0; 3]);
+    assert!(secular_freqs(h.scale(-1.0), &anion)[0] > 0.0);
+}
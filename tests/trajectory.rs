@@ -0,0 +1,129 @@
+mod common;
+use common::build_model;
+use ionwave::c2lr::solve_waveform;
+use ionwave::config::Config;
+use ionwave::lsq::LsqOptions;
+use ionwave::species::IonSpecies;
+use ionwave::trajectory::{erf, TransportPath, Trajectory, VelocityProfile};
+use ionwave::types::Vec3;
+
+const PROFILES: [VelocityProfile; 5] = [
+    VelocityProfile::Linear,
+    VelocityProfile::SinSquared,
+    VelocityProfile::MinimumJerk,
+    VelocityProfile::Erf { width: 0.15 },
+    VelocityProfile::BangBang,
+];
+
+#[test]
+fn profiles_run_from_zero_to_one_with_consistent_derivatives() {
+    for p in PROFILES {
+        assert!(p.eval(0.0).0.abs() < 1e-12, "{:?}", p);
+        assert!((p.eval(1.0).0 - 1.0).abs() < 1e-12, "{:?}", p);
+        let h = 1e-6;
+        for k in 1..20 {
+            let x = k as f64 / 20.0 + 0.013;
+            let (_, v, a) = p.eval(x);
+            let dv = (p.eval(x + h).0 - p.eval(x - h).0) / (2.0 * h);
+            let da = (p.eval(x + h).1 - p.eval(x - h).1) / (2.0 * h);
+            assert!((v - dv).abs() < 1e-6, "{:?} velocity at {}", p, x);
+            assert!((a - da).abs() < 1e-4, "{:?} acceleration at {}", p, x);
+        }
+    }
+    // smooth profiles start and stop at rest
+    for p in [VelocityProfile::SinSquared, VelocityProfile::MinimumJerk, VelocityProfile::BangBang] {
+        assert_eq!(p.eval(0.0).1, 0.0);
+        assert!(p.eval(1.0).1.abs() < 1e-12);
+    }
+    assert!(VelocityProfile::Erf { width: 0.0 }.validate().is_err());
+}
+
+#[test]
+fn erf_matches_reference_values() {
+    for (x, want) in [(0.0, 0.0), (0.5, 0.5204998778130465), (1.0, 0.8427007929497149),
+                      (2.5, 0.999593047982555), (4.0, 0.9999999845827421), (-1.0, -0.8427007929497149)] {
+        assert!((erf(x) - want).abs() < 1e-14, "erf({}) = {}", x, erf(x));
+    }
+}
+
+#[test]
+fn trajectory_follows_the_path() {
+    let start = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+    let end = Vec3 { x: 0.0, y: 0.0, z: 100e-6 };
+    let path = TransportPath::line(start, end).unwrap();
+    let duration = 20e-6;
+    let omega = 2.0 * std::f64::consts::PI * 1.5e6;
+    let t = Trajectory::generate(&path, duration, VelocityProfile::MinimumJerk, 201, omega, IonSpecies::ca40()).unwrap();
+    assert_eq!(t.times.len(), 201);
+    assert_eq!(t.duration(), duration);
+    assert_eq!(t.waypoints[0].r, start);
+    assert!((t.waypoints[200].r - end).norm() < 1e-18);
+    assert_eq!(t.waypoints[7].axial_dir, Vec3 { x: 0.0, y: 0.0, z: 1.0 });
+    // peak speed of a minimum jerk move is 15/8 of the mean
+    assert!((t.max_speed() - 1.875 * 100e-6 / duration).abs() < 1e-9);
+    assert!(t.velocity[0].norm() == 0.0 && t.acceleration[0].norm() == 0.0);
+    // speed integrates to the path length
+    let dt = duration / 200.0;
+    let travelled: f64 = t.velocity.windows(2).map(|w| 0.5 * (w[0].z + w[1].z) * dt).sum();
+    assert!((travelled - 100e-6).abs() < 1e-10);
+
+    // corner of an L shaped path turns the axis
+    let corner = Vec3 { x: 50e-6, y: 0.0, z: 0.0 };
+    let bent = TransportPath::new(vec![start, corner, Vec3 { x: 50e-6, y: 0.0, z: 50e-6 }]).unwrap();
+    let t = Trajectory::generate(&bent, duration, VelocityProfile::Linear, 5, omega, IonSpecies::ca40()).unwrap();
+    assert_eq!(t.waypoints[1].axial_dir, Vec3 { x: 1.0, y: 0.0, z: 0.0 });
+    assert_eq!(t.waypoints[3].axial_dir, Vec3 { x: 0.0, y: 0.0, z: 1.0 });
+    assert!(TransportPath::new(vec![start, start]).is_err());
+}
+
+#[test]
+fn profiles_switch_without_new_loops() {
+    let omega = 2.0 * std::f64::consts::PI * 1.5e6;
+    let model = build_model(omega);
+    let path = TransportPath::line(Vec3 { x: 0.0, y: 0.0, z: 0.0 }, Vec3 { x: 0.0, y: 0.0, z: 63e-6 }).unwrap();
+    for p in PROFILES {
+        let t = Trajectory::generate(&path, 10e-6, p, 9, omega, IonSpecies::yb171()).unwrap();
+        let v = solve_waveform(&model, &t.waypoints, false, &LsqOptions::default()).expect("solve");
+        assert_eq!(v.len(), 9);
+    }
+
+    // the same profiles from a config line
+    let text = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/configs/demo.toml")).unwrap()
+        .replace("species = \"171Yb+\"\n", "species = \"171Yb+\"\nprofile = { type = \"erf\", width = 0.2 }\n");
+    let cfg = Config::from_toml_str(&text).expect("config");
+    let wps = cfg.all_waypoints();
+    let dz: Vec<f64> = wps.windows(2).map(|w| w[1].r.z - w[0].r.z).collect();
+    assert!(dz[7] > 2.0 * dz[0]);
+    assert!((dz[0] - dz[13]).abs() < 1e-15);
+    assert_eq!(Config::from_toml_str(&cfg.to_toml_string().unwrap()).unwrap(), cfg);
+    assert!(Config::from_toml_str(&text.replace("width = 0.2", "width = -1.0")).is_err());
+}
+
+#[test]
+fn shortcut_trap_centre_leads_the_ion() {
+    let end = Vec3 { x: 0.0, y: 0.0, z: 30e-6 };
+    let path = TransportPath::line(Vec3 { x: 0.0, y: 0.0, z: 0.0 }, end).unwrap();
+    let omega = 2.0 * std::f64::consts::PI * 1e6;
+    let duration = 1.5e-6;
+    let t = Trajectory::shortcut(&path, duration, 101, omega, IonSpecies::yb171()).unwrap();
+    assert_eq!(t.waypoints[0].r, Vec3 { x: 0.0, y: 0.0, z: 0.0 });
+    assert!((t.waypoints[100].r - end).norm() < 1e-18);
+    assert!(t.velocity[0].norm() == 0.0 && t.acceleration[100].norm() == 0.0);
+    // ion and trap meet half way, where the ion stops accelerating
+    assert!((t.waypoints[50].r.z - 15e-6).abs() < 1e-18);
+    // the trap is ahead of the ion in the first half and behind it in the second
+    let dt = duration / 100.0;
+    let mut ion = 0.0;
+    for k in 1..100 {
+        ion += 0.5 * (t.velocity[k - 1].z + t.velocity[k].z) * dt;
+        let lead = t.waypoints[k].r.z - ion;
+        assert!((lead - t.acceleration[k].z / (omega * omega)).abs() < 1e-2 * 30e-6, "{} {}", k, lead);
+    }
+    // peak of 420 τ²(1-τ)²(1-2τ) is at τ = (1 - 1/√5)/2
+    let x: f64 = (1.0 - 1.0 / 5f64.sqrt()) / 2.0;
+    let peak = 420.0 * x * x * (1.0 - x).powi(2) * (1.0 - 2.0 * x) * 30e-6 / (omega * duration).powi(2);
+    assert!((t.max_lead() / peak - 1.0).abs() < 1e-3, "{} {}", t.max_lead(), peak);
+    // the centre starts at rest too
+    assert!((t.waypoints[1].r.z - t.waypoints[0].r.z) < 1e-3 * 30e-6);
+    assert!(Trajectory::shortcut(&path, duration, 101, 0.0, IonSpecies::yb171()).is_err());
+}
@@ -0,0 +1,63 @@
+mod common;
+use common::{build_model, make_waypoints, freq_along_axis};
+use ionwave::c2lr::solve_waveform;
+use ionwave::constraints::build_constraints;
+use ionwave::electrode::Electrode;
+use ionwave::lsq::LsqOptions;
+use ionwave::units::Units;
+use ionwave::types::Vec3;
+
+#[test]
+fn units_rescale_the_system_as_a_whole() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let wps = make_waypoints(3, omega_axial);
+
+    let si = build_model(omega_axial).with_units(Units::si());
+    let trap = build_model(omega_axial).with_units(Units::trap());
+    let (a_si, b_si) = build_constraints(&si, &wps[2]);
+    let (a_tr, b_tr) = build_constraints(&trap, &wps[2]);
+    // weights are quoted at a fixed length, so rows keep their relative scale
+    let c = b_si[4] / b_tr[4];
+    assert!((c / 1e6 - 1.0).abs() < 1e-12, "{}", c);
+    for (x, y) in a_si.iter().chain(b_si.iter()).zip(a_tr.iter().chain(b_tr.iter())) {
+        assert!((x - c * y).abs() <= 1e-12 * x.abs(), "{} vs {}", x, c * y);
+    }
+}
+
+#[test]
+fn solutions_are_returned_in_si() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let wps = make_waypoints(9, omega_axial);
+    let opts = LsqOptions { lambda: 1e-2, voltage_limit: Some(5.0), ..Default::default() };
+    let u = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+
+    // neither the length nor a millivolt voltage unit may change the clamp or the physics
+    let solve = |units: Units| solve_waveform(&build_model(omega_axial).with_units(units), &wps, false, &opts).expect("solve");
+    let reference = solve(Units::trap());
+    for (wp, vi) in wps.iter().zip(&reference) {
+        assert!(vi.iter().all(|x| x.abs() <= 5.0 + 1e-12));
+        let f = freq_along_axis(build_model(omega_axial).hess_total(wp.r, vi), u, &wp.species);
+        assert!((f - omega_axial).abs() / omega_axial < 0.05, "axial {} rad/s", f);
+    }
+    for units in [Units::si(), Units::new(1e-6, 1e-3, 1e6), Units::new(1e-5, 10.0, 1.0)] {
+        for (a, b) in solve(units).iter().zip(&reference) {
+            for (x, y) in a.iter().zip(b) {
+                assert!((x - y).abs() <= 1e-9 * y.abs().max(1e-3), "{:?}: {} vs {}", units, x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn bound_voltages_come_back_exactly_on_the_bound() {
+    let omega_axial = 2.0 * std::f64::consts::PI * 1.5e6;
+    let wps = make_waypoints(9, omega_axial);
+    let base = build_model(omega_axial);
+    let electrodes = (0..base.n_electrodes()).map(|j| Electrode::new(format!("e{}", j), Some(j)).with_limits(-2.9e-3, 2.9e-3)).collect();
+    // the waveform needs 4 mV, and 2.9 mV comes back from 0.3 V units a bit larger
+    let model = base.with_electrodes(electrodes).unwrap().with_units(Units::new(1e-6, 0.3, 1e7));
+    let opts = LsqOptions { voltage_limit: None, ..Default::default() };
+    let v = solve_waveform(&model, &wps, false, &opts).expect("solve");
+    assert!(v.iter().flatten().any(|x| x.abs() == 2.9e-3), "no bound is active");
+    for vi in &v { model.check_voltages(vi).expect("within limits"); }
+}